@@ -170,13 +170,236 @@ QUERY search_memory_v2(
 
     RETURN matches
 
-// Delete both the canonical memory node and its vector chunk
+// Delete a memory by the chunk_id its write returned: the canonical MemoryEntry, its vector
+// chunk, and the per-write topic/project/agent/artifact nodes, edges included.
+// `matched` is 0 when no chunk carries that id.
 QUERY delete_memory_v2(
-    memory_id: ID,
     chunk_id: String
 ) =>
+    matched <- V<MemoryChunk>::WHERE(_::{chunk_id}::EQ(chunk_id))::COUNT
+    memory_entry <- V<MemoryChunk>::WHERE(_::{chunk_id}::EQ(chunk_id))::Out<Chunk_of_memory>
+    DROP memory_entry::Out<Relates_to_topic_v2>
+    DROP memory_entry::Out<Part_of_project_v2>
+    DROP memory_entry::Out<Recorded_by>
+    DROP memory_entry::Out<References_artifact_v2>
+    DROP memory_entry::Out<Has_perspective>
+    DROP memory_entry::In<Produced_memory>
     DROP V<MemoryChunk>::WHERE(_::{chunk_id}::EQ(chunk_id))::OutE<Chunk_of_memory>
     DROP V<MemoryChunk>::WHERE(_::{chunk_id}::EQ(chunk_id))
-    DROP N<MemoryEntry>::WHERE(_::{id}::EQ(memory_id))
+    DROP memory_entry
 
-    RETURN "Deleted memory entry and chunk"
\ No newline at end of file
+    RETURN { matched: matched }
+
+// Canonical memory node for a chunk_id (edits read the stored record through this),
+// with the perspectives and tool calls attached to it.
+QUERY get_memory_v2(
+    chunk_id: String
+) =>
+    memory_entry <- V<MemoryChunk>::WHERE(_::{chunk_id}::EQ(chunk_id))::Out<Chunk_of_memory>
+    perspectives <- memory_entry::Out<Has_perspective>
+    tool_calls <- memory_entry::In<Produced_memory>
+
+    RETURN { memory_entry: memory_entry, perspectives: perspectives, tool_calls: tool_calls }
+
+// Graph context for a search hit: the perspectives and tool calls attached to its memory.
+QUERY memory_context_v2(
+    chunk_id: String
+) =>
+    memory_entry <- V<MemoryChunk>::WHERE(_::{chunk_id}::EQ(chunk_id))::Out<Chunk_of_memory>
+    perspectives <- memory_entry::Out<Has_perspective>
+    tool_calls <- memory_entry::In<Produced_memory>
+
+    RETURN { perspectives: perspectives, tool_calls: tool_calls }
+
+// Attach one specialist/stage contribution to a stored memory.
+QUERY write_perspective_v2(
+    memory: ID,
+    role: String,
+    summary: String,
+    body: String,
+    risks: String,
+    decisions: String,
+    actions: String,
+    metadata: String
+) =>
+    perspective <- AddN<PerspectiveView>({
+        role: role,
+        summary: summary,
+        body: body,
+        risks: risks,
+        decisions: decisions,
+        actions: actions,
+        metadata: metadata,
+    })
+
+    memory_entry <- N<MemoryEntry>(memory)
+    perspective_edge <- AddE<Has_perspective>::From(memory_entry)::To(perspective)
+
+    RETURN { perspective: perspective }
+
+// Record one tool invocation that fed a stored memory.
+QUERY write_tool_call_v2(
+    memory: ID,
+    tool_call_id: String,
+    tool_name: String,
+    args_json: String,
+    result_summary: String,
+    created_at: Date,
+    metadata: String
+) =>
+    tool_call <- AddN<ToolCall>({
+        tool_call_id: tool_call_id,
+        tool_name: tool_name,
+        args_json: args_json,
+        result_summary: result_summary,
+        created_at: created_at,
+        metadata: metadata,
+    })
+
+    memory_entry <- N<MemoryEntry>(memory)
+    tool_call_edge <- AddE<Produced_memory>::From(tool_call)::To(memory_entry)
+
+    RETURN { tool_call: tool_call }
+
+// Edit a memory in place: the MemoryEntry keeps its id and its agent/artifact/perspective/tool-call edges,
+// the vector chunk is re-embedded under the same chunk_id, and the per-write topic/project
+// nodes are dropped and re-added with the new values.
+QUERY update_memory_v2(
+    vector: [F64],
+    agent_name: String,
+    topic: String,
+    project: String,
+    summary: String,
+    full_content: String,
+    timestamp: Date,
+    confidence: F32,
+    open_questions: [String],
+    metadata: String,
+    payload_hash: String,
+    chunk_id: String,
+    artifact_id: String,
+    conversation_id: String
+) =>
+    memory_entry <- V<MemoryChunk>::WHERE(_::{chunk_id}::EQ(chunk_id))::Out<Chunk_of_memory>
+    updated_entry <- memory_entry::UPDATE({
+        topic: topic,
+        project: project,
+        summary: summary,
+        full_content: full_content,
+        confidence: confidence,
+        open_questions: open_questions,
+        metadata: metadata,
+        conversation_id: conversation_id,
+    })
+
+    DROP V<MemoryChunk>::WHERE(_::{chunk_id}::EQ(chunk_id))::OutE<Chunk_of_memory>
+    DROP V<MemoryChunk>::WHERE(_::{chunk_id}::EQ(chunk_id))
+    memory_chunk <- AddV<MemoryChunk>(vector, {
+        agent_name: agent_name,
+        topic: topic,
+        project: project,
+        summary: summary,
+        timestamp: timestamp,
+        open_questions: open_questions,
+        metadata: metadata,
+        payload_hash: payload_hash,
+        chunk_id: chunk_id,
+        artifact_id: artifact_id,
+    })
+    chunk_edge <- AddE<Chunk_of_memory>::From(memory_chunk)::To(memory_entry)
+
+    DROP memory_entry::Out<Relates_to_topic_v2>
+    topic_node <- AddN<Topic>({
+        name: topic,
+        metadata: metadata,
+    })
+    topic_edge <- AddE<Relates_to_topic_v2>::From(memory_entry)::To(topic_node)
+
+    DROP memory_entry::Out<Part_of_project_v2>
+    project_node <- AddN<Project>({
+        name: project,
+        metadata: metadata,
+    })
+    project_edge <- AddE<Part_of_project_v2>::From(memory_entry)::To(project_node)
+
+    RETURN { memory_entry: updated_entry, memory_chunk: memory_chunk }
+
+// Open a conversation thread node; clients cache the returned id per conversation_id.
+QUERY write_conversation_v2(
+    conversation_id: String,
+    title: String,
+    metadata: String
+) =>
+    conversation <- AddN<Conversation>({
+        conversation_id: conversation_id,
+        title: title,
+        metadata: metadata,
+    })
+
+    RETURN { conversation: conversation }
+
+// Append a message to an existing conversation thread.
+QUERY write_message_v2(
+    conversation: ID,
+    message_id: String,
+    conversation_id: String,
+    role: String,
+    content: String,
+    created_at: Date,
+    reply_to: String,
+    metadata: String
+) =>
+    message <- AddN<Message>({
+        message_id: message_id,
+        conversation_id: conversation_id,
+        role: role,
+        content: content,
+        created_at: created_at,
+        reply_to: reply_to,
+        metadata: metadata,
+    })
+
+    thread <- N<Conversation>(conversation)
+    thread_edge <- AddE<In_thread>::From(message)::To(thread)
+    message_edge <- AddE<Has_message>::From(thread)::To(message)
+
+    RETURN { message: message }
+
+// Thread a reply onto the message it answers.
+QUERY link_reply_v2(
+    message: ID,
+    parent: ID
+) =>
+    reply <- N<Message>(message)
+    parent_message <- N<Message>(parent)
+    reply_edge <- AddE<Replies_to>::From(reply)::To(parent_message)
+
+    RETURN reply_edge
+
+// Append one token-usage event; read back per agent/model for cost reports.
+QUERY write_usage_event_v1(
+    request_id: String,
+    agent_name: String,
+    operator_id: String,
+    operation: String,
+    model: String,
+    prompt_tokens: I64,
+    completion_tokens: I64,
+    tokens_consumed: I64,
+    estimated: Boolean,
+    timestamp: Date
+) =>
+    event <- AddN<UsageEvent>({
+        request_id: request_id,
+        agent_name: agent_name,
+        operator_id: operator_id,
+        operation: operation,
+        model: model,
+        prompt_tokens: prompt_tokens,
+        completion_tokens: completion_tokens,
+        tokens_consumed: tokens_consumed,
+        estimated: estimated,
+        timestamp: timestamp,
+    })
+
+    RETURN { event: event }
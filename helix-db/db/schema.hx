@@ -75,6 +75,20 @@ N::ToolCall {
     metadata: String,
 }
 
+// Token usage for one LLM or embedding call, attributed to a request and agent.
+N::UsageEvent {
+    request_id: String,
+    agent_name: String,
+    operator_id: String,
+    operation: String,
+    model: String,
+    prompt_tokens: I64,
+    completion_tokens: I64,
+    tokens_consumed: I64,
+    estimated: Boolean,
+    timestamp: Date,
+}
+
 N::PerspectiveView {
     role: String,
     summary: String,
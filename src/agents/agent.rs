@@ -1,19 +1,47 @@
 // Front-desk guidance: agents/agent_readme.md (prompt/RAG/tool flow, save/forget knobs)
+use std::sync::Arc;
+use std::time::Duration;
+
 use async_trait::async_trait;
 use chrono::Utc;
+use serde::Deserialize;
 use serde_json;
 use serde_json::{Map, Value};
 use tracing::{info, instrument, warn};
 
-use crate::llm_client::SharedLlmClient;
+use crate::llm_client::{
+    chat_json, chat_with_sink, parse_json_reply, ChatMessage, JsonReply, SharedLlmClient,
+    TokenSink, ToolCall,
+};
 use crate::rag::topic_registry::SharedTopicRegistry;
 use crate::rag::{
     MemoryDeleteRequest, MemoryFilters, MemoryQuery, MemoryRecord, MemoryRequest,
-    MemoryWriteRequest, SharedRagAgent,
+    MemoryWriteRequest, SharedRagAgent, ToolCallRecord,
 };
+use crate::usage::estimate_tokens;
 
+use super::memory::MemoryAgent;
+use super::pending::{Pending, PendingStore};
+use super::prompt::{truncate_to_tokens, PromptBudget, PromptBuilder};
+use super::taxonomy::TopicClassifier;
+use super::tools::{MemorySearchTool, ToolRegistry};
 use super::traits::{AgentBehavior, AgentRequest, AgentResponse};
 
+/// Upper bound on model round-trips that may request tools before a final answer is forced.
+const MAX_TOOL_STEPS: usize = 3;
+/// Below this much room a tool result is replaced by `TOOL_OUTPUT_OMITTED` instead of cut.
+const MIN_TOOL_OUTPUT_TOKENS: usize = 32;
+const TOOL_OUTPUT_OMITTED: &str = "[tool output omitted: prompt budget exhausted]";
+/// How long a save awaiting "save it" / "just check" stays open.
+pub const DEFAULT_CONFIRMATION_TTL: Duration = Duration::from_secs(300);
+/// Topic for saves with no `topic=`, no tags and no taxonomy match.
+const DEFAULT_TOPIC: &str = "personal.note";
+const DEFAULT_TOPIC_SOURCE: &str = "default";
+/// Words allowed around a confirmation without turning it into a new request.
+const CONFIRMATION_FILLER: &[&str] = &[
+    "please", "thanks", "thank", "you", "with", "as", "and", "it", "now", "go", "ahead",
+];
+
 #[derive(Debug, Clone)]
 struct SavePlan {
     mode: SaveMode,
@@ -22,10 +50,29 @@ struct SavePlan {
     categories: Vec<String>,
     topic: String,
     topic_source: String,
+    /// Taxonomy path from the root down to `topic`; empty unless the topic was classified.
+    topic_path: Vec<String>,
     save_reason: String,
     body: String,
 }
 
+/// Topic inference reply; local models often drop the wrapper object, so accept a bare array.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum TopicProposals {
+    Wrapped { topics: Vec<Map<String, Value>> },
+    Bare(Vec<Map<String, Value>>),
+}
+
+impl TopicProposals {
+    fn into_entries(self) -> Vec<Map<String, Value>> {
+        match self {
+            Self::Wrapped { topics } => topics,
+            Self::Bare(topics) => topics,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum SaveMode {
     Immediate,
@@ -33,11 +80,33 @@ enum SaveMode {
     Confirm,
 }
 
+/// How the user answered a save that is waiting for confirmation.
+#[derive(Debug, PartialEq)]
+enum ConfirmReply {
+    /// Store it, optionally with edits ("save it with tags=x", "yes topic=y", "save it: new text").
+    Save {
+        tags: Vec<String>,
+        topic: Option<String>,
+        body: Option<String>,
+    },
+    /// Search existing memories for the text instead of saving it.
+    Check,
+    Cancel,
+    /// Anything else; the pending save is dropped and the message handled normally.
+    Unrelated,
+}
+
 /// Front-desk Agent responsible for translating user requests into LLM prompts.
 pub struct Agent {
     llm_client: SharedLlmClient,
     rag_agent: Option<SharedRagAgent>,
     topic_registry: Option<SharedTopicRegistry>,
+    tools: ToolRegistry,
+    pending_saves: PendingStore<SavePlan>,
+    /// Picks save topics from the registered taxonomy; without it saves use `DEFAULT_TOPIC`.
+    topic_classifier: Option<Arc<TopicClassifier>>,
+    /// Answers "remind me ..." lookups and "update/retag <id>" edits; present when RAG is enabled.
+    memory_commands: Option<MemoryAgent>,
 }
 
 impl Agent {
@@ -46,23 +115,72 @@ impl Agent {
         rag_agent: Option<SharedRagAgent>,
         topic_registry: Option<SharedTopicRegistry>,
     ) -> Self {
+        let budget = PromptBudget::for_window(llm_client.context_window());
+        // Only advertise the memory tool when something can answer it.
+        let tools = match rag_agent.as_ref() {
+            Some(rag) => ToolRegistry::new().with_tool(
+                MemorySearchTool::new(rag.clone()).with_token_budget(budget.memory_tokens()),
+            ),
+            None => ToolRegistry::new(),
+        };
+
+        let memory_commands = rag_agent.clone().map(MemoryAgent::new);
+
         Self {
             llm_client,
             rag_agent,
+            memory_commands,
             topic_registry,
+            topic_classifier: None,
+            tools,
+            pending_saves: PendingStore::new(DEFAULT_CONFIRMATION_TTL),
         }
     }
 
+    /// How long a save waits for "save it" before it is dropped.
+    pub fn with_confirmation_ttl(mut self, ttl: Duration) -> Self {
+        self.pending_saves = PendingStore::new(ttl);
+        self
+    }
+
+    /// Classify saves without an explicit topic or tags against the registered taxonomy.
+    pub fn with_topic_classifier(mut self, classifier: Arc<TopicClassifier>) -> Self {
+        self.topic_classifier = Some(classifier);
+        self
+    }
+
     fn system_directive(&self) -> &'static str {
-        "You are Agent, the front-desk orchestrator of Vidkosha Cortex. Always follow the user instruction before proposing work. If the user references files, state which files you will read (or have read) and base your summary on them; do not invent content or new projects. If you see grounded snippets, use them first (cite path+chunk and agent with confidence) and blend in your own knowledge. If no snippets are present, answer directly unless more context would materially help—then call the tool. To call the tool, respond exactly with: TOOL:MEMORY_SEARCH {\"query\":\"<what to search>\",\"limit\":3} and nothing else. Delegate to a specialist only when the user requests it or when delegation clearly improves accuracy; otherwise stay front desk. Keep responses concise, actionable, and avoid persona switching."
+        "You are Agent, the front-desk orchestrator of Vidkosha Cortex. Always follow the user instruction before proposing work. If the user references files, state which files you will read (or have read) and base your summary on them; do not invent content or new projects. If you see grounded snippets, use them first (cite path+chunk and agent with confidence) and blend in your own knowledge. Delegate to a specialist only when the user requests it or when delegation clearly improves accuracy; otherwise stay front desk. Keep responses concise, actionable, and avoid persona switching."
     }
 
-    fn compose_prompt(&self, request: &AgentRequest) -> String {
-        format!(
-            "{directive}\n\nUser request:\n{input}\n\nInstructions: if files are mentioned, acknowledge them explicitly before answering; if context is missing, emit TOOL:MEMORY_SEARCH as defined. Otherwise, reason briefly and outline next steps.",
-            directive = self.system_directive(),
-            input = request.input.trim()
+    fn tool_directive(&self) -> &'static str {
+        "If no snippets are present, answer directly unless more context would materially help—then call the memory_search tool. If native tool calls are unavailable, respond exactly with: TOOL:MEMORY_SEARCH {\"query\":\"<what to search>\",\"limit\":3} and nothing else."
+    }
+
+    fn prompt(&self, request: &AgentRequest, system: String) -> PromptBuilder {
+        PromptBuilder::new(
+            PromptBudget::for_window(self.llm_client.context_window()),
+            system,
         )
+        .history(&request.history)
+    }
+
+    fn first_pass_messages(&self, request: &AgentRequest) -> Vec<ChatMessage> {
+        let (system, tool_hint) = if !self.tools.is_empty() {
+            (
+                format!("{} {}", self.system_directive(), self.tool_directive()),
+                "if context is missing, call memory_search. Otherwise, ",
+            )
+        } else {
+            (self.system_directive().to_string(), "")
+        };
+
+        let user_content = format!(
+            "User request:\n{input}\n\nInstructions: if files are mentioned, acknowledge them explicitly before answering; {tool_hint}reason briefly and outline next steps.",
+            input = request.input.trim()
+        );
+
+        self.prompt(request, system).request(user_content).build()
     }
 
     async fn handle_control(
@@ -199,43 +317,21 @@ impl Agent {
         raw: &str,
     ) -> anyhow::Result<Option<Vec<(String, Value)>>> {
         let prompt = format!(
-            "You are a concise classifier. Given text, propose at most 5 topic objects in JSON array form. Each object: name (slug-like, lowercase with dots), description, parent (optional), status=active. If no new topics are needed, return an empty JSON array.\n\nText:\n{raw}\n\nRespond with JSON only."
+            "You are a concise classifier. Given text, propose at most 5 topic objects. Each object: name (slug-like, lowercase with dots), description, parent (optional), status=active. Respond with a JSON object of the form {{\"topics\": [...]}}; use an empty array if no new topics are needed.\n\nText:\n{raw}\n\nRespond with JSON only."
         );
 
-        let output = self.llm_client.complete(&prompt).await?;
-        let parsed: Value = match serde_json::from_str(output.trim()) {
-            Ok(v) => v,
-            Err(_) => return Ok(None),
-        };
-
-        let arr = match parsed.as_array() {
-            Some(a) => a,
-            None => return Ok(None),
-        };
-
-        let mut seeds = Vec::new();
-        for entry in arr {
-            let obj = match entry.as_object() {
-                Some(o) => o,
-                None => continue,
-            };
-
-            let name = match obj.get("name").and_then(|v| v.as_str()) {
-                Some(n) if !n.trim().is_empty() => n.trim(),
-                _ => continue,
-            };
-
-            let mut meta = Value::Object(obj.clone());
-            if let Some(map) = meta.as_object_mut() {
-                map.remove("name");
-                if map.is_empty() {
-                    *map = Map::new();
-                }
+        let reply =
+            chat_json::<TopicProposals>(self.llm_client.as_ref(), &[ChatMessage::user(prompt)])
+                .await?;
+        let entries = match reply {
+            JsonReply::Valid { value, .. } => value.into_entries(),
+            JsonReply::Invalid { reason } => {
+                warn!(%reason, "Topic inference returned unusable JSON; skipping topic upsert");
+                return Ok(None);
             }
+        };
 
-            seeds.push((name.to_string(), meta));
-        }
-
+        let seeds = Self::topic_seeds_from_entries(entries);
         if seeds.is_empty() {
             Ok(None)
         } else {
@@ -243,33 +339,28 @@ impl Agent {
         }
     }
 
-    fn extract_topic_seeds(raw: &str) -> Option<Vec<(String, Value)>> {
-        let parsed: Value = serde_json::from_str(raw).ok()?;
-        let entries = parsed.as_array()?;
+    /// Split `{name, ...rest}` objects into (name, metadata) pairs, skipping unnamed entries.
+    fn topic_seeds_from_entries(entries: Vec<Map<String, Value>>) -> Vec<(String, Value)> {
         let mut seeds = Vec::new();
-
-        for entry in entries {
-            let obj = match entry.as_object() {
-                Some(o) => o,
-                None => continue,
-            };
-
-            let name = match obj.get("name").and_then(|v| v.as_str()) {
-                Some(n) if !n.trim().is_empty() => n.trim(),
+        for mut obj in entries {
+            let name = match obj.remove("name") {
+                Some(Value::String(n)) if !n.trim().is_empty() => n.trim().to_string(),
                 _ => continue,
             };
-
-            let mut meta = Value::Object(obj.clone());
-            if let Some(map) = meta.as_object_mut() {
-                map.remove("name");
-                if map.is_empty() {
-                    *map = Map::new();
-                }
-            }
-
-            seeds.push((name.to_string(), meta));
+            seeds.push((name, Value::Object(obj)));
         }
+        seeds
+    }
 
+    fn extract_topic_seeds(raw: &str) -> Option<Vec<(String, Value)>> {
+        let parsed: Value = serde_json::from_str(raw).ok()?;
+        let entries = parsed
+            .as_array()?
+            .iter()
+            .filter_map(|entry| entry.as_object().cloned())
+            .collect();
+
+        let seeds = Self::topic_seeds_from_entries(entries);
         if seeds.is_empty() {
             None
         } else {
@@ -332,56 +423,205 @@ impl Agent {
             );
         }
 
-        let (categories, topic) = if lower.contains("comedy")
-            || lower.contains("standup")
-            || lower.contains("joke")
-            || lower.contains("bit")
-        {
-            (
-                vec!["hobby".to_string(), "comedy".to_string()],
-                "standup_comedy".to_string(),
-            )
-        } else if lower.contains("fightstick")
-            || lower.contains("arcade")
-            || lower.contains("joystick")
-            || lower.contains("sanwa")
-            || lower.contains("happ")
-            || lower.contains("brook")
-            || lower.contains("buttons")
-            || lower.contains("pcb")
-        {
-            (
-                vec![
-                    "hardware".to_string(),
-                    "build".to_string(),
-                    "arcade".to_string(),
-                ],
-                "hardware.build.fightstick".to_string(),
-            )
-        } else if lower.contains("shopping")
-            || lower.contains("list")
-            || lower.contains("buy")
-            || lower.contains("purchase")
-            || lower.contains("parts")
-        {
-            (
-                vec!["task".to_string(), "list".to_string()],
-                "task.list".to_string(),
-            )
-        } else if lower.contains("client")
-            || lower.contains("proposal")
-            || lower.contains("roadmap")
-            || lower.contains("market")
-            || lower.contains("product")
-        {
-            (vec!["business".to_string()], "business.idea".to_string())
-        } else if lower.contains("project") {
-            (vec!["project".to_string()], "project.note".to_string())
-        } else {
-            (vec!["personal".to_string()], "personal.note".to_string())
+        // Otherwise the taxonomy decides at save time (see `classify_topic`).
+        (
+            vec!["personal".to_string()],
+            DEFAULT_TOPIC.to_string(),
+            DEFAULT_TOPIC_SOURCE.to_string(),
+        )
+    }
+
+    /// Swap the default topic for the closest registered one. Topics the user chose, directly
+    /// or through tags, are kept.
+    async fn classify_topic(&self, plan: &SavePlan, body: &str) -> SavePlan {
+        let mut plan = plan.clone();
+        if plan.topic_source != DEFAULT_TOPIC_SOURCE {
+            return plan;
+        }
+        let Some(classifier) = self.topic_classifier.as_ref() else {
+            return plan;
+        };
+        if let Some(choice) = classifier.classify(body).await {
+            plan.categories = if choice.path.is_empty() {
+                vec![choice.topic.clone()]
+            } else {
+                choice.path.clone()
+            };
+            plan.topic_path = choice.path;
+            plan.topic_path.push(choice.topic.clone());
+            plan.topic = choice.topic;
+            plan.topic_source = choice.source.to_string();
+        }
+        plan
+    }
+
+    fn parse_confirm_reply(lower: &str, raw: &str) -> ConfirmReply {
+        const CANCEL: &[&str] = &[
+            "no",
+            "nope",
+            "cancel",
+            "never mind",
+            "nevermind",
+            "don't save",
+            "dont save",
+            "forget it",
+            "skip",
+            "no thanks",
+        ];
+        const CHECK: &[&str] = &["just check", "check", "just search", "search"];
+        const SAVE: &[&str] = &[
+            "save it", "save", "yes", "yep", "sure", "okay", "ok", "confirm", "do it",
+        ];
+
+        let trimmed = lower.trim().trim_end_matches(['.', '!']);
+        if CANCEL.contains(&trimmed) || trimmed.starts_with("don't save") {
+            return ConfirmReply::Cancel;
+        }
+        if CHECK.contains(&trimmed) {
+            return ConfirmReply::Check;
+        }
+
+        let Some(prefix) = SAVE.iter().find(|prefix| {
+            trimmed.starts_with(*prefix) && Self::is_keyword_boundary(trimmed, 0, prefix.len())
+        }) else {
+            return ConfirmReply::Unrelated;
+        };
+
+        // `lower` and `raw` share byte offsets, so the tail can be cut from the original text.
+        let offset = raw.len() - raw.trim_start().len() + prefix.len();
+        let tail = raw[offset..].trim_start_matches([',', ' ']);
+        if let Some(body) = tail.strip_prefix(':') {
+            let body = body.trim();
+            return if body.is_empty() {
+                ConfirmReply::Unrelated
+            } else {
+                ConfirmReply::Save {
+                    tags: Vec::new(),
+                    topic: None,
+                    body: Some(body.to_string()),
+                }
+            };
+        }
+
+        let tail_lower = tail.to_lowercase();
+        let tags: Vec<String> = Self::extract_tags(tail, &tail_lower)
+            .into_iter()
+            .filter(|tag| !tag.contains('=') && !tag.contains(':'))
+            .collect();
+        let topic = ["topic=", "topic:"].iter().find_map(|token| {
+            let idx = tail_lower.find(token)?;
+            tail_lower[idx + token.len()..]
+                .split_whitespace()
+                .next()
+                .map(str::to_string)
+        });
+
+        // Whatever is left must be filler, or the reply is really a new request.
+        let leftover = tail_lower
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|word| !word.is_empty())
+            .filter(|word| !word.contains('=') && !word.contains(':'))
+            .filter(|word| !tags.iter().any(|tag| tag == word))
+            .any(|word| !CONFIRMATION_FILLER.contains(&word));
+        if leftover {
+            return ConfirmReply::Unrelated;
+        }
+
+        ConfirmReply::Save {
+            tags,
+            topic,
+            body: None,
+        }
+    }
+
+    /// Whether `request` replies to a save parked in its session, so the router hands it to the
+    /// front desk instead of classifying it. An unrelated message drops the parked save, as the
+    /// front desk would have.
+    pub fn answers_pending_save(&self, request: &AgentRequest) -> bool {
+        let session = PendingStore::<SavePlan>::session_key(request);
+        if !self.pending_saves.contains(&session) {
+            return false;
+        }
+        let raw = request.input.trim();
+        if Self::parse_confirm_reply(&raw.to_lowercase(), raw) == ConfirmReply::Unrelated {
+            let _ = self.pending_saves.take(&session);
+            return false;
+        }
+        true
+    }
+
+    /// Settle a save parked by `SaveMode::Confirm` in this session. "just check" rewrites the
+    /// request to a search for the parked text and lets the normal flow answer it.
+    async fn resolve_pending_save(
+        &self,
+        request: &mut AgentRequest,
+    ) -> anyhow::Result<Option<AgentResponse>> {
+        let session = PendingStore::<SavePlan>::session_key(request);
+        let raw = request.input.trim().to_string();
+        let reply = Self::parse_confirm_reply(&raw.to_lowercase(), &raw);
+
+        let pending = self.pending_saves.take(&session);
+        let mut plan = match (pending, &reply) {
+            (Pending::Active(plan), reply) if *reply != ConfirmReply::Unrelated => plan,
+            (Pending::Expired, ConfirmReply::Save { .. }) => {
+                return Ok(Some(AgentResponse::new(
+                    "That save request expired. Send the text you want saved again.",
+                )));
+            }
+            _ => return Ok(None),
         };
 
-        (categories, topic, "inferred".to_string())
+        match reply {
+            ConfirmReply::Cancel => Ok(Some(AgentResponse::new("OK, I won't save it."))),
+            ConfirmReply::Check => {
+                request.input = plan.body;
+                Ok(None)
+            }
+            ConfirmReply::Save { tags, topic, body } => {
+                if !tags.is_empty() {
+                    if !plan.topic_source.starts_with("user:") {
+                        plan.topic = tags[0].clone();
+                        plan.topic_source = "tags:first".to_string();
+                    }
+                    plan.tags = tags;
+                }
+                if let Some(topic) = topic {
+                    plan.topic = topic;
+                    plan.topic_source = "user:confirm".to_string();
+                }
+                if let Some(body) = body {
+                    plan.body = body;
+                }
+                let msg = self.persist_save_plan(&plan, None).await?;
+                Ok(msg.map(AgentResponse::new))
+            }
+            ConfirmReply::Unrelated => Ok(None),
+        }
+    }
+
+    /// Hints following "remind me" / "what did I save", e.g. "remind me groceries tags=weekly".
+    /// "remind me to ..." asks for a reminder rather than a lookup, so it is not a recall.
+    fn extract_recall_hints<'a>(lower: &str, raw: &'a str) -> Option<&'a str> {
+        const POLITE: &[&str] = &["please ", "can you ", "could you "];
+        const PREFIXES: &[&str] = &[
+            "remind me",
+            "what did i save",
+            "what have i saved",
+            "what did you save",
+        ];
+        let offset = POLITE
+            .iter()
+            .find(|polite| lower.starts_with(*polite))
+            .map_or(0, |polite| polite.len());
+        let prefix = PREFIXES.iter().find(|prefix| {
+            lower[offset..].starts_with(*prefix)
+                && Self::is_keyword_boundary(lower, offset, prefix.len())
+        })?;
+        let tail = raw[offset + prefix.len()..].trim();
+        if tail.to_lowercase().starts_with("to ") {
+            return None;
+        }
+        Some(tail)
     }
 
     fn extract_forget_id<'a>(lower: &str, raw: &'a str) -> Option<&'a str> {
@@ -416,6 +656,7 @@ impl Agent {
             categories,
             topic,
             topic_source,
+            topic_path: Vec::new(),
             save_reason,
             body: trimmed.to_string(),
         })
@@ -444,6 +685,7 @@ impl Agent {
             ));
         }
 
+        let plan = &self.classify_topic(plan, final_body).await;
         let summary: String = final_body.chars().take(200).collect();
         let record = MemoryRecord {
             id: None,
@@ -465,6 +707,7 @@ impl Agent {
                 "raw_input": plan.raw_input,
                 "categories": plan.categories,
                 "topic_source": plan.topic_source,
+                "topic_path": plan.topic_path,
                 "tags": plan.tags,
                 "body": final_body,
                 "save_reason": plan.save_reason,
@@ -496,103 +739,104 @@ impl Agent {
         Ok(Some(msg))
     }
 
-    #[instrument(skip_all, fields(raw_output_len = raw_output.len()))]
-    #[allow(dead_code)]
-    async fn maybe_tool_search(
-        &self,
-        request: &AgentRequest,
-        raw_output: &str,
-    ) -> anyhow::Result<Option<String>> {
-        let rag = match self.rag_agent.as_ref() {
-            Some(rag) => rag,
-            None => return Ok(None),
-        };
-
+    /// Recover a textual `TOOL:MEMORY_SEARCH {...}` directive from backends without native tool calls.
+    fn legacy_tool_call(raw_output: &str) -> Option<ToolCall> {
         const PREFIX: &str = "TOOL:MEMORY_SEARCH";
-        let trimmed = raw_output.trim();
-        let idx = match trimmed.find(PREFIX) {
-            Some(i) => i,
-            None => return Ok(None),
-        };
+        let idx = raw_output.find(PREFIX)?;
+        let arguments =
+            match parse_json_reply::<Map<String, Value>>(&raw_output[idx + PREFIX.len()..]) {
+                Ok(arguments) => arguments,
+                Err(reason) => {
+                    warn!(%reason, "Ignoring malformed TOOL:MEMORY_SEARCH directive");
+                    return None;
+                }
+            };
 
-        let json_part = trimmed[idx + PREFIX.len()..].trim();
-        let search: serde_json::Value = serde_json::from_str(json_part)?;
-
-        let query = search
-            .get("query")
-            .and_then(|v| v.as_str())
-            .unwrap_or(request.input.as_str())
-            .to_string();
-        let limit = search
-            .get("limit")
-            .and_then(|v| v.as_u64())
-            .unwrap_or(3)
-            .clamp(1, 10) as usize;
-
-        let memory_query = MemoryQuery {
-            query,
-            filters: MemoryFilters::default(),
-            limit,
-        };
+        Some(ToolCall {
+            id: String::from("legacy-memory-search"),
+            name: MemorySearchTool::NAME.to_string(),
+            arguments: Value::Object(arguments).to_string(),
+        })
+    }
 
-        info!(limit, query = %memory_query.query, "Memory tool request parsed; querying RAG");
-        let results = rag.handle(MemoryRequest::Retrieve(memory_query)).await?;
+    /// Let the model call tools for up to `MAX_TOOL_STEPS` round-trips, then return its answer.
+    /// Intermediate turns are buffered; only the final answer reaches the stream.
+    #[instrument(skip_all)]
+    async fn run_tool_loop(
+        &self,
+        request: &AgentRequest,
+        sink: Option<&TokenSink>,
+    ) -> anyhow::Result<(String, Vec<ToolCallRecord>)> {
+        let specs = self.tools.specs();
+        let budget = PromptBudget::for_window(self.llm_client.context_window());
+        let mut messages = self.first_pass_messages(request);
+        let mut records = Vec::new();
+
+        for step in 0..MAX_TOOL_STEPS {
+            let turn = self.llm_client.chat_with_tools(&messages, &specs).await?;
+
+            // Tool results share what the prompt budget has left; once one had to be cut,
+            // the model answers with what it has.
+            let mut exhausted = false;
+            if !turn.tool_calls.is_empty() {
+                messages.push(ChatMessage::assistant_tool_calls(
+                    turn.content,
+                    turn.tool_calls.clone(),
+                ));
+                for call in &turn.tool_calls {
+                    info!(step, tool = %call.name, "Model requested tool");
+                    let (output, record) = self.tools.invoke(call).await;
+                    let room = budget.room_after(&messages);
+                    exhausted |= estimate_tokens(&output) as usize > room;
+                    messages.push(ChatMessage::tool(
+                        call.id.clone(),
+                        fit_tool_output(&output, room),
+                    ));
+                    records.push(record);
+                }
+            } else if let Some(call) = Self::legacy_tool_call(&turn.content) {
+                info!(step, tool = %call.name, "Model requested tool via text directive");
+                let (output, record) = self.tools.invoke(&call).await;
+                messages.push(ChatMessage::assistant(turn.content));
+                let suffix = format!("\n\nUser request:\n{}", request.input.trim());
+                let room = budget
+                    .room_after(&messages)
+                    .saturating_sub(estimate_tokens(&suffix) as usize);
+                exhausted = estimate_tokens(&output) as usize > room;
+                messages.push(ChatMessage::user(format!(
+                    "{}{suffix}",
+                    fit_tool_output(&output, room)
+                )));
+                records.push(record);
+            } else {
+                request.emit(&turn.content);
+                return Ok((turn.content, records));
+            }
 
-        if results.records.is_empty() {
-            warn!("Memory tool returned no matches");
-            return Ok(Some(String::from(
-                "No memories found in Helix. Answer from your own knowledge, and if prior context is needed, state that no stored memory matched.",
-            )));
+            if exhausted {
+                warn!(
+                    step,
+                    "Tool results filled the prompt budget; requesting a final answer"
+                );
+                let output = chat_with_sink(self.llm_client.as_ref(), &messages, sink).await?;
+                return Ok((output, records));
+            }
         }
 
-        info!(
-            count = results.records.len(),
-            "Memory tool returned matches"
-        );
-        let context = results
-            .records
-            .iter()
-            .take(limit)
-            .map(|r| {
-                let path = r
-                    .metadata
-                    .as_ref()
-                    .and_then(|m| m.get("path"))
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("");
-                let chunk_id = r
-                    .metadata
-                    .as_ref()
-                    .and_then(|m| m.get("chunk_id"))
-                    .and_then(|v| v.as_str())
-                    .unwrap_or(r.id.as_deref().unwrap_or(""));
-                format!(
-                    "- [{}] path={} chunk={} agent={} topic={} confidence={:.2} :: {}\n{}",
-                    r.timestamp.to_rfc3339(),
-                    path,
-                    chunk_id,
-                    r.agent_name,
-                    r.topic,
-                    r.confidence,
-                    r.summary,
-                    r.full_content
-                )
-            })
-            .collect::<Vec<_>>()
-            .join("\n");
-
-        let follow_up = format!(
-            "Relevant memories found. Cite path+chunk and agent with confidence when you use them. Blend in your own knowledge to fill gaps, and if you add anything not in the snippets, say it is general knowledge.\n{context}\n\nUser request:\n{}",
-            request.input.trim()
+        warn!(
+            max_steps = MAX_TOOL_STEPS,
+            "Tool loop hit its step limit; requesting a final answer"
         );
-
-        Ok(Some(follow_up))
+        let output = chat_with_sink(self.llm_client.as_ref(), &messages, sink).await?;
+        Ok((output, records))
     }
+
+    /// Grounded prompt from a default memory search, fitted to the model's context window.
     async fn default_grounding(
         &self,
         request: &AgentRequest,
         rag: &SharedRagAgent,
-    ) -> anyhow::Result<Option<String>> {
+    ) -> anyhow::Result<Option<Vec<ChatMessage>>> {
         let query = MemoryQuery {
             query: request.input.clone(),
             filters: MemoryFilters::default(),
@@ -614,53 +858,42 @@ impl Agent {
             return Ok(None);
         }
 
-        let context = results
-            .records
-            .iter()
-            .take(query.limit())
-            .map(|r| {
-                let path = r
-                    .metadata
-                    .as_ref()
-                    .and_then(|m| m.get("path"))
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("");
-                let chunk_id = r
-                    .metadata
-                    .as_ref()
-                    .and_then(|m| m.get("chunk_id"))
-                    .and_then(|v| v.as_str())
-                    .unwrap_or(r.id.as_deref().unwrap_or(""));
-                format!(
-                    "- [{}] path={} chunk={} agent={} topic={} confidence={:.2} :: {}\n{}",
-                    r.timestamp.to_rfc3339(),
-                    path,
-                    chunk_id,
-                    r.agent_name,
-                    r.topic,
-                    r.confidence,
-                    r.summary,
-                    r.full_content
-                )
-            })
-            .collect::<Vec<_>>()
-            .join("\n");
-
-        let follow_up = format!(
-            "Retrieved memories. Use them when relevant, cite path+chunk and agent with confidence, and blend with your own knowledge. If you add anything not in the snippets, say it is general knowledge.\n{context}\n\nUser request:\n{}",
-            request.input.trim()
-        );
+        let ranked = &results.records[..query.limit().min(results.records.len())];
+        let messages = self
+            .prompt(request, self.system_directive().to_string())
+            .memories(
+                "Retrieved memories. Use them when relevant, cite path+chunk and agent with confidence, and blend with your own knowledge. If you add anything not in the snippets, say it is general knowledge.",
+                ranked,
+            )
+            .request(format!("User request:\n{}", request.input.trim()))
+            .build();
 
-        Ok(Some(follow_up))
+        Ok(Some(messages))
     }
 }
 
 #[async_trait]
 impl AgentBehavior for Agent {
     #[instrument(skip_all, fields(input = %request.input))]
-    async fn handle(&self, request: AgentRequest) -> anyhow::Result<AgentResponse> {
+    async fn handle(&self, mut request: AgentRequest) -> anyhow::Result<AgentResponse> {
+        if let Some(resolved) = self.resolve_pending_save(&mut request).await? {
+            return Ok(resolved);
+        }
+
         let raw = request.input.trim().to_string();
         let lower = raw.to_lowercase();
+        if let Some(hints) = Self::extract_recall_hints(&lower, &raw) {
+            let output = match self.memory_commands.as_ref() {
+                Some(memory) => memory.recall(hints).await?,
+                None => "I can look up saved memories when memory is enabled. Right now RAG is disabled.".to_string(),
+            };
+            return Ok(AgentResponse::new(output));
+        }
+        if let Some(memory) = self.memory_commands.as_ref() {
+            if let Some(output) = memory.edit(&raw).await? {
+                return Ok(AgentResponse::new(output));
+            }
+        }
         if let Some(registry) = self.topic_registry.as_ref() {
             if let Some(seeds) = Self::extract_topic_seeds(&raw) {
                 let ids = registry
@@ -711,9 +944,11 @@ impl AgentBehavior for Agent {
             if plan.mode == SaveMode::Confirm {
                 let preview: String = plan.body.chars().take(80).collect();
                 let msg = format!(
-                    "I spotted a possible save request but your text is short/ambiguous: \"{}\". Do you want me to save it, or should I just check existing memories? Say 'save it' to store or 'just check' to search.",
+                    "I spotted a possible save request but your text is short/ambiguous: \"{}\". Do you want me to save it, or should I just check existing memories? Say 'save it' to store (add tags=a,b or topic=x to adjust), 'just check' to search, or 'cancel'.",
                     preview
                 );
+                let session = PendingStore::<SavePlan>::session_key(&request);
+                self.pending_saves.insert(&session, plan.clone());
                 return Ok(AgentResponse::new(msg));
             }
 
@@ -729,30 +964,28 @@ impl AgentBehavior for Agent {
         }
 
         let mut output: Option<String> = None;
+        let mut tool_calls = Vec::new();
+
+        let sink = request.stream.as_ref();
 
         // Prefer a quick memory grounding when available to avoid hallucinations on rare/fictional terms.
         if let Some(rag) = self.rag_agent.as_ref() {
-            if let Ok(Some(follow_up_prompt)) = self.default_grounding(&request, rag).await {
-                let grounded = self.llm_client.complete(&follow_up_prompt).await?;
+            if let Ok(Some(messages)) = self.default_grounding(&request, rag).await {
+                let grounded = chat_with_sink(self.llm_client.as_ref(), &messages, sink).await?;
                 output = Some(grounded);
             }
         }
 
         if output.is_none() {
-            // Otherwise run once, and honor explicit TOOL:MEMORY_SEARCH directives if the model requests them.
-            let prompt = self.compose_prompt(&request);
-            let first = self.llm_client.complete(&prompt).await?;
-
-            if self.rag_agent.is_some() {
-                if let Some(follow_up_prompt) = self.maybe_tool_search(&request, &first).await? {
-                    info!("Memory tool requested; rerunning with retrieved context");
-                    let rerun = self.llm_client.complete(&follow_up_prompt).await?;
-                    output = Some(rerun);
-                }
-            }
-
-            if output.is_none() {
+            if self.tools.is_empty() {
+                // No tool can be requested, so the first pass is the final answer.
+                let messages = self.first_pass_messages(&request);
+                let first = chat_with_sink(self.llm_client.as_ref(), &messages, sink).await?;
                 output = Some(first);
+            } else {
+                let (answer, records) = self.run_tool_loop(&request, sink).await?;
+                tool_calls = records;
+                output = Some(answer);
             }
         }
 
@@ -764,11 +997,448 @@ impl AgentBehavior for Agent {
                     .persist_save_plan(plan, Some(final_output.as_str()))
                     .await?
                 {
+                    request.emit(&format!("\n\n{msg}"));
                     final_output = format!("{final_output}\n\n{msg}");
                 }
             }
         }
 
-        Ok(AgentResponse::new(final_output))
+        Ok(AgentResponse::new(final_output).with_tool_calls(tool_calls))
+    }
+}
+
+/// Cut a tool result to `room` tokens, or drop it when too little is left to be useful.
+fn fit_tool_output(output: &str, room: usize) -> String {
+    if estimate_tokens(output) as usize > room && room < MIN_TOOL_OUTPUT_TOKENS {
+        TOOL_OUTPUT_OMITTED.to_string()
+    } else {
+        truncate_to_tokens(output, room)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::agents::TurnContext;
+    use crate::llm_client::{ChatRole, ChatTurn, EchoLlmClient, LlmClient, ToolSpec};
+    use crate::rag::agent::RagAgent;
+    use crate::rag::client::RagClient;
+    use crate::rag::mock::MockRagClient;
+    use crate::rag::types::MemoryWriteResponse;
+
+    /// Requests memory_search once, then answers with whatever the tool returned.
+    #[derive(Default)]
+    struct ScriptedToolClient {
+        turns: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl LlmClient for ScriptedToolClient {
+        async fn chat(&self, _messages: &[ChatMessage]) -> anyhow::Result<String> {
+            Ok(String::from("plain answer"))
+        }
+
+        async fn chat_with_tools(
+            &self,
+            messages: &[ChatMessage],
+            tools: &[ToolSpec],
+        ) -> anyhow::Result<ChatTurn> {
+            assert_eq!(tools[0].name, MemorySearchTool::NAME);
+            if self.turns.fetch_add(1, Ordering::SeqCst) == 0 {
+                return Ok(ChatTurn {
+                    content: String::new(),
+                    tool_calls: vec![ToolCall {
+                        id: String::from("call-1"),
+                        name: MemorySearchTool::NAME.to_string(),
+                        arguments: String::from(r#"{"query":"fightstick wiring"}"#),
+                    }],
+                });
+            }
+
+            let tool_reply = messages
+                .iter()
+                .find(|m| m.role == ChatRole::Tool)
+                .expect("tool result replayed");
+            assert_eq!(tool_reply.tool_call_id.as_deref(), Some("call-1"));
+            Ok(ChatTurn {
+                content: String::from("final answer"),
+                tool_calls: Vec::new(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn tool_loop_invokes_memory_search_and_records_call() {
+        let rag = Arc::new(RagAgent::new(Arc::new(MockRagClient::default())));
+        let agent = Agent::new(Arc::new(ScriptedToolClient::default()), Some(rag), None);
+
+        let response = agent
+            .handle(AgentRequest::new("how did I wire the fightstick"))
+            .await
+            .expect("agent response");
+
+        assert_eq!(response.output, "final answer");
+        assert_eq!(response.tool_calls.len(), 1);
+        assert_eq!(response.tool_calls[0].tool_name, MemorySearchTool::NAME);
+        assert_eq!(
+            response.tool_calls[0].args_json["query"],
+            "fightstick wiring"
+        );
+    }
+
+    /// Asks for memory_search on every turn and tracks the largest prompt it was sent.
+    #[derive(Default)]
+    struct GreedyToolClient {
+        turns: AtomicUsize,
+        peak_prompt: AtomicUsize,
+    }
+
+    impl GreedyToolClient {
+        fn observe(&self, messages: &[ChatMessage]) {
+            let tokens = crate::usage::estimate_message_tokens(messages) as usize;
+            self.peak_prompt.fetch_max(tokens, Ordering::SeqCst);
+        }
+    }
+
+    #[async_trait]
+    impl LlmClient for GreedyToolClient {
+        async fn chat(&self, messages: &[ChatMessage]) -> anyhow::Result<String> {
+            self.observe(messages);
+            Ok(String::from("final answer"))
+        }
+
+        async fn chat_with_tools(
+            &self,
+            messages: &[ChatMessage],
+            _tools: &[ToolSpec],
+        ) -> anyhow::Result<ChatTurn> {
+            self.observe(messages);
+            let turn = self.turns.fetch_add(1, Ordering::SeqCst);
+            Ok(ChatTurn {
+                content: String::new(),
+                tool_calls: vec![ToolCall {
+                    id: format!("call-{turn}"),
+                    name: MemorySearchTool::NAME.to_string(),
+                    arguments: String::from(r#"{"query":"fightstick wiring"}"#),
+                }],
+            })
+        }
+
+        fn context_window(&self) -> usize {
+            2_048
+        }
+    }
+
+    /// Answers only the model's memory_search query, so the default grounding finds nothing.
+    #[derive(Default)]
+    struct ToolOnlyRag(MockRagClient);
+
+    #[async_trait]
+    impl RagClient for ToolOnlyRag {
+        async fn write(&self, request: MemoryWriteRequest) -> anyhow::Result<MemoryWriteResponse> {
+            self.0.write(request).await
+        }
+
+        async fn query(&self, query: MemoryQuery) -> anyhow::Result<Vec<MemoryRecord>> {
+            if query.query != "fightstick wiring" {
+                return Ok(Vec::new());
+            }
+            self.0.query(query).await
+        }
+
+        async fn delete(&self, request: MemoryDeleteRequest) -> anyhow::Result<()> {
+            self.0.delete(request).await
+        }
+    }
+
+    #[tokio::test]
+    async fn tool_results_share_the_remaining_prompt_budget() {
+        let rag: SharedRagAgent = Arc::new(RagAgent::new(Arc::new(ToolOnlyRag::default())));
+        let saver = Agent::new(EchoLlmClient::shared(), Some(rag.clone()), None);
+        for _ in 0..3 {
+            let note = format!(
+                "remember {}",
+                "fightstick wiring uses sanwa buttons. ".repeat(150)
+            );
+            saver.handle(AgentRequest::new(note)).await.unwrap();
+        }
+
+        let client = Arc::new(GreedyToolClient::default());
+        let agent = Agent::new(client.clone(), Some(rag), None);
+        let response = agent
+            .handle(AgentRequest::new("how did I wire the fightstick"))
+            .await
+            .unwrap();
+
+        // Each search alone fits the memory share; two would overflow without the budget.
+        assert_eq!(response.output, "final answer");
+        assert_eq!(response.tool_calls.len(), 2);
+        let prompt_tokens = PromptBudget::for_window(2_048).prompt_tokens();
+        assert!(client.peak_prompt.load(Ordering::SeqCst) <= prompt_tokens);
+    }
+
+    fn turn(conversation_id: &str) -> TurnContext {
+        TurnContext {
+            conversation_id: conversation_id.to_string(),
+            message_id: String::from("m"),
+            reply_message_id: String::from("r"),
+            reply_to: None,
+        }
+    }
+
+    async fn stored(rag: &SharedRagAgent) -> Vec<MemoryRecord> {
+        rag.handle(MemoryRequest::Retrieve(MemoryQuery {
+            query: String::from("anything"),
+            filters: MemoryFilters::default(),
+            limit: 10,
+        }))
+        .await
+        .unwrap()
+        .records
+    }
+
+    #[tokio::test]
+    async fn confirmation_saves_parked_plan_with_edits() {
+        let rag: SharedRagAgent = Arc::new(RagAgent::new(Arc::new(MockRagClient::default())));
+        let agent = Agent::new(EchoLlmClient::shared(), Some(rag.clone()), None);
+        let ask = |input: &str| AgentRequest::new(input).with_turn(turn("c1"));
+
+        let prompt = agent.handle(ask("remember milk")).await.unwrap();
+        assert!(prompt.output.contains("Say 'save it'"));
+        assert!(stored(&rag).await.is_empty());
+
+        // Another session's "save it" does not see the parked plan.
+        let other = agent
+            .handle(AgentRequest::new("save it").with_turn(turn("c2")))
+            .await
+            .unwrap();
+        assert!(other.output.contains("short/ambiguous: \"it\""));
+
+        let saved = agent
+            .handle(ask("save it with tags=groceries, weekly"))
+            .await
+            .unwrap();
+        assert!(saved
+            .output
+            .starts_with("Saved. id=mock-memory-1 topic=groceries."));
+        let records = stored(&rag).await;
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].full_content, "milk");
+        assert_eq!(
+            records[0].metadata.as_ref().unwrap()["tags"],
+            json!(["groceries", "weekly"])
+        );
+
+        // Resolved: a second confirmation starts over instead of saving again.
+        agent.handle(ask("yes")).await.unwrap();
+        assert_eq!(stored(&rag).await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn cancellation_check_and_expiry_do_not_save() {
+        let rag: SharedRagAgent = Arc::new(RagAgent::new(Arc::new(MockRagClient::default())));
+        let agent = Agent::new(EchoLlmClient::shared(), Some(rag.clone()), None);
+
+        agent
+            .handle(AgentRequest::new("remember milk"))
+            .await
+            .unwrap();
+        let cancelled = agent.handle(AgentRequest::new("cancel")).await.unwrap();
+        assert_eq!(cancelled.output, "OK, I won't save it.");
+
+        agent
+            .handle(AgentRequest::new("remember milk"))
+            .await
+            .unwrap();
+        let checked = agent.handle(AgentRequest::new("just check")).await.unwrap();
+        assert!(checked.output.contains("I received:"));
+        assert!(checked.output.contains("milk"));
+
+        let stale = Agent::new(EchoLlmClient::shared(), Some(rag.clone()), None)
+            .with_confirmation_ttl(Duration::ZERO);
+        stale
+            .handle(AgentRequest::new("remember milk"))
+            .await
+            .unwrap();
+        let expired = stale.handle(AgentRequest::new("save it")).await.unwrap();
+        assert!(expired.output.starts_with("That save request expired."));
+
+        assert!(stored(&rag).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn remind_me_lists_saves_by_tag_and_topic() {
+        let rag: SharedRagAgent = Arc::new(RagAgent::new(Arc::new(MockRagClient::default())));
+        let agent = Agent::new(EchoLlmClient::shared(), Some(rag.clone()), None);
+        for input in [
+            "remember oat milk and rye bread tags=groceries,weekly",
+            "note the fightstick needs sanwa buttons tags=hardware",
+        ] {
+            agent.handle(AgentRequest::new(input)).await.unwrap();
+        }
+
+        let groceries = agent
+            .handle(AgentRequest::new("Remind me groceries"))
+            .await
+            .unwrap()
+            .output;
+        assert!(groceries.contains("id=mock-memory-1 topic=groceries"));
+        assert!(!groceries.contains("mock-memory-2"));
+
+        let tagged = agent
+            .handle(AgentRequest::new("what did I save tags=hardware today"))
+            .await
+            .unwrap()
+            .output;
+        assert!(tagged.contains("(since="));
+        assert!(tagged.contains("tags=hardware)"));
+        assert!(tagged.contains("id=mock-memory-2"));
+        assert!(!tagged.contains("mock-memory-1"));
+
+        let none = agent
+            .handle(AgentRequest::new("remind me #comedy"))
+            .await
+            .unwrap()
+            .output;
+        assert_eq!(none, "No memories found (tags=comedy).");
+
+        let reminder = agent
+            .handle(AgentRequest::new("remind me to call the bank"))
+            .await
+            .unwrap()
+            .output;
+        assert!(reminder.contains("I received:"));
+    }
+
+    #[tokio::test]
+    async fn saves_record_the_classified_topic_path_unless_one_is_given() {
+        use crate::agents::taxonomy::tests::{seeded_store, AxisEmbedder, FixedLlm};
+        use crate::agents::taxonomy::TopicClassifier;
+
+        let embedder = Arc::new(AxisEmbedder::default());
+        let classifier = TopicClassifier::new(
+            Arc::new(seeded_store()),
+            embedder.clone(),
+            Arc::new(FixedLlm {
+                reply: "{}",
+                calls: AtomicUsize::new(0),
+            }),
+        );
+        let rag: SharedRagAgent = Arc::new(RagAgent::new(Arc::new(MockRagClient::default())));
+        let agent = Agent::new(EchoLlmClient::shared(), Some(rag.clone()), None)
+            .with_topic_classifier(Arc::new(classifier));
+
+        agent
+            .handle(AgentRequest::new(
+                "remember to restring the guitar before the gig on friday",
+            ))
+            .await
+            .unwrap();
+        let records = stored(&rag).await;
+        let metadata = records[0].metadata.as_ref().unwrap();
+        assert_eq!(records[0].topic, "hobby.music");
+        assert_eq!(metadata["topic_path"], json!(["hobby", "hobby.music"]));
+        assert_eq!(metadata["categories"], json!(["hobby"]));
+        assert_eq!(metadata["topic_source"], "taxonomy:embedding");
+
+        let embeds = embedder.calls.load(Ordering::SeqCst);
+        for input in [
+            "remember to restring the guitar before friday topic=gigs",
+            "remember to restring the guitar before friday tags=errands",
+        ] {
+            agent.handle(AgentRequest::new(input)).await.unwrap();
+        }
+        assert_eq!(embedder.calls.load(Ordering::SeqCst), embeds);
+        let topics: Vec<String> = stored(&rag)
+            .await
+            .into_iter()
+            .map(|record| record.topic)
+            .collect();
+        assert!(topics.contains(&String::from("gigs")));
+        assert!(topics.contains(&String::from("errands")));
+    }
+
+    #[tokio::test]
+    async fn update_command_edits_a_saved_memory() {
+        let rag: SharedRagAgent = Arc::new(RagAgent::new(Arc::new(MockRagClient::default())));
+        let agent = Agent::new(EchoLlmClient::shared(), Some(rag.clone()), None);
+        agent
+            .handle(AgentRequest::new(
+                "remember oat milk and rye bread tags=groceries",
+            ))
+            .await
+            .unwrap();
+
+        // "remember" in the new text must not start another save.
+        let updated = agent
+            .handle(AgentRequest::new(
+                "update mock-memory-1: remember oat milk, 2L",
+            ))
+            .await
+            .unwrap();
+        assert!(updated
+            .output
+            .starts_with("Updated memory id=mock-memory-1: text"));
+        let records = stored(&rag).await;
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].full_content, "remember oat milk, 2L");
+        assert_eq!(records[0].tags(), vec!["groceries"]);
+    }
+
+    #[tokio::test]
+    async fn edit_verbs_without_a_memory_id_are_answered_normally() {
+        let rag: SharedRagAgent = Arc::new(RagAgent::new(Arc::new(MockRagClient::default())));
+        let agent = Agent::new(EchoLlmClient::shared(), Some(rag), None);
+        for input in [
+            "update v2 of the router docs",
+            "edit 3 paragraphs in the intro",
+        ] {
+            let output = agent.handle(AgentRequest::new(input)).await.unwrap().output;
+            assert!(
+                !output.contains("memory id="),
+                "{input} was treated as an edit"
+            );
+            assert!(output.contains("I received:"));
+        }
+    }
+
+    #[test]
+    fn confirm_replies_separate_edits_from_new_requests() {
+        let parse = |raw: &str| Agent::parse_confirm_reply(&raw.to_lowercase(), raw);
+        assert_eq!(
+            parse("Yes, topic=shopping please"),
+            ConfirmReply::Save {
+                tags: Vec::new(),
+                topic: Some(String::from("shopping")),
+                body: None,
+            }
+        );
+        assert_eq!(
+            parse("save it: Oat milk, 2L"),
+            ConfirmReply::Save {
+                tags: Vec::new(),
+                topic: None,
+                body: Some(String::from("Oat milk, 2L")),
+            }
+        );
+        assert_eq!(parse("Never mind."), ConfirmReply::Cancel);
+        assert_eq!(parse("ok so what is the weather"), ConfirmReply::Unrelated);
+        assert_eq!(parse("yesterday was long"), ConfirmReply::Unrelated);
+    }
+
+    #[test]
+    fn legacy_directive_tolerates_surrounding_prose() {
+        let call = Agent::legacy_tool_call(
+            "Let me check.\nTOOL:MEMORY_SEARCH {\"query\":\"sanwa\",\"limit\":2}\nThanks!",
+        )
+        .expect("legacy call");
+        assert_eq!(call.name, MemorySearchTool::NAME);
+        let arguments: Value = serde_json::from_str(&call.arguments).unwrap();
+        assert_eq!(arguments["query"], "sanwa");
+        assert_eq!(arguments["limit"], 2);
     }
 }
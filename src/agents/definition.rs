@@ -0,0 +1,464 @@
+use std::collections::{BTreeMap, HashSet};
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{bail, Context};
+use serde::Deserialize;
+use tracing::info;
+
+use crate::orchestrator::router::RouterIntent;
+use crate::orchestrator::routing::classifier::SpecialistProfile;
+use crate::orchestrator::routing::rules::{RoutingRulesConfig, RuleConfig};
+use crate::orchestrator::routing::semantic::SemanticPrototype;
+
+const DEFAULT_DEFINITIONS_DIR: &str = "agents/specialists";
+/// Confidence given to the keyword rule generated for a file-defined specialist.
+const DEFINITION_RULE_CONFIDENCE: f32 = 0.8;
+
+/// A specialist described as data: who it is, how it answers, and how requests reach it.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AgentDefinition {
+    pub name: String,
+    pub intent: RouterIntent,
+    /// One line for the intent classifier's roster.
+    #[serde(default)]
+    pub description: String,
+    pub system_prompt: String,
+    /// Appended as "Respond with ..."; e.g. the markdown sections expected back.
+    #[serde(default)]
+    pub response_format: Option<String>,
+    /// Routing keywords, same syntax as `routing_rules.json`.
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    /// Example requests for the semantic router.
+    #[serde(default)]
+    pub prototypes: Vec<String>,
+    /// Topic used to filter this specialist's memories.
+    #[serde(default)]
+    pub memory_topic: Option<String>,
+    /// `llm_providers.json` agent entry to use; defaults to the specialist's own name.
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+impl AgentDefinition {
+    fn builtin(
+        name: &str,
+        intent: RouterIntent,
+        description: &str,
+        system_prompt: &str,
+        response_format: &str,
+        memory_topic: &str,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            intent,
+            description: description.to_string(),
+            system_prompt: system_prompt.to_string(),
+            response_format: Some(response_format.to_string()),
+            keywords: Vec::new(),
+            prototypes: Vec::new(),
+            memory_topic: Some(memory_topic.to_string()),
+            model: None,
+        }
+    }
+
+    /// Parse a markdown agent file laid out like `agents/agent_template.md`: a `## Metadata`
+    /// table of backticked fields, with the rest of the document used as the system prompt.
+    pub fn from_markdown(raw: &str) -> anyhow::Result<Self> {
+        let mut fields = BTreeMap::new();
+        let mut prompt = Vec::new();
+        let mut in_metadata = false;
+
+        for line in raw.lines() {
+            let trimmed = line.trim();
+            if trimmed.starts_with('#') {
+                in_metadata = trimmed.trim_start_matches('#').trim() == "Metadata";
+                if in_metadata {
+                    continue;
+                }
+            }
+            if !in_metadata {
+                prompt.push(line);
+                continue;
+            }
+            let cells: Vec<&str> = trimmed
+                .trim_matches('|')
+                .split('|')
+                .map(|cell| cell.trim().trim_matches('`').trim())
+                .collect();
+            if let [key, value] = cells.as_slice() {
+                if *key != "Field" && !key.starts_with("---") {
+                    fields.insert(key.to_string(), value.to_string());
+                }
+            }
+        }
+
+        let field = |key: &str| {
+            fields
+                .get(key)
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        let list = |key: &str, separator: char| {
+            field(key)
+                .map(|value| {
+                    value
+                        .split(separator)
+                        .map(|item| item.trim().trim_matches('`').trim().to_string())
+                        .filter(|item| !item.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+
+        let Some(name) = field("agent_name") else {
+            bail!("Agent definition has no `agent_name` in its Metadata table");
+        };
+        let intent = field("routing_intent")
+            .with_context(|| format!("Agent definition {name} has no `routing_intent`"))?;
+        let definition = Self {
+            intent: parse_intent(&intent)
+                .with_context(|| format!("Agent definition {name} has an unknown intent"))?,
+            description: field("description").unwrap_or_default(),
+            system_prompt: prompt.join("\n").trim().to_string(),
+            response_format: field("response_format"),
+            keywords: list("keywords", ','),
+            // Example requests may contain commas, so they are `;`-separated.
+            prototypes: list("prototypes", ';'),
+            memory_topic: field("memory_topic"),
+            model: field("model"),
+            name,
+        };
+        definition.validate()?;
+        Ok(definition)
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let raw = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read agent definition {}", path.display()))?;
+        let definition = if path.extension().is_some_and(|ext| ext == "json") {
+            let definition: Self = serde_json::from_str(&raw)
+                .with_context(|| format!("Failed to parse agent definition {}", path.display()))?;
+            definition.validate().map(|_| definition)
+        } else {
+            Self::from_markdown(&raw)
+        };
+        definition.with_context(|| format!("Invalid agent definition {}", path.display()))
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        let name = self.name.trim();
+        if name.is_empty() || name.chars().any(|c| !c.is_alphanumeric()) {
+            bail!("Agent name '{name}' must be a single alphanumeric word");
+        }
+        if matches!(name, "Agent" | "RagAgent" | "MegaBrain") {
+            bail!("Agent name '{name}' is reserved");
+        }
+        if self.system_prompt.trim().is_empty() {
+            bail!("Agent definition {name} has an empty system prompt");
+        }
+        Ok(())
+    }
+
+    /// LLM registry entry that serves this specialist.
+    pub fn model_entry(&self) -> &str {
+        self.model.as_deref().unwrap_or(&self.name)
+    }
+
+    fn rule(&self) -> Option<RuleConfig> {
+        (!self.keywords.is_empty()).then(|| RuleConfig {
+            name: format!("agent:{}", self.name.to_lowercase()),
+            agent: self.name.clone(),
+            intent: self.intent,
+            keywords: self.keywords.clone(),
+            negative_keywords: Vec::new(),
+            rationale: Some(format!("Request matches {}'s keywords", self.name)),
+            confidence: DEFINITION_RULE_CONFIDENCE,
+            priority: 0,
+        })
+    }
+
+    pub fn prototype(&self) -> Option<SemanticPrototype> {
+        (!self.prototypes.is_empty()).then(|| SemanticPrototype {
+            agent_name: self.name.clone(),
+            intent: self.intent,
+            examples: self.prototypes.clone(),
+        })
+    }
+
+    pub fn profile(&self) -> Option<SpecialistProfile> {
+        (!self.description.trim().is_empty()).then(|| SpecialistProfile {
+            name: self.name.clone(),
+            intent: self.intent,
+            description: self.description.trim().to_string(),
+        })
+    }
+}
+
+/// The set of specialists to register: the built-ins, overridden or extended by the files in
+/// `AGENT_DEFINITIONS_DIR` (default `agents/specialists`).
+#[derive(Debug, Clone)]
+pub struct AgentCatalog {
+    definitions: Vec<AgentDefinition>,
+}
+
+impl Default for AgentCatalog {
+    fn default() -> Self {
+        Self {
+            definitions: builtin_definitions(),
+        }
+    }
+}
+
+impl AgentCatalog {
+    /// Layer every `.md` and `.json` file in `dir` over the built-ins, in file name order.
+    pub fn load_dir(dir: &Path) -> anyhow::Result<Self> {
+        let mut paths = fs::read_dir(dir)
+            .with_context(|| format!("Failed to read agent definitions in {}", dir.display()))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.is_file()
+                    && path
+                        .extension()
+                        .is_some_and(|ext| ext == "md" || ext == "json")
+            })
+            .collect::<Vec<_>>();
+        paths.sort();
+
+        let mut catalog = Self::default();
+        let mut seen = HashSet::new();
+        for path in paths {
+            let definition = AgentDefinition::load(&path)?;
+            if !seen.insert(definition.name.clone()) {
+                bail!(
+                    "Agent {} is defined more than once in {}",
+                    definition.name,
+                    dir.display()
+                );
+            }
+            catalog.insert(definition);
+        }
+        info!(
+            dir = %dir.display(),
+            agents = catalog.definitions.len(),
+            "Loaded agent definitions"
+        );
+        Ok(catalog)
+    }
+
+    /// Use `AGENT_DEFINITIONS_DIR` when set, else `agents/specialists` if it exists; the
+    /// built-ins alone otherwise.
+    pub fn from_env() -> anyhow::Result<Self> {
+        let explicit = env::var("AGENT_DEFINITIONS_DIR").ok();
+        let dir = explicit
+            .clone()
+            .unwrap_or_else(|| DEFAULT_DEFINITIONS_DIR.to_string());
+
+        if Path::new(&dir).is_dir() {
+            Self::load_dir(Path::new(&dir))
+        } else if explicit.is_some() {
+            bail!("AGENT_DEFINITIONS_DIR points at missing directory {dir}");
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    fn insert(&mut self, definition: AgentDefinition) {
+        match self
+            .definitions
+            .iter_mut()
+            .find(|existing| existing.name == definition.name)
+        {
+            Some(existing) => *existing = definition,
+            None => self.definitions.push(definition),
+        }
+    }
+
+    pub fn definitions(&self) -> &[AgentDefinition] {
+        &self.definitions
+    }
+
+    /// Classifier roster: every specialist that has a description.
+    pub fn profiles(&self) -> Vec<SpecialistProfile> {
+        self.definitions
+            .iter()
+            .filter_map(AgentDefinition::profile)
+            .collect()
+    }
+
+    /// One `name: description` entry per specialist other than `except`, for the handoff hint.
+    pub fn handoff_roster(&self, except: &str) -> String {
+        self.definitions
+            .iter()
+            .filter(|definition| definition.name != except)
+            .map(|definition| match definition.description.trim() {
+                "" => definition.name.clone(),
+                description => format!("{}: {description}", definition.name),
+            })
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+
+    /// Keyword rules plus `@name` / `specialist:name` aliases for the routing table.
+    pub fn routing_rules(&self) -> RoutingRulesConfig {
+        let mut config = RoutingRulesConfig::default();
+        for definition in &self.definitions {
+            config.rules.extend(definition.rule());
+            let lower = definition.name.to_lowercase();
+            config
+                .aliases
+                .insert(format!("@{lower}"), definition.name.clone());
+            config.aliases.insert(
+                format!("specialist:{}", lower.trim_end_matches("agent")),
+                definition.name.clone(),
+            );
+        }
+        config
+    }
+}
+
+fn parse_intent(raw: &str) -> anyhow::Result<RouterIntent> {
+    Ok(match raw.trim().to_lowercase().as_str() {
+        "general_support" | "general" => RouterIntent::GeneralSupport,
+        "engineering" => RouterIntent::Engineering,
+        "research" => RouterIntent::Research,
+        "operations" | "ops" | "chain" => RouterIntent::Operations,
+        "memory" => RouterIntent::Memory,
+        other => bail!(
+            "'{other}' is not one of general_support, engineering, research, operations, memory"
+        ),
+    })
+}
+
+fn builtin_definitions() -> Vec<AgentDefinition> {
+    vec![
+        AgentDefinition::builtin(
+            "CTOAgent",
+            RouterIntent::Engineering,
+            "architecture, system design, service boundaries, roadmaps and trade-offs",
+            "You are CTOAgent, the architecture strategist of Vidkosha Cortex. Restate constraints, articulate service boundaries, and surface trade-offs before recommending next steps.",
+            "a structured brief with sections: ## Overview, ## Components, ## Trade-offs, ## Next Actions",
+            "architecture",
+        ),
+        AgentDefinition::builtin(
+            "SeniorEngineerAgent",
+            RouterIntent::Engineering,
+            "hands-on implementation, Rust code, debugging, refactors and tests",
+            "You are SeniorEngineerAgent, a pragmatic Rust engineer. Outline implementation steps, cite risks, and note verification commands.",
+            "## Changes (bullets), ## Verification (commands vs expectations), ## Next Actions",
+            "engineering",
+        ),
+        AgentDefinition::builtin(
+            "ResearcherAgent",
+            RouterIntent::Research,
+            "research, literature and source comparison, cited summaries",
+            "You are ResearcherAgent. Surface the most relevant knowledge, cite every claim, and flag open questions.",
+            "## Findings (bulleted, cite sources), ## Sources (list), ## Next Steps, Confidence: <0-1>, Open Questions: <list>",
+            "research",
+        ),
+        AgentDefinition::builtin(
+            "OpsChainAgent",
+            RouterIntent::Operations,
+            "deployment, infrastructure, capacity, reliability and cost planning",
+            "You are OpsChainAgent. Model capacity, reliability, and ops/anchoring trade-offs with actionable next steps.",
+            "## Options (table or bullets with cost + capacity), ## Recommendation, ## Risks, ## Next Actions",
+            "operations",
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOCS_AGENT: &str = "# DocsAgent (Lite)
+
+Keeps the README and changelog honest.
+
+## Metadata
+
+| Field | Value |
+| --- | --- |
+| `agent_name` | `DocsAgent` |
+| `routing_intent` | `docs` |
+| `keywords` | `readme, changelog, release notes` |
+
+## Quick Brief
+
+- **Mission:** keep docs in sync with the code.
+";
+
+    #[test]
+    fn parses_markdown_metadata_and_prompt() {
+        let err = AgentDefinition::from_markdown(DOCS_AGENT).unwrap_err();
+        assert!(format!("{err:#}").contains("'docs' is not one of"));
+
+        let raw = DOCS_AGENT.replace("`docs`", "`general`").replace(
+            "| `keywords`",
+            "| `prototypes` | `Update the README; Draft notes, briefly` |\n\
+             | `memory_topic` | `docs` |\n\
+             | `keywords`",
+        );
+        let definition = AgentDefinition::from_markdown(&raw).unwrap();
+        assert_eq!(definition.name, "DocsAgent");
+        assert_eq!(definition.intent, RouterIntent::GeneralSupport);
+        assert_eq!(
+            definition.keywords,
+            ["readme", "changelog", "release notes"]
+        );
+        assert_eq!(
+            definition.prototypes,
+            ["Update the README", "Draft notes, briefly"]
+        );
+        assert_eq!(definition.memory_topic.as_deref(), Some("docs"));
+        assert_eq!(definition.model_entry(), "DocsAgent");
+        assert!(definition.system_prompt.starts_with("# DocsAgent (Lite)"));
+        assert!(definition.system_prompt.contains("## Quick Brief"));
+        assert!(!definition.system_prompt.contains("agent_name"));
+    }
+
+    #[test]
+    fn files_extend_and_override_builtins() {
+        let dir = std::env::temp_dir().join(format!("vk-agents-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("docs.md"),
+            DOCS_AGENT.replace("`docs`", "`general`"),
+        )
+        .unwrap();
+        fs::write(
+            dir.join("cto.json"),
+            r#"{"name": "CTOAgent", "intent": "engineering", "system_prompt": "You are the CTO."}"#,
+        )
+        .unwrap();
+
+        let catalog = AgentCatalog::load_dir(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let names: Vec<_> = catalog
+            .definitions()
+            .iter()
+            .map(|d| d.name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "CTOAgent",
+                "SeniorEngineerAgent",
+                "ResearcherAgent",
+                "OpsChainAgent",
+                "DocsAgent"
+            ]
+        );
+        assert_eq!(catalog.definitions()[0].system_prompt, "You are the CTO.");
+
+        let rules = catalog.routing_rules();
+        assert_eq!(rules.rules.len(), 1);
+        assert_eq!(rules.rules[0].name, "agent:docsagent");
+        assert_eq!(rules.aliases["@docsagent"], "DocsAgent");
+        assert_eq!(rules.aliases["specialist:docs"], "DocsAgent");
+        rules.validate().unwrap();
+    }
+}
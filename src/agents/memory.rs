@@ -0,0 +1,945 @@
+use std::cmp::Reverse;
+
+use async_trait::async_trait;
+use chrono::{Duration, NaiveDate, TimeZone, Utc};
+use tracing::{instrument, warn};
+
+use crate::orchestrator::router::RouterIntent;
+use crate::orchestrator::routing::classifier::SpecialistProfile;
+use crate::rag::{
+    MemoryDeleteRequest, MemoryFilters, MemoryGetRequest, MemoryPatch, MemoryPatchRequest,
+    MemoryQuery, MemoryRecord, MemoryRequest, SharedRagAgent,
+};
+
+use super::traits::{AgentBehavior, AgentRequest, AgentResponse};
+
+const DEFAULT_LIMIT: usize = 5;
+/// Results fetched per query; filters are applied client-side on some backends, so over-fetch.
+const FETCH_LIMIT: usize = 50;
+/// Query text for listings that carry no search terms of their own.
+const LIST_QUERY: &str = "recent memories";
+/// Prefixes of the ids the Helix and mock stores assign (`chunk-<millis>`, `mock-memory-<n>`).
+const ID_PREFIXES: &[&str] = &["chunk", "mock-memory"];
+
+/// Words that frame a memory request without being part of what to search for.
+const FILLER: &[&str] = &[
+    "a",
+    "about",
+    "all",
+    "any",
+    "anything",
+    "everything",
+    "for",
+    "from",
+    "in",
+    "me",
+    "memories",
+    "memory",
+    "my",
+    "of",
+    "on",
+    "our",
+    "please",
+    "stored",
+    "saved",
+    "that",
+    "the",
+    "what",
+    "whats",
+    "with",
+    "we",
+    "have",
+    "do",
+    "is",
+    "are",
+    "there",
+    "notes",
+    "note",
+    "i",
+    "did",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum MemoryCommand {
+    List {
+        filters: MemoryFilters,
+        limit: usize,
+    },
+    Search {
+        query: String,
+        filters: MemoryFilters,
+        limit: usize,
+    },
+    Show {
+        id: String,
+    },
+    Forget {
+        id: String,
+    },
+    Retag {
+        id: String,
+        tags: Vec<String>,
+    },
+    Update {
+        id: String,
+        patch: MemoryPatch,
+    },
+    /// A verb that needs a memory id, given without one.
+    MissingId {
+        verb: &'static str,
+    },
+}
+
+/// Memory specialist behind the `RagAgent` route: lists, searches, shows, edits and forgets
+/// stored memories from plain-language requests, without an LLM round-trip.
+pub struct MemoryAgent {
+    rag_agent: SharedRagAgent,
+}
+
+impl MemoryAgent {
+    pub fn new(rag_agent: SharedRagAgent) -> Self {
+        Self { rag_agent }
+    }
+
+    /// Classifier roster entry for the `RagAgent` route.
+    pub fn profile() -> SpecialistProfile {
+        SpecialistProfile {
+            name: String::from("RagAgent"),
+            intent: RouterIntent::Memory,
+            description: String::from(
+                "stored memories: list, search, show, forget or re-tag saved notes and indexed files",
+            ),
+        }
+    }
+
+    async fn retrieve(
+        &self,
+        query: String,
+        filters: MemoryFilters,
+    ) -> anyhow::Result<Vec<MemoryRecord>> {
+        let response = self
+            .rag_agent
+            .handle(MemoryRequest::Retrieve(MemoryQuery {
+                query,
+                filters: filters.clone(),
+                limit: FETCH_LIMIT,
+            }))
+            .await?;
+        Ok(response
+            .records
+            .into_iter()
+            .filter(|record| filters.matches(record))
+            .collect())
+    }
+
+    /// Front-desk "remind me ..." lookups. Leftover words that name a memory's topic or one of
+    /// its tags narrow the hits to those memories; otherwise the similarity hits are listed.
+    pub async fn recall(&self, hints: &str) -> anyhow::Result<String> {
+        let words = split_words(hints);
+        let (filters, limit, terms) = parse_filters(&words);
+        if terms.is_empty() {
+            return self.run(MemoryCommand::List { filters, limit }).await;
+        }
+
+        let query = terms.join(" ");
+        let mut records = self.retrieve(query.clone(), filters.clone()).await?;
+        let labelled: Vec<MemoryRecord> = records
+            .iter()
+            .filter(|record| is_labelled(record, &terms))
+            .cloned()
+            .collect();
+        if !labelled.is_empty() {
+            records = labelled;
+            records.sort_by_key(|record| Reverse(record.timestamp));
+        }
+        let title = format!("Saved memories for \"{query}\"");
+        Ok(render_listing(&title, &filters, &records, limit))
+    }
+
+    /// Runs `raw` when it is an edit command with a memory id right after the verb
+    /// ("update chunk-123: ...", "retag chunk-123 tags=a,b"); anything else, such as
+    /// "update v2 of the docs", is left alone.
+    pub async fn edit(&self, raw: &str) -> anyhow::Result<Option<String>> {
+        let words = split_words(raw);
+        let verb = words.first().map(|word| word.to_lowercase());
+        let is_edit = matches!(
+            verb.as_deref(),
+            Some("update" | "edit" | "retag" | "re-tag")
+        ) && words.get(1..2).and_then(find_id).is_some();
+        if !is_edit {
+            return Ok(None);
+        }
+        self.run(parse_command(raw)).await.map(Some)
+    }
+
+    async fn patch(&self, id: String, patch: MemoryPatch) -> anyhow::Result<String> {
+        let changes = describe_patch(&patch);
+        let response = self
+            .rag_agent
+            .handle(MemoryRequest::Patch(MemoryPatchRequest {
+                id: id.clone(),
+                patch,
+            }))
+            .await;
+        Ok(match response {
+            Ok(_) => format!("Updated memory id={id}: {changes}."),
+            Err(err) => {
+                warn!(?err, %id, "Memory update failed");
+                format!("I could not update memory id={id}. Verify the id and try again.")
+            }
+        })
+    }
+
+    async fn run(&self, command: MemoryCommand) -> anyhow::Result<String> {
+        match command {
+            MemoryCommand::List { filters, limit } => {
+                let query = list_query(&filters);
+                let mut records = self.retrieve(query, filters.clone()).await?;
+                records.sort_by_key(|record| Reverse(record.timestamp));
+                Ok(render_listing("Latest memories", &filters, &records, limit))
+            }
+            MemoryCommand::Search {
+                query,
+                filters,
+                limit,
+            } => {
+                let records = self.retrieve(query.clone(), filters.clone()).await?;
+                let title = format!("Memories matching \"{query}\"");
+                Ok(render_listing(&title, &filters, &records, limit))
+            }
+            MemoryCommand::Show { id } => {
+                let response = self
+                    .rag_agent
+                    .handle(MemoryRequest::Get(MemoryGetRequest { id: id.clone() }))
+                    .await?;
+                Ok(match response.records.first() {
+                    Some(record) => render_record(record),
+                    None => format!(
+                        "No memory with id={id} turned up. List memories to see the available ids."
+                    ),
+                })
+            }
+            MemoryCommand::Forget { id } => {
+                let response = self
+                    .rag_agent
+                    .handle(MemoryRequest::Delete(MemoryDeleteRequest { id: id.clone() }))
+                    .await;
+                Ok(match response {
+                    Ok(_) => format!("Deleted memory id={id}."),
+                    Err(err) => {
+                        warn!(?err, %id, "Memory delete failed");
+                        format!("I could not delete memory id={id}. Verify the id and try again.")
+                    }
+                })
+            }
+            MemoryCommand::Retag { id, tags } => {
+                if tags.is_empty() {
+                    return Ok(format!(
+                        "Which tags should memory id={id} get? Say e.g. 'retag {id} tags=a,b'."
+                    ));
+                }
+                let patch = MemoryPatch {
+                    tags: Some(tags),
+                    ..MemoryPatch::default()
+                };
+                self.patch(id, patch).await
+            }
+            MemoryCommand::Update { id, patch } => {
+                if patch.is_empty() {
+                    return Ok(format!(
+                        "What should change in memory id={id}? Say e.g. 'update {id}: new text' or 'update {id} topic=x tags=a,b'."
+                    ));
+                }
+                self.patch(id, patch).await
+            }
+            MemoryCommand::MissingId { verb } => Ok(format!(
+                "Tell me which memory id to {verb} (e.g., {verb} chunk-123). List memories to see ids."
+            )),
+        }
+    }
+}
+
+#[async_trait]
+impl AgentBehavior for MemoryAgent {
+    #[instrument(skip_all, fields(role = "RagAgent", input = %request.input))]
+    async fn handle(&self, request: AgentRequest) -> anyhow::Result<AgentResponse> {
+        let command = parse_command(&request.input);
+        let output = self.run(command).await?;
+        request.emit(&output);
+        Ok(AgentResponse::new(output))
+    }
+}
+
+fn split_words(raw: &str) -> Vec<String> {
+    raw.split_whitespace()
+        .map(|word| {
+            word.trim_matches(|c: char| ",.;!?'\"".contains(c))
+                .to_string()
+        })
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+/// Id named among `words` once filters ("last 7 days", "top 10", "since ...") are set aside.
+fn command_id(words: &[String]) -> Option<String> {
+    find_id(&parse_filters(words).2)
+}
+
+fn parse_command(raw: &str) -> MemoryCommand {
+    let words = split_words(raw);
+    let lower: Vec<String> = words.iter().map(|word| word.to_lowercase()).collect();
+    let verb = lower
+        .iter()
+        .position(|word| !matches!(word.as_str(), "please" | "can" | "could" | "you" | "now"));
+    let verb_word = verb.map(|idx| lower[idx].as_str()).unwrap_or_default();
+    let rest = verb.map(|idx| &words[idx + 1..]).unwrap_or_default();
+
+    match verb_word {
+        "forget" | "delete" | "remove" | "drop" => {
+            return match command_id(rest) {
+                Some(id) => MemoryCommand::Forget { id },
+                None => MemoryCommand::MissingId { verb: "forget" },
+            }
+        }
+        "retag" | "re-tag" | "tag" => {
+            return match command_id(rest) {
+                Some(id) => MemoryCommand::Retag {
+                    tags: parse_tags(rest, &id),
+                    id,
+                },
+                None => MemoryCommand::MissingId { verb: "retag" },
+            }
+        }
+        "update" | "edit" => {
+            return match command_id(rest) {
+                Some(id) => MemoryCommand::Update {
+                    patch: parse_patch(raw, &id),
+                    id,
+                },
+                None => MemoryCommand::MissingId { verb: "update" },
+            }
+        }
+        "show" | "open" | "view" | "get" | "display" => {
+            if let Some(id) = command_id(rest) {
+                return MemoryCommand::Show { id };
+            }
+        }
+        _ => {}
+    }
+
+    let (filters, limit, terms) = parse_filters(rest_after_verb(verb_word, rest, &words));
+    let query = terms.join(" ");
+    if query.is_empty() {
+        MemoryCommand::List { filters, limit }
+    } else {
+        MemoryCommand::Search {
+            query,
+            filters,
+            limit,
+        }
+    }
+}
+
+/// Words left once a recognised verb is removed; unrecognised requests are searched whole.
+fn rest_after_verb<'a>(verb: &str, rest: &'a [String], all: &'a [String]) -> &'a [String] {
+    const VERBS: &[&str] = &[
+        "list", "show", "browse", "search", "find", "lookup", "look", "recall", "query",
+    ];
+    if VERBS.contains(&verb) {
+        match rest.first().map(|word| word.to_lowercase()) {
+            Some(next) if next == "up" => &rest[1..],
+            _ => rest,
+        }
+    } else {
+        all
+    }
+}
+
+/// Pull `key=value` filters, `#tags` and phrases like "from CTOAgent", "topic architecture",
+/// "last 7 days", "this week" or "top 10" out of the words; what remains are search terms.
+fn parse_filters(words: &[String]) -> (MemoryFilters, usize, Vec<String>) {
+    let mut filters = MemoryFilters::default();
+    let mut limit = DEFAULT_LIMIT;
+    let mut terms = Vec::new();
+    let mut idx = 0;
+
+    while idx < words.len() {
+        let word = &words[idx];
+        let lower = word.to_lowercase();
+        let next = words.get(idx + 1);
+
+        if let Some((key, value)) = word.split_once('=') {
+            let value = value.trim();
+            match key.to_lowercase().as_str() {
+                "topic" => filters.topic = Some(value.to_string()),
+                "agent" => filters.agent_name = Some(value.to_string()),
+                "project" => filters.project = Some(value.to_string()),
+                "conversation" => filters.conversation_id = Some(value.to_string()),
+                "since" => filters.since = parse_since(value, None),
+                "tag" | "tags" => filters.tags.extend(split_tags(value)),
+                "limit" => limit = value.parse().unwrap_or(limit),
+                _ => terms.push(word.clone()),
+            }
+            idx += 1;
+            continue;
+        }
+
+        if let Some(tag) = lower.strip_prefix('#').filter(|tag| !tag.is_empty()) {
+            filters.tags.push(tag.to_string());
+            idx += 1;
+            continue;
+        }
+        if matches!(lower.as_str(), "today" | "yesterday") {
+            filters.since = parse_since(&lower, None);
+            idx += 1;
+            continue;
+        }
+
+        match (lower.as_str(), next) {
+            ("topic", Some(value)) => filters.topic = Some(value.clone()),
+            ("tagged", Some(value)) => filters.tags.extend(split_tags(value)),
+            ("this", Some(value)) if parse_since("1", Some(&value.to_lowercase())).is_some() => {
+                filters.since = parse_since("1", Some(&value.to_lowercase()))
+            }
+            ("project", Some(value)) => filters.project = Some(value.clone()),
+            ("agent", Some(value)) => filters.agent_name = Some(value.clone()),
+            ("from" | "by", Some(value)) if value.to_lowercase().ends_with("agent") => {
+                filters.agent_name = Some(value.clone())
+            }
+            ("since", Some(value)) => filters.since = parse_since(value, None),
+            ("last" | "past", Some(value)) => match value.parse::<usize>() {
+                Ok(count) => match words.get(idx + 2).map(|unit| unit.to_lowercase()) {
+                    Some(unit) if parse_since(&count.to_string(), Some(&unit)).is_some() => {
+                        filters.since = parse_since(&count.to_string(), Some(&unit));
+                        idx += 3;
+                        continue;
+                    }
+                    _ => limit = count,
+                },
+                Err(_) => match parse_since("1", Some(&value.to_lowercase())) {
+                    Some(since) => filters.since = Some(since),
+                    None => {
+                        terms.push(word.clone());
+                        idx += 1;
+                        continue;
+                    }
+                },
+            },
+            ("top", Some(value)) if value.parse::<usize>().is_ok() => {
+                limit = value.parse().unwrap_or(limit)
+            }
+            _ => {
+                if !FILLER.contains(&lower.trim_end_matches("'s")) {
+                    terms.push(word.clone());
+                }
+                idx += 1;
+                continue;
+            }
+        }
+        idx += 2;
+    }
+
+    (filters, limit.clamp(1, FETCH_LIMIT), terms)
+}
+
+/// `2026-01-31`, `7d`, or a count with a unit (`3` + `days`); also `today` / `yesterday`.
+fn parse_since(value: &str, unit: Option<&str>) -> Option<chrono::DateTime<Utc>> {
+    let value = value.to_lowercase();
+    match value.as_str() {
+        "today" => {
+            return Utc::now()
+                .date_naive()
+                .and_hms_opt(0, 0, 0)
+                .map(|dt| Utc.from_utc_datetime(&dt))
+        }
+        "yesterday" => return Some(Utc::now() - Duration::days(1)),
+        _ => {}
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(&value, "%Y-%m-%d") {
+        return date
+            .and_hms_opt(0, 0, 0)
+            .map(|dt| Utc.from_utc_datetime(&dt));
+    }
+
+    let (count, unit) = match unit {
+        Some(unit) => (value.parse::<i64>().ok()?, unit.to_string()),
+        None => {
+            let split = value.find(|c: char| !c.is_ascii_digit())?;
+            (
+                value[..split].parse::<i64>().ok()?,
+                value[split..].to_string(),
+            )
+        }
+    };
+    let span = match unit.trim_end_matches('s') {
+        "h" | "hour" => Duration::hours(count),
+        "d" | "day" => Duration::days(count),
+        "w" | "week" => Duration::weeks(count),
+        "m" | "month" => Duration::days(30 * count),
+        _ => return None,
+    };
+    Some(Utc::now() - span)
+}
+
+/// First memory id in `words`: an explicit `id=...`, or a word shaped like the ids the stores
+/// hand out. Bare numbers, dates and versions ("7", "2026-01-31", "v2") are not ids.
+fn find_id(words: &[String]) -> Option<String> {
+    words.iter().find_map(|word| {
+        let word = word.trim_end_matches(':');
+        match word.strip_prefix("id=") {
+            Some(id) => (!id.is_empty()).then(|| id.to_string()),
+            None => is_memory_id(word).then(|| word.to_string()),
+        }
+    })
+}
+
+/// `chunk-1700000000000` (Helix), `mock-memory-3` (mock store) or a bare node UUID.
+fn is_memory_id(word: &str) -> bool {
+    let lower = word.to_lowercase();
+    let prefixed = ID_PREFIXES.iter().any(|prefix| {
+        lower
+            .strip_prefix(prefix)
+            .and_then(|rest| rest.strip_prefix(['-', '_']))
+            .is_some_and(|rest| {
+                rest.chars().any(|c| c.is_ascii_digit())
+                    && rest
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || "-_".contains(c))
+            })
+    });
+    let uuid = lower.split('-').map(str::len).eq([8, 4, 4, 4, 12])
+        && lower.chars().all(|c| c.is_ascii_hexdigit() || c == '-');
+    prefixed || uuid
+}
+
+/// Tags from `tags=a,b`, or the words following "as" / "with" after the id.
+fn parse_tags(words: &[String], id: &str) -> Vec<String> {
+    let joined = words.join(" ");
+    let lower = joined.to_lowercase();
+    let tail = ["tags=", "tags:", "tag=", "tag:"]
+        .iter()
+        .find_map(|token| lower.find(token).map(|idx| &joined[idx + token.len()..]))
+        .or_else(|| {
+            [" as ", " with "]
+                .iter()
+                .find_map(|token| lower.find(token).map(|idx| &joined[idx + token.len()..]))
+        })
+        .unwrap_or_default();
+
+    tail.split(|c: char| c == ',' || c.is_whitespace())
+        .map(|tag| tag.trim().to_lowercase())
+        .filter(|tag| !tag.is_empty() && tag != id && tag != "and")
+        .collect()
+}
+
+/// Edits from "update <id> [key=value ...][: new text]": `tags=`, `topic=`, `project=` and
+/// `confidence=` set fields, and the text after the colon replaces the body.
+fn parse_patch(raw: &str, id: &str) -> MemoryPatch {
+    let tail = raw
+        .find(id)
+        .map(|idx| &raw[idx + id.len()..])
+        .unwrap_or_default();
+    let (fields, body) = match tail.split_once(':') {
+        Some((fields, body)) if fields.split_whitespace().all(|word| word.contains('=')) => {
+            (fields, Some(body.trim()))
+        }
+        _ => (tail, None),
+    };
+
+    let mut patch = MemoryPatch {
+        body: body.filter(|body| !body.is_empty()).map(str::to_string),
+        ..MemoryPatch::default()
+    };
+    for word in split_words(fields) {
+        let Some((key, value)) = word.split_once('=') else {
+            continue;
+        };
+        match key.to_lowercase().as_str() {
+            "tag" | "tags" => patch.tags = Some(split_tags(value)),
+            "topic" => patch.topic = Some(value.to_string()),
+            "project" => patch.project = Some(value.to_string()),
+            "confidence" => {
+                patch.confidence = value
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|confidence| (0.0..=1.0).contains(confidence))
+            }
+            _ => {}
+        }
+    }
+    patch
+}
+
+fn describe_patch(patch: &MemoryPatch) -> String {
+    let mut parts = Vec::new();
+    if let Some(body) = &patch.body {
+        let preview: String = body.chars().take(80).collect();
+        parts.push(format!("text \"{preview}\""));
+    }
+    if let Some(tags) = &patch.tags {
+        parts.push(format!("tags={}", tags.join(",")));
+    }
+    if let Some(topic) = &patch.topic {
+        parts.push(format!("topic={topic}"));
+    }
+    if let Some(project) = &patch.project {
+        parts.push(format!("project={project}"));
+    }
+    if let Some(confidence) = patch.confidence {
+        parts.push(format!("confidence={confidence:.2}"));
+    }
+    parts.join(" ")
+}
+
+fn split_tags(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|tag| tag.trim().to_lowercase())
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+/// Whether a term names the record's topic (or one of its dotted segments) or one of its tags.
+fn is_labelled(record: &MemoryRecord, terms: &[String]) -> bool {
+    let tags = record.tags();
+    terms.iter().any(|term| {
+        record.topic.eq_ignore_ascii_case(term)
+            || record
+                .topic
+                .split('.')
+                .any(|segment| segment.eq_ignore_ascii_case(term))
+            || tags.iter().any(|tag| tag.eq_ignore_ascii_case(term))
+    })
+}
+
+fn list_query(filters: &MemoryFilters) -> String {
+    let hints: Vec<&str> = [&filters.topic, &filters.project, &filters.agent_name]
+        .into_iter()
+        .flatten()
+        .map(String::as_str)
+        .collect();
+    if hints.is_empty() {
+        LIST_QUERY.to_string()
+    } else {
+        hints.join(" ")
+    }
+}
+
+fn metadata_str<'a>(record: &'a MemoryRecord, key: &str) -> Option<&'a str> {
+    record.metadata.as_ref()?.get(key)?.as_str()
+}
+
+fn describe_filters(filters: &MemoryFilters) -> String {
+    let mut parts = Vec::new();
+    if let Some(topic) = &filters.topic {
+        parts.push(format!("topic={topic}"));
+    }
+    if let Some(agent) = &filters.agent_name {
+        parts.push(format!("agent={agent}"));
+    }
+    if let Some(project) = &filters.project {
+        parts.push(format!("project={project}"));
+    }
+    if let Some(conversation) = &filters.conversation_id {
+        parts.push(format!("conversation={conversation}"));
+    }
+    if let Some(since) = &filters.since {
+        parts.push(format!("since={}", since.format("%Y-%m-%d")));
+    }
+    if !filters.tags.is_empty() {
+        parts.push(format!("tags={}", filters.tags.join(",")));
+    }
+    parts.join(" ")
+}
+
+fn render_listing(
+    title: &str,
+    filters: &MemoryFilters,
+    records: &[MemoryRecord],
+    limit: usize,
+) -> String {
+    let scope = describe_filters(filters);
+    let scope = if scope.is_empty() {
+        String::new()
+    } else {
+        format!(" ({scope})")
+    };
+    if records.is_empty() {
+        return format!("No memories found{scope}.");
+    }
+
+    let mut out = format!(
+        "{title}{scope}: showing {} of {}.",
+        limit.min(records.len()),
+        records.len()
+    );
+    for record in records.iter().take(limit) {
+        let tags = record.tags();
+        let tags = if tags.is_empty() {
+            String::new()
+        } else {
+            format!(" tags={}", tags.join(","))
+        };
+        out.push_str(&format!(
+            "\n- id={} topic={} agent={} at {}{tags} :: {}",
+            record.id.as_deref().unwrap_or("unknown"),
+            record.topic,
+            record.agent_name,
+            record.timestamp.format("%Y-%m-%d %H:%M"),
+            record.summary.lines().next().unwrap_or_default()
+        ));
+    }
+    out.push_str("\nSay 'show <id>' for the full memory or 'forget <id>' to delete it.");
+    out
+}
+
+fn render_record(record: &MemoryRecord) -> String {
+    let mut out = format!(
+        "Memory id={}\ntopic: {}\nagent: {}\nsaved: {}\nconfidence: {:.2}",
+        record.id.as_deref().unwrap_or("unknown"),
+        record.topic,
+        record.agent_name,
+        record.timestamp.to_rfc3339(),
+        record.confidence
+    );
+    if let Some(project) = &record.project {
+        out.push_str(&format!("\nproject: {project}"));
+    }
+    if let Some(path) = metadata_str(record, "path") {
+        out.push_str(&format!("\npath: {path}"));
+    }
+    let tags = record.tags();
+    if !tags.is_empty() {
+        out.push_str(&format!("\ntags: {}", tags.join(", ")));
+    }
+    out.push_str(&format!("\n\n{}", record.full_content.trim()));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::rag::agent::RagAgent;
+    use crate::rag::client::SharedRagClient;
+    use crate::rag::mock::MockRagClient;
+    use crate::rag::MemoryWriteRequest;
+
+    fn record(agent: &str, topic: &str, summary: &str) -> MemoryRecord {
+        MemoryRecord {
+            id: None,
+            agent_name: agent.to_string(),
+            topic: topic.to_string(),
+            project: None,
+            conversation_id: None,
+            timestamp: Utc::now(),
+            summary: summary.to_string(),
+            full_content: format!("{summary} in full"),
+            confidence: 0.6,
+            open_questions: Vec::new(),
+            perspectives: Vec::new(),
+            messages: Vec::new(),
+            artifacts: Vec::new(),
+            tool_calls: Vec::new(),
+            metadata: Some(json!({ "tags": ["q3"] })),
+        }
+    }
+
+    #[test]
+    fn parses_verbs_ids_and_filters() {
+        assert_eq!(
+            parse_command("forget chunk-123"),
+            MemoryCommand::Forget {
+                id: "chunk-123".into()
+            }
+        );
+        assert_eq!(
+            parse_command("please delete the memory"),
+            MemoryCommand::MissingId { verb: "forget" }
+        );
+        assert_eq!(
+            parse_command("retag chunk-9 tags=a,b"),
+            MemoryCommand::Retag {
+                id: "chunk-9".into(),
+                tags: vec!["a".into(), "b".into()]
+            }
+        );
+        assert_eq!(
+            parse_command("update chunk-9 topic=ops confidence=0.9: Use the new relay"),
+            MemoryCommand::Update {
+                id: "chunk-9".into(),
+                patch: MemoryPatch {
+                    body: Some("Use the new relay".into()),
+                    topic: Some("ops".into()),
+                    confidence: Some(0.9),
+                    ..MemoryPatch::default()
+                }
+            }
+        );
+
+        let MemoryCommand::List { filters, limit } =
+            parse_command("list memories from CTOAgent topic=architecture last 7 days")
+        else {
+            panic!("expected a listing");
+        };
+        assert_eq!(filters.agent_name.as_deref(), Some("CTOAgent"));
+        assert_eq!(filters.topic.as_deref(), Some("architecture"));
+        assert!(filters.since.is_some());
+        assert_eq!(limit, DEFAULT_LIMIT);
+
+        let MemoryCommand::List { filters, .. } =
+            parse_command("list tags=q3,Ops #helix this week")
+        else {
+            panic!("expected a listing");
+        };
+        assert_eq!(filters.tags, vec!["q3", "ops", "helix"]);
+        assert!(filters.since.is_some());
+
+        let MemoryCommand::Search { query, limit, .. } =
+            parse_command("search my memory for helix schema top 3")
+        else {
+            panic!("expected a search");
+        };
+        assert_eq!(query, "helix schema");
+        assert_eq!(limit, 3);
+    }
+
+    #[test]
+    fn numbers_dates_and_versions_are_not_ids() {
+        let MemoryCommand::List { filters, .. } =
+            parse_command("show memories from the last 7 days")
+        else {
+            panic!("expected a listing");
+        };
+        assert!(filters.since.is_some());
+
+        let MemoryCommand::List { limit, .. } = parse_command("show top 10") else {
+            panic!("expected a listing");
+        };
+        assert_eq!(limit, 10);
+
+        let MemoryCommand::List { filters, .. } = parse_command("show notes since 2026-01-31")
+        else {
+            panic!("expected a listing");
+        };
+        assert!(filters.since.is_some());
+
+        assert_eq!(
+            parse_command("delete my notes from 2024"),
+            MemoryCommand::MissingId { verb: "forget" }
+        );
+        assert_eq!(
+            parse_command("show id=7"),
+            MemoryCommand::Show { id: "7".into() }
+        );
+        assert_eq!(
+            parse_command("open mock-memory-3"),
+            MemoryCommand::Show {
+                id: "mock-memory-3".into()
+            }
+        );
+        assert_eq!(
+            parse_command("forget 550e8400-e29b-41d4-a716-446655440000"),
+            MemoryCommand::Forget {
+                id: "550e8400-e29b-41d4-a716-446655440000".into()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn lists_filtered_memories_with_ids_and_shows_one() {
+        let client: SharedRagClient = Arc::new(MockRagClient::default());
+        let rag = Arc::new(RagAgent::new(client));
+        for (agent, topic, summary) in [
+            ("CTOAgent", "architecture", "Split the router into tiers"),
+            ("Agent", "personal.note", "Buy fightstick parts"),
+        ] {
+            rag.handle(MemoryRequest::Write(MemoryWriteRequest {
+                record: record(agent, topic, summary),
+            }))
+            .await
+            .unwrap();
+        }
+        let agent = MemoryAgent::new(rag);
+
+        let listing = agent
+            .handle(AgentRequest::new("list memories topic=architecture"))
+            .await
+            .unwrap()
+            .output;
+        assert!(listing.contains("id=mock-memory-1"));
+        assert!(listing.contains("tags=q3"));
+        assert!(!listing.contains("fightstick"));
+
+        let shown = agent
+            .handle(AgentRequest::new("show mock-memory-2"))
+            .await
+            .unwrap()
+            .output;
+        assert!(shown.contains("Buy fightstick parts in full"));
+        let unknown = agent
+            .handle(AgentRequest::new("show mock-memory-9"))
+            .await
+            .unwrap()
+            .output;
+        assert!(unknown.starts_with("No memory with id=mock-memory-9 turned up."));
+
+        let forgotten = agent
+            .handle(AgentRequest::new("forget mock-memory-2"))
+            .await
+            .unwrap()
+            .output;
+        assert_eq!(forgotten, "Deleted memory id=mock-memory-2.");
+    }
+
+    #[tokio::test]
+    async fn retags_and_updates_memories_in_place() {
+        let client: SharedRagClient = Arc::new(MockRagClient::default());
+        let rag = Arc::new(RagAgent::new(client.clone()));
+        rag.handle(MemoryRequest::Write(MemoryWriteRequest {
+            record: record("Agent", "personal.note", "Buy fightstick parts"),
+        }))
+        .await
+        .unwrap();
+        let agent = MemoryAgent::new(rag);
+
+        let retagged = agent
+            .handle(AgentRequest::new(
+                "retag mock-memory-1 tags=hardware,arcade",
+            ))
+            .await
+            .unwrap()
+            .output;
+        assert_eq!(
+            retagged,
+            "Updated memory id=mock-memory-1: tags=hardware,arcade."
+        );
+
+        let updated = agent
+            .edit("update mock-memory-1 topic=hardware.build: Sanwa buttons and a Brook PCB")
+            .await
+            .unwrap()
+            .expect("edit command");
+        assert!(updated.starts_with("Updated memory id=mock-memory-1: text"));
+
+        let stored = client.get("mock-memory-1").await.unwrap().unwrap();
+        assert_eq!(stored.tags(), vec!["hardware", "arcade"]);
+        assert_eq!(stored.topic, "hardware.build");
+        assert_eq!(stored.full_content, "Sanwa buttons and a Brook PCB");
+
+        let missing = agent
+            .handle(AgentRequest::new("retag mock-memory-9 tags=x"))
+            .await
+            .unwrap()
+            .output;
+        assert!(missing.starts_with("I could not update memory id=mock-memory-9."));
+        assert!(agent
+            .edit("update the docs for the router")
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
@@ -1,7 +1,18 @@
 pub mod agent;
+pub mod definition;
+pub mod memory;
+pub mod pending;
+pub mod prompt;
+pub mod reflection;
 pub mod specialists;
+pub mod taxonomy;
+pub mod tools;
 pub mod traits;
 
 pub use agent::Agent;
-pub use specialists::{CTOAgent, OpsChainAgent, ResearcherAgent, SeniorEngineerAgent};
-pub use traits::{AgentBehavior, AgentRequest, AgentResponse};
+pub use definition::AgentCatalog;
+pub use memory::MemoryAgent;
+pub use reflection::{Reflection, Reflective};
+pub use specialists::Specialist;
+pub use taxonomy::TopicClassifier;
+pub use traits::{AgentBehavior, AgentRequest, AgentResponse, TurnContext};
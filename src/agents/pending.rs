@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::traits::AgentRequest;
+
+/// Session key used when a request carries no conversation (one-off CLI prompts).
+const DETACHED_SESSION: &str = "detached";
+
+/// Outcome of looking up a session's pending action.
+#[derive(Debug, PartialEq)]
+pub enum Pending<T> {
+    Active(T),
+    /// There was one, but it outlived the TTL and has been dropped.
+    Expired,
+    None,
+}
+
+/// Actions awaiting a follow-up from the user, one per session, dropped after `ttl`.
+pub struct PendingStore<T> {
+    ttl: Duration,
+    entries: Mutex<HashMap<String, (Instant, T)>>,
+}
+
+impl<T> PendingStore<T> {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Conversation id of the request, so replies in the same session find the action.
+    pub fn session_key(request: &AgentRequest) -> String {
+        request
+            .turn
+            .as_ref()
+            .map(|turn| turn.conversation_id.clone())
+            .unwrap_or_else(|| DETACHED_SESSION.to_string())
+    }
+
+    /// Park `action` for `session`, replacing whatever was pending there.
+    pub fn insert(&self, session: &str, action: T) {
+        let mut entries = self.entries.lock().expect("pending store poisoned");
+        let ttl = self.ttl;
+        entries.retain(|_, (created, _)| created.elapsed() < ttl);
+        entries.insert(session.to_string(), (Instant::now(), action));
+    }
+
+    /// Whether `session` has a parked action, expired or not (`take` tells them apart).
+    pub fn contains(&self, session: &str) -> bool {
+        self.entries
+            .lock()
+            .expect("pending store poisoned")
+            .contains_key(session)
+    }
+
+    /// Remove and return the session's pending action.
+    pub fn take(&self, session: &str) -> Pending<T> {
+        let mut entries = self.entries.lock().expect("pending store poisoned");
+        match entries.remove(session) {
+            Some((created, _)) if created.elapsed() >= self.ttl => Pending::Expired,
+            Some((_, action)) => Pending::Active(action),
+            None => Pending::None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn actions_are_per_session_and_expire() {
+        let store = PendingStore::new(Duration::from_secs(60));
+        store.insert("a", 1);
+        store.insert("b", 2);
+        store.insert("a", 3);
+        assert!(store.contains("a"));
+        assert_eq!(store.take("a"), Pending::Active(3));
+        assert!(!store.contains("a"));
+        assert_eq!(store.take("a"), Pending::None);
+        assert_eq!(store.take("b"), Pending::Active(2));
+
+        let stale = PendingStore::new(Duration::ZERO);
+        stale.insert("a", 1);
+        assert_eq!(stale.take("a"), Pending::Expired);
+    }
+}
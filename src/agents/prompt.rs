@@ -0,0 +1,354 @@
+use tracing::debug;
+
+use crate::llm_client::{ChatMessage, ChatRole, DEFAULT_CONTEXT_WINDOW};
+use crate::rag::MemoryRecord;
+use crate::usage::{estimate_message_tokens, estimate_tokens};
+
+use super::tools::render_memory_header;
+
+/// Upper bound on the tokens held back for the model's reply.
+const MAX_COMPLETION_RESERVE: usize = 4_096;
+/// Share (percent) of the space left after the system directive and request that retrieved
+/// memories may claim while history still wants room.
+const MEMORY_SHARE_PERCENT: usize = 60;
+/// A memory body is cut down rather than reduced to its summary only when this much still fits.
+const MIN_TRUNCATED_BODY_TOKENS: usize = 64;
+/// Framing tokens per chat message; matches `estimate_message_tokens`.
+const MESSAGE_OVERHEAD: usize = 4;
+const TRUNCATION_MARKER: &str = " …[truncated]";
+
+/// Token budget for one prompt, derived from the model's context window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PromptBudget {
+    pub context_window: usize,
+    /// Tokens kept free for the completion.
+    pub completion_reserve: usize,
+}
+
+impl Default for PromptBudget {
+    fn default() -> Self {
+        Self::for_window(DEFAULT_CONTEXT_WINDOW)
+    }
+}
+
+impl PromptBudget {
+    /// Reserve a quarter of the window (at most `MAX_COMPLETION_RESERVE`) for the reply.
+    pub fn for_window(context_window: usize) -> Self {
+        Self {
+            context_window,
+            completion_reserve: (context_window / 4).min(MAX_COMPLETION_RESERVE),
+        }
+    }
+
+    pub fn prompt_tokens(&self) -> usize {
+        self.context_window.saturating_sub(self.completion_reserve)
+    }
+
+    /// Ceiling for a block of retrieved memories, e.g. a `memory_search` tool result.
+    pub fn memory_tokens(&self) -> usize {
+        self.prompt_tokens() * MEMORY_SHARE_PERCENT / 100
+    }
+
+    /// Tokens one more message may use once `messages` are sent, net of its framing.
+    pub fn room_after(&self, messages: &[ChatMessage]) -> usize {
+        self.prompt_tokens()
+            .saturating_sub(estimate_message_tokens(messages) as usize + MESSAGE_OVERHEAD)
+    }
+}
+
+/// Assembles system directive + history + retrieved memories + user request within a budget.
+///
+/// The system directive is always sent whole. The request is only cut when it alone would
+/// overflow the window. Memories and history share what is left: lower-ranked memories are
+/// reduced to their summary line, then dropped; history loses its oldest turns first.
+pub struct PromptBuilder {
+    budget: PromptBudget,
+    system: String,
+    history: Vec<ChatMessage>,
+    memory_preamble: String,
+    memories: Vec<MemoryRecord>,
+    request: String,
+}
+
+impl PromptBuilder {
+    pub fn new(budget: PromptBudget, system: impl Into<String>) -> Self {
+        Self {
+            budget,
+            system: system.into(),
+            history: Vec::new(),
+            memory_preamble: String::new(),
+            memories: Vec::new(),
+            request: String::new(),
+        }
+    }
+
+    pub fn history(mut self, history: &[ChatMessage]) -> Self {
+        self.history = history.to_vec();
+        self
+    }
+
+    /// Retrieved memories, best match first, rendered under `preamble` ahead of the request.
+    pub fn memories(mut self, preamble: impl Into<String>, records: &[MemoryRecord]) -> Self {
+        self.memory_preamble = preamble.into();
+        self.memories = records.to_vec();
+        self
+    }
+
+    pub fn request(mut self, content: impl Into<String>) -> Self {
+        self.request = content.into();
+        self
+    }
+
+    pub fn build(self) -> Vec<ChatMessage> {
+        let available = self.budget.prompt_tokens();
+        let system_cost = tokens(&self.system) + MESSAGE_OVERHEAD;
+
+        let request_cap = available.saturating_sub(system_cost + MESSAGE_OVERHEAD);
+        let request = if tokens(&self.request) > request_cap {
+            debug!(
+                request_tokens = tokens(&self.request),
+                request_cap, "User request exceeds the prompt budget; truncating"
+            );
+            truncate_to_tokens(&self.request, request_cap)
+        } else {
+            self.request
+        };
+        let mut remaining =
+            available.saturating_sub(system_cost + tokens(&request) + MESSAGE_OVERHEAD);
+
+        // Memories may claim their share up front; history gets the rest, then any slack
+        // history leaves behind flows back to the memories.
+        let preamble_cost = tokens(&self.memory_preamble) + 1;
+        let memory_need = if self.memories.is_empty() {
+            0
+        } else {
+            preamble_cost + full_memory_tokens(&self.memories)
+        };
+        let memory_claim = memory_need.min(remaining * MEMORY_SHARE_PERCENT / 100);
+
+        let history = fit_history(&self.history, remaining - memory_claim);
+        remaining = remaining.saturating_sub(estimate_message_tokens(&history) as usize);
+
+        let memory_block = if self.memories.is_empty() || remaining <= preamble_cost {
+            String::new()
+        } else {
+            fit_memory_snippets(&self.memories, remaining - preamble_cost)
+        };
+
+        let user_content = if memory_block.is_empty() {
+            request
+        } else {
+            format!("{}\n{memory_block}\n\n{request}", self.memory_preamble)
+        };
+
+        let mut messages = Vec::with_capacity(history.len() + 2);
+        messages.push(ChatMessage::system(self.system));
+        messages.extend(history);
+        messages.push(ChatMessage::user(user_content));
+        messages
+    }
+}
+
+/// Render memories in rank order within `max_tokens`. Records are kept whole while they fit.
+/// The first that does not is cut short, leaving room for the summary lines of every record
+/// below it; those keep only their summary line, and whatever no longer fits is dropped.
+pub fn fit_memory_snippets(records: &[MemoryRecord], max_tokens: usize) -> String {
+    let headers: Vec<String> = records.iter().map(render_memory_header).collect();
+    let header_costs: Vec<usize> = headers.iter().map(|header| tokens(header) + 1).collect();
+
+    let mut entries = Vec::with_capacity(records.len());
+    let mut used = 0;
+    let mut whole = true;
+
+    for (rank, (record, header)) in records.iter().zip(headers).enumerate() {
+        let header_cost = header_costs[rank];
+
+        if whole {
+            let body_cost = tokens(&record.full_content) + 1;
+            if used + header_cost + body_cost <= max_tokens {
+                entries.push(format!("{header}\n{}", record.full_content));
+                used += header_cost + body_cost;
+                continue;
+            }
+
+            whole = false;
+            let lower_ranked: usize = header_costs[rank + 1..].iter().sum();
+            let room = max_tokens.saturating_sub(used + header_cost + lower_ranked + 1);
+            if room >= MIN_TRUNCATED_BODY_TOKENS {
+                let body = truncate_to_tokens(&record.full_content, room);
+                used += header_cost + tokens(&body) + 1;
+                entries.push(format!("{header}\n{body}"));
+                continue;
+            }
+        }
+
+        if used + header_cost > max_tokens {
+            break;
+        }
+        used += header_cost;
+        entries.push(header);
+    }
+
+    if entries.len() < records.len() {
+        debug!(
+            kept = entries.len(),
+            dropped = records.len() - entries.len(),
+            max_tokens,
+            "Dropped lower-ranked memories to fit the prompt budget"
+        );
+    }
+
+    entries.join("\n")
+}
+
+/// Keep the newest turns that fit in `max_tokens`. A leading summary (system message) is kept
+/// first when it fits, since it stands in for everything already dropped.
+fn fit_history(history: &[ChatMessage], max_tokens: usize) -> Vec<ChatMessage> {
+    let pinned_len = history
+        .iter()
+        .take_while(|message| message.role == ChatRole::System)
+        .count();
+    let (pinned, turns) = history.split_at(pinned_len);
+
+    let mut budget = max_tokens;
+    let pinned_cost = estimate_message_tokens(pinned) as usize;
+    let keep_pinned = pinned_cost <= budget;
+    if keep_pinned {
+        budget -= pinned_cost;
+    }
+
+    let mut start = turns.len();
+    for (idx, message) in turns.iter().enumerate().rev() {
+        let cost = estimate_message_tokens(std::slice::from_ref(message)) as usize;
+        if cost > budget {
+            break;
+        }
+        budget -= cost;
+        start = idx;
+    }
+    // Never open on a reply whose question was dropped.
+    while turns
+        .get(start)
+        .is_some_and(|message| message.role != ChatRole::User)
+    {
+        start += 1;
+    }
+
+    if start > 0 {
+        debug!(
+            dropped = start,
+            kept = turns.len() - start,
+            "Dropped oldest history turns to fit the prompt budget"
+        );
+    }
+
+    let mut kept = Vec::with_capacity(pinned_len + turns.len() - start);
+    if keep_pinned {
+        kept.extend_from_slice(pinned);
+    }
+    kept.extend_from_slice(&turns[start..]);
+    kept
+}
+
+fn full_memory_tokens(records: &[MemoryRecord]) -> usize {
+    records
+        .iter()
+        .map(|record| tokens(&render_memory_header(record)) + tokens(&record.full_content) + 2)
+        .sum()
+}
+
+fn tokens(text: &str) -> usize {
+    estimate_tokens(text) as usize
+}
+
+/// Cut `text` at a word boundary so it estimates to at most `max_tokens`, marking the cut.
+pub fn truncate_to_tokens(text: &str, max_tokens: usize) -> String {
+    let marker_chars = TRUNCATION_MARKER.chars().count();
+    let max_chars = (max_tokens * 4).saturating_sub(marker_chars);
+    if text.chars().count() <= max_tokens * 4 {
+        return text.to_string();
+    }
+
+    let cut = text
+        .char_indices()
+        .nth(max_chars)
+        .map(|(idx, _)| idx)
+        .unwrap_or(text.len());
+    let head = &text[..cut];
+    let head = match head.rfind(char::is_whitespace) {
+        Some(idx) if idx > cut / 2 => &head[..idx],
+        _ => head,
+    };
+    format!("{}{TRUNCATION_MARKER}", head.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+
+    fn record(rank: usize, body_chars: usize) -> MemoryRecord {
+        MemoryRecord {
+            id: Some(format!("mem-{rank}")),
+            agent_name: String::from("Indexer"),
+            topic: String::from("engineering"),
+            project: None,
+            conversation_id: None,
+            timestamp: Utc::now(),
+            summary: format!("summary {rank}"),
+            full_content: "word ".repeat(body_chars / 5),
+            confidence: 0.9,
+            open_questions: Vec::new(),
+            perspectives: Vec::new(),
+            messages: Vec::new(),
+            artifacts: Vec::new(),
+            tool_calls: Vec::new(),
+            metadata: None,
+        }
+    }
+
+    #[test]
+    fn lower_ranked_memories_are_trimmed_before_higher_ones() {
+        let records: Vec<_> = (0..5).map(|rank| record(rank, 1_200)).collect();
+        let rendered = fit_memory_snippets(&records, 600);
+
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert!(lines[0].ends_with("summary 0"));
+        assert_eq!(lines[1], records[0].full_content, "top record kept whole");
+        assert!(lines[2].ends_with("summary 1"));
+        assert!(lines[3].ends_with(TRUNCATION_MARKER));
+        assert!(lines[4..].iter().all(|line| line.contains(":: summary")));
+        assert!(lines.last().unwrap().ends_with("summary 4"));
+        assert!(tokens(&rendered) <= 600);
+    }
+
+    #[test]
+    fn builder_respects_window_and_keeps_newest_history() {
+        let history: Vec<ChatMessage> = (0..40)
+            .flat_map(|turn| {
+                [
+                    ChatMessage::user(format!("question {turn} {}", "x".repeat(400))),
+                    ChatMessage::assistant(format!("answer {turn} {}", "y".repeat(400))),
+                ]
+            })
+            .collect();
+        let records: Vec<_> = (0..5).map(|rank| record(rank, 1_200)).collect();
+        let budget = PromptBudget::for_window(4_096);
+
+        let messages = PromptBuilder::new(budget, "You are Agent.")
+            .history(&history)
+            .memories("Retrieved memories.", &records)
+            .request("User request:\nwhat changed?")
+            .build();
+
+        assert!(estimate_message_tokens(&messages) as usize <= budget.prompt_tokens());
+        assert_eq!(messages[0].role, ChatRole::System);
+        assert_eq!(messages[1].role, ChatRole::User);
+        assert!(messages[messages.len() - 2]
+            .content
+            .starts_with("answer 39"));
+        let user = &messages.last().unwrap().content;
+        assert!(user.starts_with("Retrieved memories.") && user.ends_with("what changed?"));
+    }
+}
@@ -0,0 +1,308 @@
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tracing::{debug, instrument, warn};
+
+use crate::llm_client::{chat_json, ChatMessage, JsonReply, SharedLlmClient};
+
+use super::prompt::{PromptBudget, PromptBuilder};
+use super::traits::{AgentBehavior, AgentRequest, AgentResponse};
+
+const DEFAULT_MIN_LENGTH: usize = 500;
+const DEFAULT_MAX_SECS: u64 = 20;
+const DEFAULT_KEYWORDS: &[&str] = &[
+    "architecture",
+    "roadmap",
+    "research",
+    "migration",
+    "security",
+    "production",
+];
+/// Agents whose output is data rather than prose; a critic would only risk mangling it.
+const DEFAULT_EXEMPT: &[&str] = &["RagAgent"];
+
+const CRITIC_DIRECTIVE: &str = "You review draft answers from Vidkosha Cortex specialists before they reach the user. Check the draft against the request for factual errors, missed constraints, unsupported claims and vague next steps. Reply with JSON only: {\"critique\": \"<2-4 short bullet points>\", \"revised\": \"<the full improved answer, keeping the draft's format>\"}. If the draft needs no changes, return it unchanged as revised.";
+
+#[derive(Debug, Deserialize)]
+struct CriticReply {
+    critique: String,
+    revised: String,
+}
+
+/// Settings for the critique pass, shared by every specialist it wraps.
+pub struct Reflection {
+    critic: SharedLlmClient,
+    min_length: usize,
+    keywords: Vec<String>,
+    max_time: Duration,
+    exempt: Vec<String>,
+}
+
+impl Reflection {
+    pub fn new(critic: SharedLlmClient) -> Self {
+        Self {
+            critic,
+            min_length: DEFAULT_MIN_LENGTH,
+            keywords: DEFAULT_KEYWORDS.iter().map(|k| k.to_string()).collect(),
+            max_time: Duration::from_secs(DEFAULT_MAX_SECS),
+            exempt: DEFAULT_EXEMPT.iter().map(|a| a.to_string()).collect(),
+        }
+    }
+
+    pub fn with_min_length(mut self, min_length: usize) -> Self {
+        self.min_length = min_length;
+        self
+    }
+
+    pub fn with_keywords(mut self, keywords: Vec<String>) -> Self {
+        self.keywords = keywords;
+        self
+    }
+
+    pub fn with_max_time(mut self, max_time: Duration) -> Self {
+        self.max_time = max_time;
+        self
+    }
+
+    /// Enabled by `REFLECTION_ENABLED`; tuned by `REFLECTION_MIN_LENGTH`, `REFLECTION_MAX_SECS`
+    /// and `REFLECTION_KEYWORDS` (comma-separated).
+    pub fn from_env(critic: SharedLlmClient) -> Option<Self> {
+        let enabled = env::var("REFLECTION_ENABLED")
+            .ok()
+            .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+        if !enabled {
+            return None;
+        }
+
+        let min_length = env::var("REFLECTION_MIN_LENGTH")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(DEFAULT_MIN_LENGTH);
+        let max_secs = env::var("REFLECTION_MAX_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_MAX_SECS);
+
+        let mut reflection = Self::new(critic)
+            .with_min_length(min_length)
+            .with_max_time(Duration::from_secs(max_secs));
+        if let Ok(raw) = env::var("REFLECTION_KEYWORDS") {
+            reflection = reflection.with_keywords(
+                raw.split(',')
+                    .map(|k| k.trim().to_lowercase())
+                    .filter(|k| !k.is_empty())
+                    .collect(),
+            );
+        }
+        Some(reflection)
+    }
+
+    pub fn applies_to(&self, agent_name: &str) -> bool {
+        !self.exempt.iter().any(|exempt| exempt == agent_name)
+    }
+
+    /// Long requests, or ones naming a high-stakes keyword, get a critique pass.
+    pub fn should_reflect(&self, input: &str) -> bool {
+        if input.trim().chars().count() >= self.min_length {
+            return true;
+        }
+        let lower = input.to_lowercase();
+        lower
+            .split(|c: char| !c.is_alphanumeric())
+            .any(|word| self.keywords.iter().any(|keyword| keyword == word))
+    }
+
+    fn critic_messages(&self, agent_name: &str, input: &str, draft: &str) -> Vec<ChatMessage> {
+        PromptBuilder::new(
+            PromptBudget::for_window(self.critic.context_window()),
+            CRITIC_DIRECTIVE,
+        )
+        .request(format!(
+            "Request:\n{}\n\nDraft answer from {agent_name}:\n{}",
+            input.trim(),
+            draft.trim()
+        ))
+        .build()
+    }
+
+    /// Critique `draft`; `Err` carries the reason to fall back to the first pass.
+    async fn critique(
+        &self,
+        agent_name: &str,
+        input: &str,
+        draft: &str,
+    ) -> Result<CriticReply, &'static str> {
+        let messages = self.critic_messages(agent_name, input, draft);
+        let call = chat_json::<CriticReply>(self.critic.as_ref(), &messages);
+        match tokio::time::timeout(self.max_time, call).await {
+            Ok(Ok(JsonReply::Valid { value, .. })) if !value.revised.trim().is_empty() => Ok(value),
+            Ok(Ok(_)) => Err("invalid"),
+            Ok(Err(err)) => {
+                warn!(?err, agent = agent_name, "Critic call failed");
+                Err("error")
+            }
+            Err(_) => {
+                warn!(
+                    agent = agent_name,
+                    max_secs = self.max_time.as_secs(),
+                    "Critic exceeded its time budget"
+                );
+                Err("timeout")
+            }
+        }
+    }
+}
+
+/// Decorator that runs a critique pass over a specialist's first answer and returns the
+/// refined version, keeping the first pass when the critic is slow or unusable.
+pub struct Reflective {
+    name: String,
+    inner: Arc<dyn AgentBehavior>,
+    reflection: Arc<Reflection>,
+}
+
+impl Reflective {
+    pub fn new(
+        name: impl Into<String>,
+        inner: Arc<dyn AgentBehavior>,
+        reflection: Arc<Reflection>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            inner,
+            reflection,
+        }
+    }
+}
+
+#[async_trait]
+impl AgentBehavior for Reflective {
+    #[instrument(skip_all, fields(agent = %self.name))]
+    async fn handle(&self, request: AgentRequest) -> anyhow::Result<AgentResponse> {
+        if !self.reflection.should_reflect(&request.input) {
+            return self.inner.handle(request).await;
+        }
+
+        // The draft is held back from the stream; only the final answer is sent.
+        let mut quiet = request.clone();
+        quiet.stream = None;
+        let mut response = self.inner.handle(quiet).await?;
+
+        let reflection = match self
+            .reflection
+            .critique(&self.name, &request.input, &response.output)
+            .await
+        {
+            Ok(reply) => {
+                debug!(agent = %self.name, "Answer refined by critic");
+                response.output = reply.revised.trim().to_string();
+                json!({ "status": "refined", "critique": reply.critique.trim() })
+            }
+            Err(status) => json!({ "status": status }),
+        };
+        request.emit(&response.output);
+
+        let mut metadata = response.metadata.take().unwrap_or_else(|| json!({}));
+        if let Value::Object(map) = &mut metadata {
+            map.insert(String::from("reflection"), reflection);
+        }
+        response.metadata = Some(metadata);
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm_client::LlmClient;
+
+    struct Scripted {
+        reply: &'static str,
+        delay: Duration,
+    }
+
+    #[async_trait]
+    impl LlmClient for Scripted {
+        async fn chat(&self, _messages: &[ChatMessage]) -> anyhow::Result<String> {
+            tokio::time::sleep(self.delay).await;
+            Ok(self.reply.to_string())
+        }
+    }
+
+    struct Draft;
+
+    #[async_trait]
+    impl AgentBehavior for Draft {
+        async fn handle(&self, _request: AgentRequest) -> anyhow::Result<AgentResponse> {
+            Ok(AgentResponse::new("Use one big database."))
+        }
+    }
+
+    fn reflective(reply: &'static str, delay_ms: u64) -> Reflective {
+        let critic = Arc::new(Scripted {
+            reply,
+            delay: Duration::from_millis(delay_ms),
+        });
+        let reflection = Reflection::new(critic).with_max_time(Duration::from_millis(50));
+        Reflective::new("CTOAgent", Arc::new(Draft), Arc::new(reflection))
+    }
+
+    #[test]
+    fn gates_on_length_and_keywords() {
+        let reflection =
+            Reflection::new(crate::llm_client::EchoLlmClient::shared()).with_min_length(20);
+        assert!(reflection.should_reflect("Draft the migration plan"));
+        assert!(reflection.should_reflect("please write me a longer request"));
+        assert!(!reflection.should_reflect("quick fix"));
+        assert!(!reflection.should_reflect("researchers"));
+        assert!(!reflection.applies_to("RagAgent"));
+    }
+
+    #[test]
+    fn critic_prompt_carries_request_and_draft() {
+        let reflection = Reflection::new(crate::llm_client::EchoLlmClient::shared());
+        let messages = reflection.critic_messages("CTOAgent", " plan it ", "Draft body\n");
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].content, CRITIC_DIRECTIVE);
+        assert_eq!(
+            messages[1].content,
+            "Request:\nplan it\n\nDraft answer from CTOAgent:\nDraft body"
+        );
+    }
+
+    #[tokio::test]
+    async fn refines_draft_and_falls_back_on_timeout() {
+        let agent = reflective(
+            r#"{"critique": "- single point of failure", "revised": "Shard the database."}"#,
+            0,
+        );
+        let response = agent
+            .handle(AgentRequest::new("architecture for billing"))
+            .await
+            .unwrap();
+        assert_eq!(response.output, "Shard the database.");
+        let meta = response.metadata.unwrap();
+        assert_eq!(meta["reflection"]["status"], "refined");
+        assert_eq!(meta["reflection"]["critique"], "- single point of failure");
+
+        let slow = reflective(r#"{"critique": "x", "revised": "y"}"#, 500);
+        let response = slow
+            .handle(AgentRequest::new("architecture for billing"))
+            .await
+            .unwrap();
+        assert_eq!(response.output, "Use one big database.");
+        assert_eq!(
+            response.metadata.unwrap()["reflection"]["status"],
+            "timeout"
+        );
+
+        // Below the gate the critic is never consulted.
+        let response = slow.handle(AgentRequest::new("hi")).await.unwrap();
+        assert!(response.metadata.is_none());
+    }
+}
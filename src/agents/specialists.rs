@@ -1,306 +1,422 @@
 use async_trait::async_trait;
+use futures::StreamExt;
+use serde_json::json;
 use tracing::{instrument, warn};
 
-use crate::llm_client::SharedLlmClient;
+use crate::llm_client::{ChatMessage, LlmClient, SharedLlmClient, TokenSink};
 use crate::rag::{MemoryFilters, MemoryQuery, MemoryRecord, MemoryRequest, SharedRagAgent};
 
-use super::traits::{AgentBehavior, AgentRequest, AgentResponse};
+use super::definition::{AgentCatalog, AgentDefinition};
+use super::prompt::{PromptBudget, PromptBuilder};
+use super::traits::{AgentBehavior, AgentRequest, AgentResponse, Handoff};
 
-fn format_prompt(
+const DEFAULT_RESPONSE_FORMAT: &str = "a concise markdown answer that ends with ## Next Actions";
+const HANDOFF_HINT: &str = "If part of the request belongs to another specialist, answer your own part first, then end with exactly one line:";
+
+const MEMORY_PREAMBLE: &str = "Retrieved memories, best match first. Use them when relevant and cite each one you rely on as [mem:<chunk>]. If you go beyond them, say it is general knowledge.";
+const GROUNDING_LIMIT: usize = 5;
+/// Hits fetched before scoping; some backends filter after top-k, so over-fetch like the
+/// memory specialist does.
+const GROUNDING_FETCH_LIMIT: usize = 50;
+
+fn format_messages(
+    budget: PromptBudget,
     directive: &str,
     body_hint: &str,
+    handoff_roster: &str,
     request: &AgentRequest,
-    context: Option<&str>,
-) -> String {
-    let mut prompt = String::from(directive.trim());
-
-    if let Some(ctx) = context.and_then(|raw| {
-        let trimmed = raw.trim();
-        (!trimmed.is_empty()).then_some(trimmed)
-    }) {
-        prompt.push_str("\n\nContext snippets:\n");
-        prompt.push_str(ctx);
+    memories: &[MemoryRecord],
+) -> Vec<ChatMessage> {
+    let mut system = String::from(directive.trim());
+    system.push_str("\n\nRespond with ");
+    system.push_str(body_hint.trim());
+    system.push('.');
+    if !handoff_roster.is_empty() {
+        system.push_str(&format!(
+            "\n\nOther specialists: {handoff_roster}.\n{HANDOFF_HINT}\n{} {{\"agent\": \"<name>\", \"reason\": \"<why>\", \"task\": \"<standalone request for them>\"}}",
+            Handoff::MARKER
+        ));
     }
 
-    prompt.push_str("\n\nUser brief:\n");
-    prompt.push_str(request.input.trim());
-    prompt.push_str("\n\nRespond with ");
-    prompt.push_str(body_hint.trim());
-    prompt.push('.');
-
-    prompt
+    PromptBuilder::new(budget, system)
+        .history(&request.history)
+        .memories(MEMORY_PREAMBLE, memories)
+        .request(format!("User brief:\n{}", request.input.trim()))
+        .build()
 }
 
-pub struct CTOAgent {
+/// A specialist built from an `AgentDefinition`: its prompt, response format and memory topic
+/// come from data, so adding one needs no new Rust type.
+pub struct Specialist {
+    name: String,
+    directive: String,
+    response_format: String,
+    memory_topic: Option<String>,
+    /// Specialists this one may hand work to; empty means no handoff hint.
+    handoff_roster: String,
     llm_client: SharedLlmClient,
     rag_agent: Option<SharedRagAgent>,
 }
 
-impl CTOAgent {
-    const DIRECTIVE: &'static str = "You are CTOAgent, the architecture strategist of Vidkosha Cortex. Restate constraints, articulate service boundaries, and surface trade-offs before recommending next steps.";
-    const AGENT_NAME: &'static str = "CTOAgent";
-    const DEFAULT_TOPIC: &'static str = "architecture";
-
-    pub fn new(llm_client: SharedLlmClient) -> Self {
+impl Specialist {
+    pub fn new(definition: &AgentDefinition, llm_client: SharedLlmClient) -> Self {
         Self {
+            name: definition.name.clone(),
+            directive: definition.system_prompt.clone(),
+            response_format: definition
+                .response_format
+                .clone()
+                .unwrap_or_else(|| String::from(DEFAULT_RESPONSE_FORMAT)),
+            memory_topic: definition.memory_topic.clone(),
+            handoff_roster: String::new(),
             llm_client,
             rag_agent: None,
         }
     }
 
-    #[allow(dead_code)]
+    /// Offer handoffs to the other specialists registered from `catalog`.
+    pub fn with_peers(mut self, catalog: &AgentCatalog) -> Self {
+        self.handoff_roster = catalog.handoff_roster(&self.name);
+        self
+    }
+
     pub fn with_rag(mut self, rag_agent: Option<SharedRagAgent>) -> Self {
         self.rag_agent = rag_agent;
         self
     }
 
-    fn compose_prompt(&self, request: &AgentRequest, context: Option<&str>) -> String {
-        format_prompt(
-            Self::DIRECTIVE,
-            "a structured brief with sections: ## Overview, ## Components, ## Trade-offs, ## Next Actions",
+    fn compose_messages(
+        &self,
+        request: &AgentRequest,
+        memories: &[MemoryRecord],
+    ) -> Vec<ChatMessage> {
+        format_messages(
+            PromptBudget::for_window(self.llm_client.context_window()),
+            &self.directive,
+            &self.response_format,
+            &self.handoff_roster,
             request,
-            context,
+            memories,
         )
     }
-
-    async fn build_context(&self, request: &AgentRequest) -> Option<String> {
-        fetch_recent_memories(
-            &self.rag_agent,
-            Self::AGENT_NAME,
-            Some(Self::DEFAULT_TOPIC),
-            &request.input,
-        )
-        .await
-    }
 }
 
 #[async_trait]
-impl AgentBehavior for CTOAgent {
-    #[instrument(skip_all, fields(role = "CTOAgent", input = %request.input))]
+impl AgentBehavior for Specialist {
+    #[instrument(skip_all, fields(role = %self.name, input = %request.input))]
     async fn handle(&self, request: AgentRequest) -> anyhow::Result<AgentResponse> {
-        let context = self.build_context(&request).await;
-        let prompt = self.compose_prompt(&request, context.as_deref());
-        let output = self.llm_client.complete(&prompt).await?;
-        Ok(AgentResponse::new(output))
+        let grounding = match self.rag_agent.as_ref() {
+            Some(rag) => {
+                fetch_grounding(
+                    rag,
+                    &self.name,
+                    self.memory_topic.as_deref(),
+                    &request.input,
+                )
+                .await
+            }
+            None => None,
+        };
+        let memories = grounding
+            .as_ref()
+            .map(|(_, records)| records.as_slice())
+            .unwrap_or_default();
+
+        let messages = self.compose_messages(&request, memories);
+        let output =
+            chat_holding_handoff(self.llm_client.as_ref(), &messages, request.stream.as_ref())
+                .await?;
+
+        let mut response = AgentResponse::from_model_output(&output);
+        if let Some((scope, records)) = grounding {
+            response.metadata = Some(json!({
+                "grounding": {
+                    "scope": scope,
+                    "memory_ids": records.iter().filter_map(|r| r.id.as_deref()).collect::<Vec<_>>(),
+                }
+            }));
+        }
+        Ok(response)
     }
 }
 
-pub struct SeniorEngineerAgent {
-    llm_client: SharedLlmClient,
-    rag_agent: Option<SharedRagAgent>,
-}
-
-impl SeniorEngineerAgent {
-    const DIRECTIVE: &'static str = "You are SeniorEngineerAgent, a pragmatic Rust engineer. Outline implementation steps, cite risks, and note verification commands.";
-    const AGENT_NAME: &'static str = "SeniorEngineerAgent";
-    const DEFAULT_TOPIC: &'static str = "engineering";
+/// Like `chat_with_sink`, but a line that starts (or may start) with the handoff marker is held
+/// back until more text shows it is not the trailing `HANDOFF:` line, so the user never sees
+/// the marker the router strips. Returns the full model output.
+async fn chat_holding_handoff(
+    llm: &dyn LlmClient,
+    messages: &[ChatMessage],
+    sink: Option<&TokenSink>,
+) -> anyhow::Result<String> {
+    let Some(sink) = sink else {
+        return llm.chat(messages).await;
+    };
 
-    pub fn new(llm_client: SharedLlmClient) -> Self {
-        Self {
-            llm_client,
-            rag_agent: None,
+    let mut stream = llm.chat_stream(messages).await?;
+    let mut output = String::new();
+    let mut sent = 0;
+    while let Some(delta) = stream.next().await {
+        output.push_str(&delta?);
+        let held = held_from(&output, sent);
+        if held > sent {
+            let _ = sink.send(output[sent..held].to_string());
+            sent = held;
         }
     }
 
-    #[allow(dead_code)]
-    pub fn with_rag(mut self, rag_agent: Option<SharedRagAgent>) -> Self {
-        self.rag_agent = rag_agent;
-        self
-    }
-
-    fn compose_prompt(&self, request: &AgentRequest, context: Option<&str>) -> String {
-        format_prompt(
-            Self::DIRECTIVE,
-            "## Changes (bullets), ## Verification (commands vs expectations), ## Next Actions",
-            request,
-            context,
-        )
-    }
-
-    async fn build_context(&self, request: &AgentRequest) -> Option<String> {
-        fetch_recent_memories(
-            &self.rag_agent,
-            Self::AGENT_NAME,
-            Some(Self::DEFAULT_TOPIC),
-            &request.input,
-        )
-        .await
+    let (answer, _) = Handoff::extract(&output);
+    if let Some(rest) = answer.get(sent..).filter(|rest| !rest.is_empty()) {
+        let _ = sink.send(rest.to_string());
     }
+    Ok(output)
 }
 
-#[async_trait]
-impl AgentBehavior for SeniorEngineerAgent {
-    #[instrument(skip_all, fields(role = "SeniorEngineerAgent", input = %request.input))]
-    async fn handle(&self, request: AgentRequest) -> anyhow::Result<AgentResponse> {
-        let context = self.build_context(&request).await;
-        let prompt = self.compose_prompt(&request, context.as_deref());
-        let output = self.llm_client.complete(&prompt).await?;
-        Ok(AgentResponse::new(output))
-    }
-}
+/// Byte offset from which `output` must be held back: the first line at or after `sent` that
+/// starts with the handoff marker, or is still too short to tell.
+fn held_from(output: &str, sent: usize) -> usize {
+    let mut start = if sent == 0 || output[..sent].ends_with('\n') {
+        sent
+    } else {
+        match output[sent..].find('\n') {
+            Some(idx) => sent + idx + 1,
+            None => return output.len(),
+        }
+    };
 
-pub struct ResearcherAgent {
-    llm_client: SharedLlmClient,
-    rag_agent: Option<SharedRagAgent>,
+    while start < output.len() {
+        let rest = &output[start..];
+        let (line, complete) = match rest.find('\n') {
+            Some(idx) => (&rest[..idx], true),
+            None => (rest, false),
+        };
+        let line = line.trim_start();
+        if line.starts_with(Handoff::MARKER) || (!complete && Handoff::MARKER.starts_with(line)) {
+            return start;
+        }
+        start += rest.find('\n').map_or(rest.len(), |idx| idx + 1);
+    }
+    output.len()
 }
 
-impl ResearcherAgent {
-    const DIRECTIVE: &'static str = "You are ResearcherAgent. Surface the most relevant knowledge, cite every claim, and flag open questions.";
-    const AGENT_NAME: &'static str = "ResearcherAgent";
-    const DEFAULT_TOPIC: &'static str = "research";
+/// Semantic search over the specialist's own memories (by agent, and topic when it has one),
+/// widened to every agent when that comes back empty. Returns the scope that answered.
+async fn fetch_grounding(
+    rag: &SharedRagAgent,
+    agent_name: &str,
+    topic: Option<&str>,
+    query_text: &str,
+) -> Option<(&'static str, Vec<MemoryRecord>)> {
+    let scoped = MemoryFilters {
+        agent_name: Some(agent_name.to_string()),
+        topic: topic.map(str::to_string),
+        ..MemoryFilters::default()
+    };
 
-    pub fn new(llm_client: SharedLlmClient) -> Self {
-        Self {
-            llm_client,
-            rag_agent: None,
+    for (scope, filters) in [("agent", scoped), ("all", MemoryFilters::default())] {
+        let query = MemoryQuery {
+            query: query_text.trim().to_string(),
+            filters: filters.clone(),
+            limit: GROUNDING_FETCH_LIMIT,
+        };
+        match rag.handle(MemoryRequest::Retrieve(query)).await {
+            Ok(response) => {
+                let records: Vec<MemoryRecord> = response
+                    .records
+                    .into_iter()
+                    .filter(|record| filters.matches(record))
+                    .take(GROUNDING_LIMIT)
+                    .collect();
+                if !records.is_empty() {
+                    return Some((scope, records));
+                }
+            }
+            Err(err) => {
+                warn!(
+                    ?err,
+                    agent = agent_name,
+                    scope,
+                    "Failed to fetch RAG grounding for specialist"
+                );
+                return None;
+            }
         }
     }
+    None
+}
 
-    #[allow(dead_code)]
-    pub fn with_rag(mut self, rag_agent: Option<SharedRagAgent>) -> Self {
-        self.rag_agent = rag_agent;
-        self
-    }
-
-    fn compose_prompt(&self, request: &AgentRequest, context: Option<&str>) -> String {
-        format_prompt(
-            Self::DIRECTIVE,
-            "## Findings (bulleted, cite sources), ## Sources (list), ## Next Steps, Confidence: <0-1>, Open Questions: <list>",
-            request,
-            context,
-        )
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use chrono::Utc;
+
+    use super::*;
+    use crate::llm_client::{EchoLlmClient, TokenStream};
+    use crate::rag::agent::RagAgent;
+    use crate::rag::client::RagClient;
+    use crate::rag::mock::MockRagClient;
+    use crate::rag::types::MemoryWriteResponse;
+    use crate::rag::{MemoryDeleteRequest, MemoryWriteRequest};
+
+    fn record(agent: &str, topic: &str, body: &str) -> MemoryRecord {
+        MemoryRecord {
+            id: None,
+            agent_name: agent.to_string(),
+            topic: topic.to_string(),
+            project: None,
+            conversation_id: None,
+            timestamp: Utc::now(),
+            summary: body.to_string(),
+            full_content: body.to_string(),
+            confidence: 0.8,
+            open_questions: Vec::new(),
+            perspectives: Vec::new(),
+            messages: Vec::new(),
+            artifacts: Vec::new(),
+            tool_calls: Vec::new(),
+            metadata: None,
+        }
     }
 
-    async fn build_context(&self, request: &AgentRequest) -> Option<String> {
-        fetch_recent_memories(
-            &self.rag_agent,
-            Self::AGENT_NAME,
-            Some(Self::DEFAULT_TOPIC),
-            &request.input,
-        )
+    #[tokio::test]
+    async fn grounds_on_own_memories_then_falls_back_to_all() {
+        let rag = Arc::new(RagAgent::new(Arc::new(MockRagClient::default())));
+        rag.handle(MemoryRequest::Write(MemoryWriteRequest {
+            record: record("Agent", "notes", "Billing runs nightly at 02:00."),
+        }))
+        .await
+        .unwrap();
+
+        let catalog = AgentCatalog::default();
+        let cto = Specialist::new(&catalog.definitions()[0], EchoLlmClient::shared())
+            .with_rag(Some(rag.clone()));
+
+        // No CTOAgent memories yet, so the front desk's note grounds the answer.
+        let response = cto.handle(AgentRequest::new("plan billing")).await.unwrap();
+        assert!(response.output.contains("chunk=mock-memory-1"));
+        assert!(response.output.contains("Billing runs nightly at 02:00."));
+        let grounding = &response.metadata.unwrap()["grounding"];
+        assert_eq!(grounding["scope"], "all");
+        assert_eq!(grounding["memory_ids"], json!(["mock-memory-1"]));
+
+        rag.handle(MemoryRequest::Write(MemoryWriteRequest {
+            record: record("CTOAgent", "architecture", "Split billing out."),
+        }))
         .await
+        .unwrap();
+        let response = cto.handle(AgentRequest::new("plan billing")).await.unwrap();
+        let grounding = &response.metadata.unwrap()["grounding"];
+        assert_eq!(grounding["scope"], "agent");
+        assert_eq!(grounding["memory_ids"], json!(["mock-memory-2"]));
     }
-}
 
-#[async_trait]
-impl AgentBehavior for ResearcherAgent {
-    #[instrument(skip_all, fields(role = "ResearcherAgent", input = %request.input))]
-    async fn handle(&self, request: AgentRequest) -> anyhow::Result<AgentResponse> {
-        let context = self.build_context(&request).await;
-        let prompt = self.compose_prompt(&request, context.as_deref());
-        let output = self.llm_client.complete(&prompt).await?;
-        Ok(AgentResponse::new(output))
-    }
-}
+    /// Vector-only backend: returns the first `limit` hits and leaves filtering to the caller.
+    struct TopK(Vec<MemoryRecord>);
 
-pub struct OpsChainAgent {
-    llm_client: SharedLlmClient,
-    rag_agent: Option<SharedRagAgent>,
-}
-
-impl OpsChainAgent {
-    const DIRECTIVE: &'static str = "You are OpsChainAgent. Model capacity, reliability, and ops/anchoring trade-offs with actionable next steps.";
-    const AGENT_NAME: &'static str = "OpsChainAgent";
-    const DEFAULT_TOPIC: &'static str = "operations";
-
-    pub fn new(llm_client: SharedLlmClient) -> Self {
-        Self {
-            llm_client,
-            rag_agent: None,
+    #[async_trait]
+    impl RagClient for TopK {
+        async fn write(&self, _request: MemoryWriteRequest) -> anyhow::Result<MemoryWriteResponse> {
+            anyhow::bail!("read-only store")
         }
-    }
 
-    #[allow(dead_code)]
-    pub fn with_rag(mut self, rag_agent: Option<SharedRagAgent>) -> Self {
-        self.rag_agent = rag_agent;
-        self
-    }
+        async fn query(&self, query: MemoryQuery) -> anyhow::Result<Vec<MemoryRecord>> {
+            Ok(self.0.iter().take(query.limit()).cloned().collect())
+        }
 
-    fn compose_prompt(&self, request: &AgentRequest, context: Option<&str>) -> String {
-        format_prompt(
-            Self::DIRECTIVE,
-            "## Options (table or bullets with cost + capacity), ## Recommendation, ## Risks, ## Next Actions",
-            request,
-            context,
-        )
+        async fn delete(&self, _request: MemoryDeleteRequest) -> anyhow::Result<()> {
+            anyhow::bail!("read-only store")
+        }
     }
 
-    async fn build_context(&self, request: &AgentRequest) -> Option<String> {
-        fetch_recent_memories(
-            &self.rag_agent,
-            Self::AGENT_NAME,
-            Some(Self::DEFAULT_TOPIC),
-            &request.input,
-        )
-        .await
+    #[tokio::test]
+    async fn over_fetches_before_scoping_to_the_agent() {
+        let mut records: Vec<MemoryRecord> = (1..=6)
+            .map(|n| MemoryRecord {
+                id: Some(format!("note-{n}")),
+                ..record("Agent", "notes", "Billing runs nightly.")
+            })
+            .collect();
+        records.push(MemoryRecord {
+            id: Some(String::from("cto-1")),
+            ..record("CTOAgent", "architecture", "Split billing out.")
+        });
+        let rag = Arc::new(RagAgent::new(Arc::new(TopK(records))));
+
+        let catalog = AgentCatalog::default();
+        let cto =
+            Specialist::new(&catalog.definitions()[0], EchoLlmClient::shared()).with_rag(Some(rag));
+        let response = cto.handle(AgentRequest::new("plan billing")).await.unwrap();
+        let grounding = &response.metadata.unwrap()["grounding"];
+        assert_eq!(grounding["scope"], "agent");
+        assert_eq!(grounding["memory_ids"], json!(["cto-1"]));
     }
-}
 
-#[async_trait]
-impl AgentBehavior for OpsChainAgent {
-    #[instrument(skip_all, fields(role = "OpsChainAgent", input = %request.input))]
-    async fn handle(&self, request: AgentRequest) -> anyhow::Result<AgentResponse> {
-        let context = self.build_context(&request).await;
-        let prompt = self.compose_prompt(&request, context.as_deref());
-        let output = self.llm_client.complete(&prompt).await?;
-        Ok(AgentResponse::new(output))
+    #[test]
+    fn handoff_hint_lists_the_other_registered_specialists() {
+        let catalog = AgentCatalog::default();
+        let system = |roster: &str| {
+            format_messages(
+                PromptBudget::for_window(8_192),
+                "You are CTOAgent.",
+                "a brief",
+                roster,
+                &AgentRequest::new("plan billing"),
+                &[],
+            )[0]
+            .content
+            .clone()
+        };
+
+        let with_peers = system(&catalog.handoff_roster("CTOAgent"));
+        assert!(with_peers.contains("OpsChainAgent: deployment, infrastructure"));
+        assert!(!with_peers.contains("CTOAgent: architecture"));
+        assert!(with_peers.contains(Handoff::MARKER));
+        assert!(!system("").contains(Handoff::MARKER));
     }
-}
-
-async fn fetch_recent_memories(
-    rag_agent: &Option<SharedRagAgent>,
-    agent_name: &str,
-    topic_hint: Option<&str>,
-    query_text: &str,
-) -> Option<String> {
-    let rag = rag_agent.as_ref()?;
-    let trimmed_query = query_text.trim();
-    let query_string = if trimmed_query.is_empty() {
-        format!("latest {agent_name} context")
-    } else {
-        trimmed_query.to_string()
-    };
 
-    let filters = MemoryFilters {
-        agent_name: Some(agent_name.to_string()),
-        topic: topic_hint.map(|topic| topic.to_string()),
-        ..MemoryFilters::default()
-    };
-
-    let query = MemoryQuery {
-        query: query_string,
-        filters,
-        limit: 3,
-    };
+    /// Streams a fixed answer in 4-byte deltas so the marker arrives split across chunks.
+    struct Chunked(&'static str);
 
-    match rag.handle(MemoryRequest::Retrieve(query)).await {
-        Ok(response) if !response.records.is_empty() => {
-            Some(render_memory_context(agent_name, &response.records))
+    #[async_trait]
+    impl LlmClient for Chunked {
+        async fn chat(&self, _messages: &[ChatMessage]) -> anyhow::Result<String> {
+            Ok(self.0.to_string())
         }
-        Ok(_) => None,
-        Err(err) => {
-            warn!(
-                ?err,
-                agent = agent_name,
-                "Failed to fetch RAG context for specialist"
-            );
-            None
+
+        async fn chat_stream(&self, _messages: &[ChatMessage]) -> anyhow::Result<TokenStream> {
+            let deltas: Vec<anyhow::Result<String>> = self
+                .0
+                .as_bytes()
+                .chunks(4)
+                .map(|chunk| Ok(String::from_utf8_lossy(chunk).into_owned()))
+                .collect();
+            Ok(Box::pin(futures::stream::iter(deltas)))
         }
     }
-}
-
-fn render_memory_context(agent_name: &str, records: &[MemoryRecord]) -> String {
-    let mut lines = Vec::with_capacity(records.len() + 1);
-    lines.push(format!("Latest {agent_name} memos:"));
 
-    for record in records {
-        lines.push(format!(
-            "- [{}] {} — {}",
-            record.timestamp.to_rfc3339(),
-            record.topic,
-            record.summary
-        ));
+    async fn streamed(answer: &'static str) -> (String, String) {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let output = chat_holding_handoff(&Chunked(answer), &[], Some(&tx))
+            .await
+            .unwrap();
+        drop(tx);
+        let mut seen = String::new();
+        while let Some(delta) = rx.recv().await {
+            seen.push_str(&delta);
+        }
+        (output, seen)
     }
 
-    lines.join("\n")
+    #[tokio::test]
+    async fn holds_back_the_handoff_line_while_streaming() {
+        let answer = "Add a cache.\nHANDOFF: {\"agent\": \"OpsChainAgent\", \"reason\": \"cost\", \"task\": \"Price it\"}";
+        let (output, seen) = streamed(answer).await;
+        assert_eq!(output, answer);
+        assert_eq!(seen, "Add a cache.\n");
+
+        // A marker that is not the last line is part of the answer and is released.
+        let answer = "HANDOFF: maybe later\nShip it.";
+        let (_, seen) = streamed(answer).await;
+        assert_eq!(seen, answer);
+    }
 }
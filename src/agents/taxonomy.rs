@@ -0,0 +1,643 @@
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use futures::future::try_join_all;
+use serde::Deserialize;
+use serde_json::json;
+use tracing::{debug, warn};
+
+use crate::cassette::wrap_embeddings_from_env;
+use crate::llm_client::{chat_json, ChatMessage, JsonReply, SharedLlmClient};
+use crate::orchestrator::routing::semantic::cosine_similarity;
+use crate::rag::config::RagConfig;
+use crate::rag::embed::{EmbeddingsProvider, OpenAiEmbeddingsClient};
+use crate::rag::topic_registry::{TopicEntry, TopicStore};
+
+const DEFAULT_THRESHOLD: f32 = 0.45;
+const DEFAULT_MARGIN: f32 = 0.05;
+/// Topics within the margin of the best one that the LLM may choose between.
+const TIE_BREAK_CANDIDATES: usize = 3;
+/// Nearest topics offered as possible parents when a new topic is proposed.
+const PARENT_HINTS: usize = 5;
+const EMBED_CONCURRENCY: usize = 8;
+/// How long a `ListTopics` result is reused before the taxonomy is listed again.
+const DEFAULT_LISTING_TTL: Duration = Duration::from_secs(300);
+/// Guard against parent cycles in hand-edited taxonomies.
+const MAX_DEPTH: usize = 8;
+
+#[derive(Debug, Deserialize)]
+struct TieBreak {
+    topic: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Proposal {
+    name: String,
+    #[serde(default)]
+    parent: Option<String>,
+    #[serde(default)]
+    description: String,
+}
+
+/// Topic picked for a save, with where it sits in the taxonomy.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TopicChoice {
+    pub topic: String,
+    /// Ancestors from the root down, e.g. `["hardware", "hardware.build"]`.
+    pub path: Vec<String>,
+    /// `taxonomy:embedding`, `taxonomy:llm` or `taxonomy:proposed`.
+    pub source: &'static str,
+}
+
+/// Save-time topic inference against the registered taxonomy (`ListTopics`). The saved text is
+/// embedded and compared with each topic's name and description; near-ties go to the LLM, and a
+/// new child topic is proposed and registered only when nothing clears the threshold.
+pub struct TopicClassifier {
+    store: Arc<dyn TopicStore>,
+    embedder: Arc<dyn EmbeddingsProvider>,
+    llm: SharedLlmClient,
+    threshold: f32,
+    margin: f32,
+    listing_ttl: Duration,
+    /// Last `ListTopics` result and when it was fetched.
+    listing: Mutex<Option<(Instant, Vec<TopicEntry>)>>,
+    /// Topic vectors keyed by the embedded text, so an edited description is embedded again.
+    vectors: Mutex<HashMap<String, Vec<f32>>>,
+}
+
+impl TopicClassifier {
+    pub fn new(
+        store: Arc<dyn TopicStore>,
+        embedder: Arc<dyn EmbeddingsProvider>,
+        llm: SharedLlmClient,
+    ) -> Self {
+        Self {
+            store,
+            embedder,
+            llm,
+            threshold: DEFAULT_THRESHOLD,
+            margin: DEFAULT_MARGIN,
+            listing_ttl: DEFAULT_LISTING_TTL,
+            listing: Mutex::new(None),
+            vectors: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn with_threshold(mut self, threshold: f32) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    pub fn with_margin(mut self, margin: f32) -> Self {
+        self.margin = margin.max(0.0);
+        self
+    }
+
+    pub fn with_listing_ttl(mut self, ttl: Duration) -> Self {
+        self.listing_ttl = ttl;
+        self
+    }
+
+    /// On unless `TOPIC_CLASSIFIER_ENABLED` is false; embeds through the `RAG_EMBEDDING_*`
+    /// backend, so it stays off when that is not configured. `TOPIC_LIST_TTL_SECS` sets how
+    /// long the topic listing is reused.
+    pub fn from_env(
+        store: Arc<dyn TopicStore>,
+        llm: SharedLlmClient,
+    ) -> anyhow::Result<Option<Self>> {
+        let enabled = env::var("TOPIC_CLASSIFIER_ENABLED")
+            .ok()
+            .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+            .unwrap_or(true);
+        if !enabled {
+            return Ok(None);
+        }
+
+        let Ok(embed_config) = RagConfig::from_env() else {
+            return Ok(None);
+        };
+        let embedder = wrap_embeddings_from_env(
+            Arc::new(OpenAiEmbeddingsClient::from_config(&embed_config)?),
+            &embed_config.embedding_model,
+        )?;
+
+        let threshold = env::var("TOPIC_MATCH_THRESHOLD")
+            .ok()
+            .and_then(|v| v.parse::<f32>().ok())
+            .unwrap_or(DEFAULT_THRESHOLD);
+        let margin = env::var("TOPIC_TIE_MARGIN")
+            .ok()
+            .and_then(|v| v.parse::<f32>().ok())
+            .unwrap_or(DEFAULT_MARGIN);
+        let listing_ttl = env::var("TOPIC_LIST_TTL_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_LISTING_TTL);
+
+        Ok(Some(
+            Self::new(store, embedder, llm)
+                .with_threshold(threshold)
+                .with_margin(margin)
+                .with_listing_ttl(listing_ttl),
+        ))
+    }
+
+    /// List and embed the taxonomy ahead of the first save; run it in the background at startup.
+    pub async fn warm_up(&self) -> anyhow::Result<usize> {
+        let topics = self.topics().await?;
+        self.topic_vectors(&topics).await?;
+        Ok(topics.len())
+    }
+
+    /// Best registered topic for `text`. Failures are logged and yield `None`, so the caller
+    /// keeps its default topic.
+    pub async fn classify(&self, text: &str) -> Option<TopicChoice> {
+        match self.try_classify(text).await {
+            Ok(choice) => choice,
+            Err(err) => {
+                warn!(
+                    ?err,
+                    "Topic classification failed; keeping the default topic"
+                );
+                None
+            }
+        }
+    }
+
+    async fn try_classify(&self, text: &str) -> anyhow::Result<Option<TopicChoice>> {
+        let topics = self.topics().await?;
+        if topics.is_empty() || text.trim().is_empty() {
+            return Ok(None);
+        }
+
+        let query = self.embedder.embed(text).await?;
+        let vectors = self.topic_vectors(&topics).await?;
+        let mut scored: Vec<(&TopicEntry, f32)> = topics
+            .iter()
+            .zip(&vectors)
+            .map(|(topic, vector)| (topic, cosine_similarity(&query, vector)))
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        let (best, best_score) = scored[0];
+        debug!(topic = %best.name, score = best_score, "Closest registered topic");
+        if best_score < self.threshold {
+            return self.propose(text, &topics, &scored).await;
+        }
+
+        let contenders: Vec<&TopicEntry> = scored
+            .iter()
+            .take(TIE_BREAK_CANDIDATES)
+            .filter(|(_, score)| best_score - score <= self.margin)
+            .map(|(topic, _)| *topic)
+            .collect();
+        if contenders.len() > 1 {
+            if let Some(picked) = self.tie_break(text, &contenders).await? {
+                return Ok(Some(choice(&topics, picked, "taxonomy:llm")));
+            }
+        }
+
+        Ok(Some(choice(&topics, best, "taxonomy:embedding")))
+    }
+
+    /// The registered topics, listed again once the cached listing is older than the TTL.
+    async fn topics(&self) -> anyhow::Result<Vec<TopicEntry>> {
+        if let Some((fetched, topics)) = self
+            .listing
+            .lock()
+            .expect("topic listing cache poisoned")
+            .as_ref()
+        {
+            if fetched.elapsed() < self.listing_ttl {
+                return Ok(topics.clone());
+            }
+        }
+
+        let topics = self.store.list_topics().await?;
+        *self.listing.lock().expect("topic listing cache poisoned") =
+            Some((Instant::now(), topics.clone()));
+        Ok(topics)
+    }
+
+    /// Vectors for `topics`, embedding the ones not cached yet in batches. Vectors for topics
+    /// that left the listing, or whose description changed, are dropped.
+    async fn topic_vectors(&self, topics: &[TopicEntry]) -> anyhow::Result<Vec<Vec<f32>>> {
+        let texts: Vec<String> = topics.iter().map(topic_text).collect();
+        let missing: Vec<&String> = {
+            let cache = self.vectors.lock().expect("topic vector cache poisoned");
+            texts
+                .iter()
+                .filter(|text| !cache.contains_key(*text))
+                .collect::<HashSet<_>>()
+                .into_iter()
+                .collect()
+        };
+
+        let mut embedded = Vec::with_capacity(missing.len());
+        for batch in missing.chunks(EMBED_CONCURRENCY) {
+            let vectors = try_join_all(batch.iter().map(|text| self.embed_topic(text))).await?;
+            embedded.extend(batch.iter().map(|text| (*text).clone()).zip(vectors));
+        }
+
+        let mut cache = self.vectors.lock().expect("topic vector cache poisoned");
+        cache.extend(embedded);
+        let current: HashSet<&String> = texts.iter().collect();
+        cache.retain(|text, _| current.contains(text));
+        Ok(texts
+            .iter()
+            .map(|text| cache.get(text).cloned().unwrap_or_default())
+            .collect())
+    }
+
+    async fn embed_topic(&self, text: &str) -> anyhow::Result<Vec<f32>> {
+        self.embedder
+            .embed(text)
+            .await
+            .with_context(|| format!("Failed to embed topic \"{text}\""))
+    }
+
+    async fn tie_break<'a>(
+        &self,
+        text: &str,
+        contenders: &[&'a TopicEntry],
+    ) -> anyhow::Result<Option<&'a TopicEntry>> {
+        let options = contenders
+            .iter()
+            .map(|topic| format!("- {}: {}", topic.name, topic_text(topic)))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let prompt = format!(
+            "Pick the topic that best fits the text. Reply with JSON only: {{\"topic\": \"<one of the names below>\"}}.\n\nTopics:\n{options}\n\nText:\n{text}"
+        );
+
+        let reply = chat_json::<TieBreak>(self.llm.as_ref(), &[ChatMessage::user(prompt)]).await?;
+        Ok(match reply {
+            JsonReply::Valid { value, .. } => {
+                let picked = contenders
+                    .iter()
+                    .find(|topic| topic.name.eq_ignore_ascii_case(value.topic.trim()))
+                    .copied();
+                if picked.is_none() {
+                    warn!(topic = %value.topic, "Topic tie-break named an unlisted topic");
+                }
+                picked
+            }
+            JsonReply::Invalid { reason } => {
+                warn!(%reason, "Topic tie-break returned unusable JSON");
+                None
+            }
+        })
+    }
+
+    /// Nothing fits: ask for a new child topic under the nearest branch and register it.
+    async fn propose(
+        &self,
+        text: &str,
+        topics: &[TopicEntry],
+        scored: &[(&TopicEntry, f32)],
+    ) -> anyhow::Result<Option<TopicChoice>> {
+        let nearest = scored
+            .iter()
+            .take(PARENT_HINTS)
+            .map(|(topic, _)| {
+                format!(
+                    "- {} (parent: {})",
+                    topic.name,
+                    topic.parent.as_deref().unwrap_or("none")
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let prompt = format!(
+            "None of the registered topics fits the text below. Propose one new topic. Reply with JSON only: {{\"name\": \"<lowercase dotted slug, e.g. hardware.fightstick>\", \"parent\": \"<existing parent topic or branch, or null>\", \"description\": \"<one short line>\"}}. Prefer a parent from the nearest topics.\n\nNearest topics:\n{nearest}\n\nText:\n{text}"
+        );
+
+        let proposal =
+            match chat_json::<Proposal>(self.llm.as_ref(), &[ChatMessage::user(prompt)]).await? {
+                JsonReply::Valid { value, .. } => value,
+                JsonReply::Invalid { reason } => {
+                    warn!(%reason, "Topic proposal returned unusable JSON");
+                    return Ok(None);
+                }
+            };
+
+        // Only nest under a topic or branch that is already registered; an invented parent
+        // would leave the new topic orphaned in the tree.
+        let parent = proposal.parent.as_deref().map(slug).filter(|p| {
+            topics
+                .iter()
+                .any(|t| &t.name == p || t.parent.as_ref() == Some(p))
+        });
+        let mut name = slug(&proposal.name);
+        if name.is_empty() {
+            return Ok(None);
+        }
+        if let Some(parent) = parent.as_deref() {
+            if !name.starts_with(&format!("{parent}.")) {
+                name = format!("{parent}.{name}");
+            }
+        }
+
+        // The model may hand back a topic that already exists; use it as is.
+        if let Some(existing) = topics.iter().find(|topic| topic.name == name) {
+            return Ok(Some(choice(topics, existing, "taxonomy:llm")));
+        }
+
+        let metadata = json!({
+            "description": proposal.description.trim(),
+            "parent": parent,
+            "status": "active",
+            "source": "agent.save",
+        });
+        // An unregistered topic would never be matched again, so fall back to the default.
+        if let Err(err) = self.store.register_topic(&name, &metadata).await {
+            warn!(?err, topic = %name, "Failed to register proposed topic");
+            return Ok(None);
+        }
+
+        let entry = TopicEntry {
+            name,
+            description: proposal.description.trim().to_string(),
+            parent,
+        };
+        // Add it to the cached listing so close saves match it before the next refresh.
+        if let Some((_, cached)) = self
+            .listing
+            .lock()
+            .expect("topic listing cache poisoned")
+            .as_mut()
+        {
+            cached.push(entry.clone());
+        }
+        Ok(Some(choice(topics, &entry, "taxonomy:proposed")))
+    }
+}
+
+fn choice(topics: &[TopicEntry], topic: &TopicEntry, source: &'static str) -> TopicChoice {
+    TopicChoice {
+        topic: topic.name.clone(),
+        path: ancestors(topics, topic.parent.as_deref()),
+        source,
+    }
+}
+
+/// Parent chain from the root down; parents that are not registered themselves end the chain.
+fn ancestors(topics: &[TopicEntry], parent: Option<&str>) -> Vec<String> {
+    let mut path = Vec::new();
+    let mut next = parent;
+    while let Some(name) = next {
+        if path.len() >= MAX_DEPTH || path.iter().any(|seen| seen == name) {
+            break;
+        }
+        path.push(name.to_string());
+        next = topics
+            .iter()
+            .find(|topic| topic.name == name)
+            .and_then(|topic| topic.parent.as_deref());
+    }
+    path.reverse();
+    path
+}
+
+/// What gets embedded for a topic: its name as words plus the description.
+fn topic_text(topic: &TopicEntry) -> String {
+    let words = topic.name.replace(['.', '_', '-'], " ");
+    if topic.description.is_empty() {
+        words
+    } else {
+        format!("{words}: {}", topic.description)
+    }
+}
+
+fn slug(value: &str) -> String {
+    value
+        .trim()
+        .to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("_")
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_'))
+        .collect::<String>()
+        .trim_matches('.')
+        .to_string()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use async_trait::async_trait;
+    use serde_json::Value;
+
+    use super::*;
+    use crate::llm_client::LlmClient;
+
+    #[derive(Default)]
+    pub(crate) struct StaticStore {
+        pub(crate) topics: Mutex<Vec<TopicEntry>>,
+        pub(crate) registered: Mutex<Vec<(String, Value)>>,
+        pub(crate) lists: AtomicUsize,
+        pub(crate) fail_register: bool,
+    }
+
+    impl StaticStore {
+        pub(crate) fn new(topics: Vec<TopicEntry>) -> Self {
+            Self {
+                topics: Mutex::new(topics),
+                ..Self::default()
+            }
+        }
+    }
+
+    #[async_trait]
+    impl TopicStore for StaticStore {
+        async fn list_topics(&self) -> anyhow::Result<Vec<TopicEntry>> {
+            self.lists.fetch_add(1, Ordering::SeqCst);
+            Ok(self.topics.lock().unwrap().clone())
+        }
+
+        async fn register_topic(&self, name: &str, metadata: &Value) -> anyhow::Result<String> {
+            anyhow::ensure!(!self.fail_register, "topic store unavailable");
+            self.registered
+                .lock()
+                .unwrap()
+                .push((name.to_string(), metadata.clone()));
+            Ok(name.to_string())
+        }
+    }
+
+    /// One axis per keyword so similarities are predictable; counts calls to check caching.
+    #[derive(Default)]
+    pub(crate) struct AxisEmbedder {
+        pub(crate) calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl EmbeddingsProvider for AxisEmbedder {
+        async fn embed(&self, text: &str) -> anyhow::Result<Vec<f32>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let text = text.to_lowercase();
+            Ok(["guitar", "recipe", "bread", "arcade"]
+                .iter()
+                .map(|axis| if text.contains(axis) { 1.0 } else { 0.0 })
+                .collect())
+        }
+    }
+
+    pub(crate) struct FixedLlm {
+        pub(crate) reply: &'static str,
+        pub(crate) calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl LlmClient for FixedLlm {
+        async fn chat(&self, _messages: &[ChatMessage]) -> anyhow::Result<String> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(self.reply.to_string())
+        }
+    }
+
+    pub(crate) fn topic(name: &str, description: &str, parent: Option<&str>) -> TopicEntry {
+        TopicEntry {
+            name: name.to_string(),
+            description: description.to_string(),
+            parent: parent.map(str::to_string),
+        }
+    }
+
+    pub(crate) fn seeded_store() -> StaticStore {
+        StaticStore::new(vec![
+            topic("hobby", "Hobbies", None),
+            topic(
+                "hobby.music",
+                "Playing guitar and other instruments",
+                Some("hobby"),
+            ),
+            topic("food.baking", "Bread and pastry recipe notes", Some("food")),
+            topic("food.recipes", "Recipe collection", Some("food")),
+        ])
+    }
+
+    fn classifier(reply: &'static str) -> (TopicClassifier, Arc<StaticStore>, Arc<FixedLlm>) {
+        classifier_with(seeded_store(), reply)
+    }
+
+    fn classifier_with(
+        store: StaticStore,
+        reply: &'static str,
+    ) -> (TopicClassifier, Arc<StaticStore>, Arc<FixedLlm>) {
+        let store = Arc::new(store);
+        let llm = Arc::new(FixedLlm {
+            reply,
+            calls: AtomicUsize::new(0),
+        });
+        let classifier = TopicClassifier::new(
+            store.clone(),
+            Arc::new(AxisEmbedder::default()),
+            llm.clone(),
+        )
+        .with_threshold(0.5)
+        .with_margin(0.3);
+        (classifier, store, llm)
+    }
+
+    #[tokio::test]
+    async fn clear_match_uses_embeddings_and_records_the_path() {
+        let (classifier, _, llm) = classifier("{}");
+        let choice = classifier.classify("new guitar strings").await.unwrap();
+        assert_eq!(choice.topic, "hobby.music");
+        assert_eq!(choice.path, vec!["hobby"]);
+        assert_eq!(choice.source, "taxonomy:embedding");
+        assert_eq!(llm.calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn near_tie_is_settled_by_the_llm() {
+        let (classifier, _, llm) = classifier(r#"{"topic": "food.baking"}"#);
+        let choice = classifier.classify("sourdough recipe").await.unwrap();
+        assert_eq!(choice.topic, "food.baking");
+        assert_eq!(choice.path, vec!["food"]);
+        assert_eq!(choice.source, "taxonomy:llm");
+        assert_eq!(llm.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn unmatched_text_proposes_and_registers_a_child_topic() {
+        let (classifier, store, _) = classifier(
+            r#"{"name": "Fightstick", "parent": "hobby", "description": "Arcade stick builds"}"#,
+        );
+        let choice = classifier.classify("arcade stick wiring").await.unwrap();
+        assert_eq!(choice.topic, "hobby.fightstick");
+        assert_eq!(choice.path, vec!["hobby"]);
+        assert_eq!(choice.source, "taxonomy:proposed");
+
+        let registered = store.registered.lock().unwrap();
+        assert_eq!(registered.len(), 1);
+        assert_eq!(registered[0].0, "hobby.fightstick");
+        assert_eq!(registered[0].1["parent"], "hobby");
+    }
+
+    #[tokio::test]
+    async fn proposals_drop_unknown_parents_and_are_reused() {
+        let (classifier, store, llm) = classifier(
+            r#"{"name": "Fightstick", "parent": "gadgets", "description": "Arcade stick builds"}"#,
+        );
+        let choice = classifier.classify("arcade stick wiring").await.unwrap();
+        assert_eq!(choice.topic, "fightstick");
+        assert!(choice.path.is_empty());
+        assert_eq!(store.registered.lock().unwrap()[0].1["parent"], Value::Null);
+
+        // The registered proposal is in the cached listing, so a close save matches it.
+        let again = classifier.classify("arcade button layout").await.unwrap();
+        assert_eq!(again.topic, "fightstick");
+        assert_eq!(again.source, "taxonomy:embedding");
+        assert_eq!(llm.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(store.lists.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn failed_registration_keeps_the_default_topic() {
+        let store = StaticStore {
+            fail_register: true,
+            ..seeded_store()
+        };
+        let (classifier, _, _) = classifier_with(
+            store,
+            r#"{"name": "fightstick", "parent": "hobby", "description": "Arcade stick builds"}"#,
+        );
+        assert!(classifier.classify("arcade stick wiring").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn listing_and_vectors_are_cached_until_they_change() {
+        let store = Arc::new(StaticStore::new(vec![topic("hobby.music", "Guitar", None)]));
+        let embedder = Arc::new(AxisEmbedder::default());
+        let classifier = TopicClassifier::new(
+            store.clone(),
+            embedder.clone(),
+            Arc::new(FixedLlm {
+                reply: "{}",
+                calls: AtomicUsize::new(0),
+            }),
+        );
+        assert_eq!(classifier.warm_up().await.unwrap(), 1);
+        classifier.classify("guitar").await.unwrap();
+        classifier.classify("guitar pedals").await.unwrap();
+        // One listing and one topic vector, plus one embed per classified text.
+        assert_eq!(store.lists.load(Ordering::SeqCst), 1);
+        assert_eq!(embedder.calls.load(Ordering::SeqCst), 3);
+
+        // Once the listing expires, an edited description is embedded again.
+        let classifier = classifier.with_listing_ttl(Duration::ZERO);
+        *store.topics.lock().unwrap() = vec![topic("hobby.music", "Guitar and bass", None)];
+        classifier.classify("guitar").await.unwrap();
+        assert_eq!(store.lists.load(Ordering::SeqCst), 2);
+        assert_eq!(embedder.calls.load(Ordering::SeqCst), 5);
+        assert_eq!(classifier.vectors.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,223 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+use crate::llm_client::{parse_json_reply, ToolCall, ToolSpec};
+use crate::rag::{
+    MemoryFilters, MemoryQuery, MemoryRecord, MemoryRequest, SharedRagAgent, ToolCallRecord,
+};
+
+use super::prompt::{fit_memory_snippets, PromptBudget};
+
+/// A callable capability the model can request by name with JSON arguments.
+#[async_trait]
+pub trait Tool: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn description(&self) -> &'static str;
+    /// JSON Schema describing the arguments object.
+    fn parameters(&self) -> Value;
+    /// Execute the tool; the returned text is fed back to the model verbatim.
+    async fn invoke(&self, arguments: Value) -> anyhow::Result<String>;
+
+    fn spec(&self) -> ToolSpec {
+        ToolSpec {
+            name: self.name().to_string(),
+            description: self.description().to_string(),
+            parameters: self.parameters(),
+        }
+    }
+}
+
+pub type SharedTool = Arc<dyn Tool>;
+
+/// Ordered set of tools advertised to the model for one agent.
+#[derive(Clone, Default)]
+pub struct ToolRegistry {
+    tools: Vec<SharedTool>,
+}
+
+impl ToolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_tool<T>(mut self, tool: T) -> Self
+    where
+        T: Tool + 'static,
+    {
+        self.tools.retain(|existing| existing.name() != tool.name());
+        self.tools.push(Arc::new(tool));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tools.is_empty()
+    }
+
+    pub fn specs(&self) -> Vec<ToolSpec> {
+        self.tools.iter().map(|tool| tool.spec()).collect()
+    }
+
+    fn get(&self, name: &str) -> Option<&SharedTool> {
+        self.tools.iter().find(|tool| tool.name() == name)
+    }
+
+    /// Run one model-requested call. Failures become the tool output so the model can recover.
+    pub async fn invoke(&self, call: &ToolCall) -> (String, ToolCallRecord) {
+        let arguments: Value = if call.arguments.trim().is_empty() {
+            json!({})
+        } else {
+            parse_json_reply::<Value>(&call.arguments)
+                .unwrap_or_else(|_| json!({ "raw": call.arguments }))
+        };
+
+        let result = match self.get(&call.name) {
+            Some(tool) => tool.invoke(arguments.clone()).await,
+            None => Err(anyhow::anyhow!("Unknown tool '{}'", call.name)),
+        };
+
+        let (output, status) = match result {
+            Ok(output) => (output, "ok"),
+            Err(err) => {
+                warn!(?err, tool = %call.name, "Tool invocation failed");
+                (format!("Tool {} failed: {err}", call.name), "error")
+            }
+        };
+
+        let record = ToolCallRecord {
+            tool_call_id: Some(call.id.clone()),
+            tool_name: call.name.clone(),
+            args_json: arguments,
+            result_summary: Some(output.chars().take(200).collect()),
+            created_at: Some(Utc::now()),
+            metadata: Some(json!({ "status": status })),
+        };
+
+        (output, record)
+    }
+}
+
+/// Semantic search over stored memories, backed by the RAG agent.
+pub struct MemorySearchTool {
+    rag: SharedRagAgent,
+    max_tokens: usize,
+}
+
+impl MemorySearchTool {
+    pub const NAME: &'static str = "memory_search";
+
+    pub fn new(rag: SharedRagAgent) -> Self {
+        Self {
+            rag,
+            max_tokens: PromptBudget::default().memory_tokens(),
+        }
+    }
+
+    /// Cap the snippet text returned to the model; lower-ranked matches are trimmed first.
+    pub fn with_token_budget(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = max_tokens;
+        self
+    }
+}
+
+#[async_trait]
+impl Tool for MemorySearchTool {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn description(&self) -> &'static str {
+        "Search Vidkosha Cortex memory (saved notes, indexed files, past transcripts) for snippets relevant to the request."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "query": {
+                    "type": "string",
+                    "description": "What to search for, phrased as a short natural-language query."
+                },
+                "limit": {
+                    "type": "integer",
+                    "minimum": 1,
+                    "maximum": 10,
+                    "description": "Maximum number of snippets to return (default 3)."
+                }
+            },
+            "required": ["query"]
+        })
+    }
+
+    async fn invoke(&self, arguments: Value) -> anyhow::Result<String> {
+        let query = arguments
+            .get("query")
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|q| !q.is_empty())
+            .ok_or_else(|| anyhow::anyhow!("memory_search requires a non-empty 'query'"))?
+            .to_string();
+        let limit = arguments
+            .get("limit")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(3)
+            .clamp(1, 10) as usize;
+
+        let memory_query = MemoryQuery {
+            query,
+            filters: MemoryFilters::default(),
+            limit,
+        };
+
+        info!(limit, query = %memory_query.query, "Memory tool invoked; querying RAG");
+        let results = self
+            .rag
+            .handle(MemoryRequest::Retrieve(memory_query))
+            .await?;
+
+        if results.records.is_empty() {
+            warn!("Memory tool returned no matches");
+            return Ok(String::from(
+                "No memories found in Helix. Answer from your own knowledge, and if prior context is needed, state that no stored memory matched.",
+            ));
+        }
+
+        info!(
+            count = results.records.len(),
+            "Memory tool returned matches"
+        );
+        Ok(format!(
+            "Relevant memories found. Cite path+chunk and agent with confidence when you use them. Blend in your own knowledge to fill gaps, and if you add anything not in the snippets, say it is general knowledge.\n{}",
+            fit_memory_snippets(&results.records[..limit.min(results.records.len())], self.max_tokens)
+        ))
+    }
+}
+
+/// Provenance line for one record (path/chunk/agent/confidence) ending with its summary.
+pub fn render_memory_header(record: &MemoryRecord) -> String {
+    let path = record
+        .metadata
+        .as_ref()
+        .and_then(|m| m.get("path"))
+        .and_then(|v| v.as_str())
+        .unwrap_or("");
+    let chunk_id = record
+        .metadata
+        .as_ref()
+        .and_then(|m| m.get("chunk_id"))
+        .and_then(|v| v.as_str())
+        .unwrap_or(record.id.as_deref().unwrap_or(""));
+    format!(
+        "- [{}] path={} chunk={} agent={} topic={} confidence={:.2} :: {}",
+        record.timestamp.to_rfc3339(),
+        path,
+        chunk_id,
+        record.agent_name,
+        record.topic,
+        record.confidence,
+        record.summary
+    )
+}
@@ -1,16 +1,111 @@
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 
+use crate::llm_client::{parse_json_reply, ChatMessage, TokenSink};
+use crate::rag::ToolCallRecord;
+
+/// Locates a request inside a conversation thread so transcripts can be linked.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TurnContext {
+    pub conversation_id: String,
+    /// Id assigned to the user message for this turn.
+    pub message_id: String,
+    /// Id reserved for the agent reply to this turn.
+    pub reply_message_id: String,
+    /// Previous agent reply this message follows, if any.
+    #[serde(default)]
+    pub reply_to: Option<String>,
+}
+
 /// Structured payload for messages entering the Agent network.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentRequest {
     pub input: String,
+    /// Prior conversation turns, oldest first, replayed ahead of the current input.
+    #[serde(default)]
+    pub history: Vec<ChatMessage>,
+    /// Conversation placement; absent for one-off requests outside a session.
+    #[serde(default)]
+    pub turn: Option<TurnContext>,
+    /// Optional sink for streaming the final answer while it is generated.
+    #[serde(skip)]
+    pub stream: Option<TokenSink>,
+    /// Run the deep reasoning pipeline instead of a single specialist.
+    #[serde(default)]
+    pub deep_reasoning: bool,
 }
 
 impl AgentRequest {
     pub fn new(input: impl Into<String>) -> Self {
         Self {
             input: input.into(),
+            history: Vec::new(),
+            turn: None,
+            stream: None,
+            deep_reasoning: false,
+        }
+    }
+
+    pub fn with_history(mut self, history: Vec<ChatMessage>) -> Self {
+        self.history = history;
+        self
+    }
+
+    pub fn with_turn(mut self, turn: TurnContext) -> Self {
+        self.turn = Some(turn);
+        self
+    }
+
+    pub fn with_stream(mut self, sink: TokenSink) -> Self {
+        self.stream = Some(sink);
+        self
+    }
+
+    pub fn with_deep_reasoning(mut self, deep_reasoning: bool) -> Self {
+        self.deep_reasoning = deep_reasoning;
+        self
+    }
+
+    /// Forward text that was produced without streaming so sink consumers still see it.
+    pub fn emit(&self, text: &str) {
+        if let Some(sink) = self.stream.as_ref() {
+            let _ = sink.send(text.to_string());
+        }
+    }
+}
+
+/// Request from one specialist to pass part of the work to another.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Handoff {
+    /// Registered specialist name, e.g. `OpsChainAgent`.
+    #[serde(alias = "agent")]
+    pub target: String,
+    pub reason: String,
+    /// What the target should do, phrased as a standalone request.
+    pub task: String,
+}
+
+impl Handoff {
+    /// Line prefix a model uses to ask for a handoff at the end of its answer.
+    pub const MARKER: &'static str = "HANDOFF:";
+
+    /// Split a trailing `HANDOFF: {...}` line off a model answer. Malformed markers are left in
+    /// the text untouched.
+    pub fn extract(output: &str) -> (String, Option<Handoff>) {
+        let trimmed = output.trim_end();
+        let (head, last) = match trimmed.rfind('\n') {
+            Some(idx) => (&trimmed[..idx], &trimmed[idx + 1..]),
+            None => ("", trimmed),
+        };
+        let Some(payload) = last.trim().strip_prefix(Self::MARKER) else {
+            return (output.to_string(), None);
+        };
+
+        match parse_json_reply::<Handoff>(payload) {
+            Ok(handoff) if !handoff.target.trim().is_empty() && !handoff.task.trim().is_empty() => {
+                (head.trim_end().to_string(), Some(handoff))
+            }
+            _ => (output.to_string(), None),
         }
     }
 }
@@ -20,6 +115,12 @@ impl AgentRequest {
 pub struct AgentResponse {
     pub output: String,
     pub metadata: Option<serde_json::Value>,
+    /// Tool invocations made while producing `output`, persisted with the transcript.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCallRecord>,
+    /// Set when the agent wants another specialist to continue; the router follows it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub handoff: Option<Handoff>,
 }
 
 impl AgentResponse {
@@ -27,14 +128,32 @@ impl AgentResponse {
         Self {
             output: output.into(),
             metadata: None,
+            tool_calls: Vec::new(),
+            handoff: None,
         }
     }
 
+    /// Response for a model answer that may end with a `HANDOFF:` line.
+    pub fn from_model_output(output: &str) -> Self {
+        let (output, handoff) = Handoff::extract(output);
+        Self {
+            handoff,
+            ..Self::new(output)
+        }
+    }
+
+    pub fn with_tool_calls(mut self, tool_calls: Vec<ToolCallRecord>) -> Self {
+        self.tool_calls = tool_calls;
+        self
+    }
+
     #[allow(dead_code)]
     pub fn with_metadata(output: impl Into<String>, metadata: serde_json::Value) -> Self {
         Self {
             output: output.into(),
             metadata: Some(metadata),
+            tool_calls: Vec::new(),
+            handoff: None,
         }
     }
 }
@@ -43,3 +162,23 @@ impl AgentResponse {
 pub trait AgentBehavior: Send + Sync {
     async fn handle(&self, request: AgentRequest) -> anyhow::Result<AgentResponse>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_trailing_handoff_line() {
+        let (output, handoff) = Handoff::extract(
+            "Use a queue between the services.\nHANDOFF: {\"agent\": \"OpsChainAgent\", \"reason\": \"cost\", \"task\": \"Price two extra nodes\"}\n",
+        );
+        assert_eq!(output, "Use a queue between the services.");
+        let handoff = handoff.unwrap();
+        assert_eq!(handoff.target, "OpsChainAgent");
+        assert_eq!(handoff.task, "Price two extra nodes");
+
+        let raw = "Done.\nHANDOFF: not json";
+        assert_eq!(Handoff::extract(raw), (raw.to_string(), None));
+        assert_eq!(Handoff::extract("No handoff here").1, None);
+    }
+}
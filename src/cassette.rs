@@ -0,0 +1,343 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{bail, Context};
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+use crate::llm_client::{
+    ChatMessage, ChatTurn, LlmClient, SharedLlmClient, ToolSpec, DEFAULT_CONTEXT_WINDOW,
+};
+use crate::rag::embed::EmbeddingsProvider;
+
+/// How a cassette treats prompts it has not seen before.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CassetteMode {
+    /// Always call the live backend and overwrite the stored response.
+    Record,
+    /// Serve stored responses; record misses when a live backend is available.
+    Replay,
+    /// Serve stored responses only; an unknown prompt is an error.
+    Strict,
+}
+
+impl CassetteMode {
+    fn parse(raw: &str) -> anyhow::Result<Self> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "record" => Ok(Self::Record),
+            "replay" => Ok(Self::Replay),
+            "strict" => Ok(Self::Strict),
+            other => {
+                bail!("Unknown VK_CORTEX_CASSETTE_MODE '{other}' (use record, replay, or strict)")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CassetteEntry {
+    /// The request that produced `response`, kept for reviewing fixture diffs.
+    request: Value,
+    response: Value,
+}
+
+/// JSON fixture file mapping request hashes to recorded responses.
+pub struct CassetteStore {
+    path: PathBuf,
+    mode: CassetteMode,
+    entries: Mutex<BTreeMap<String, CassetteEntry>>,
+}
+
+impl CassetteStore {
+    pub fn open(path: impl Into<PathBuf>, mode: CassetteMode) -> anyhow::Result<Self> {
+        let path = path.into();
+        let entries = if path.exists() {
+            let raw = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read cassette {}", path.display()))?;
+            serde_json::from_str(&raw)
+                .with_context(|| format!("Failed to parse cassette {}", path.display()))?
+        } else if mode == CassetteMode::Strict {
+            bail!(
+                "Strict cassette replay needs an existing fixture at {}",
+                path.display()
+            );
+        } else {
+            BTreeMap::new()
+        };
+
+        Ok(Self {
+            path,
+            mode,
+            entries: Mutex::new(entries),
+        })
+    }
+
+    pub fn mode(&self) -> CassetteMode {
+        self.mode
+    }
+
+    /// Stable key for a request: blake3 over its canonical JSON form.
+    fn key(request: &Value) -> String {
+        blake3::hash(request.to_string().as_bytes())
+            .to_hex()
+            .to_string()
+    }
+
+    fn lookup<T: DeserializeOwned>(&self, request: &Value) -> anyhow::Result<Option<T>> {
+        let key = Self::key(request);
+        let entries = self.entries.lock().expect("cassette lock poisoned");
+        match entries.get(&key) {
+            Some(entry) => Ok(Some(
+                serde_json::from_value(entry.response.clone())
+                    .with_context(|| format!("Cassette entry {key} has an unexpected shape"))?,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    fn store<T: Serialize>(&self, request: Value, response: &T) -> anyhow::Result<()> {
+        let key = Self::key(&request);
+        let mut entries = self.entries.lock().expect("cassette lock poisoned");
+        entries.insert(
+            key,
+            CassetteEntry {
+                request,
+                response: serde_json::to_value(response)?,
+            },
+        );
+
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let serialized = serde_json::to_string_pretty(&*entries)?;
+        fs::write(&self.path, serialized)
+            .with_context(|| format!("Failed to write cassette {}", self.path.display()))
+    }
+
+    /// Replay or record one request according to the cassette mode.
+    async fn play<T, F, Fut>(&self, request: Value, live: Option<F>) -> anyhow::Result<T>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnOnce() -> Fut,
+        Fut: std::future::Future<Output = anyhow::Result<T>>,
+    {
+        if self.mode != CassetteMode::Record {
+            if let Some(hit) = self.lookup(&request)? {
+                return Ok(hit);
+            }
+        }
+
+        let live = match (self.mode, live) {
+            (CassetteMode::Strict, _) | (_, None) => bail!(
+                "Cassette {} has no recording for request {} ({})",
+                self.path.display(),
+                Self::key(&request),
+                request
+            ),
+            (_, Some(live)) => live,
+        };
+
+        let response = live().await?;
+        self.store(request, &response)?;
+        Ok(response)
+    }
+}
+
+/// `LlmClient` that records live responses and replays them by prompt hash.
+pub struct CassetteLlmClient {
+    store: Arc<CassetteStore>,
+    inner: Option<SharedLlmClient>,
+    /// Registry entry this client serves. It is part of every key, so agents that send the
+    /// same prompt to different models never replay each other's recordings.
+    agent: String,
+    context_window: Option<usize>,
+}
+
+impl CassetteLlmClient {
+    /// `inner` may be `None` for pure replay (tests, CI).
+    pub fn new(
+        store: Arc<CassetteStore>,
+        inner: Option<SharedLlmClient>,
+        agent: impl Into<String>,
+    ) -> Self {
+        Self {
+            store,
+            inner,
+            agent: agent.into(),
+            context_window: None,
+        }
+    }
+
+    /// Window reported without a live client; it must match the recording's so prompts are
+    /// trimmed the same way.
+    pub fn with_context_window(mut self, context_window: usize) -> Self {
+        self.context_window = Some(context_window);
+        self
+    }
+}
+
+#[async_trait]
+impl LlmClient for CassetteLlmClient {
+    async fn chat(&self, messages: &[ChatMessage]) -> anyhow::Result<String> {
+        let request = json!({ "kind": "chat", "agent": self.agent, "messages": messages });
+        let live = self
+            .inner
+            .as_ref()
+            .map(|inner| move || async move { inner.chat(messages).await });
+        self.store.play(request, live).await
+    }
+
+    async fn chat_with_tools(
+        &self,
+        messages: &[ChatMessage],
+        tools: &[ToolSpec],
+    ) -> anyhow::Result<ChatTurn> {
+        let request = json!({
+            "kind": "chat_with_tools",
+            "agent": self.agent,
+            "messages": messages,
+            "tools": tools,
+        });
+        let live = self
+            .inner
+            .as_ref()
+            .map(|inner| move || async move { inner.chat_with_tools(messages, tools).await });
+        self.store.play(request, live).await
+    }
+
+    async fn chat_json_mode(&self, messages: &[ChatMessage]) -> anyhow::Result<String> {
+        let request =
+            json!({ "kind": "chat_json_mode", "agent": self.agent, "messages": messages });
+        let live = self
+            .inner
+            .as_ref()
+            .map(|inner| move || async move { inner.chat_json_mode(messages).await });
+        self.store.play(request, live).await
+    }
+
+    fn context_window(&self) -> usize {
+        self.context_window
+            .or_else(|| self.inner.as_ref().map(|inner| inner.context_window()))
+            .unwrap_or(DEFAULT_CONTEXT_WINDOW)
+    }
+}
+
+/// `EmbeddingsProvider` counterpart of `CassetteLlmClient`, keyed by the model and input text.
+pub struct CassetteEmbeddings {
+    store: Arc<CassetteStore>,
+    inner: Option<Arc<dyn EmbeddingsProvider>>,
+    model: String,
+}
+
+impl CassetteEmbeddings {
+    pub fn new(
+        store: Arc<CassetteStore>,
+        inner: Option<Arc<dyn EmbeddingsProvider>>,
+        model: impl Into<String>,
+    ) -> Self {
+        Self {
+            store,
+            inner,
+            model: model.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl EmbeddingsProvider for CassetteEmbeddings {
+    async fn embed(&self, text: &str) -> anyhow::Result<Vec<f32>> {
+        let request = json!({ "kind": "embed", "model": self.model, "text": text });
+        let live = self
+            .inner
+            .as_ref()
+            .map(|inner| move || async move { inner.embed(text).await });
+        self.store.play(request, live).await
+    }
+}
+
+/// Cassette settings from `VK_CORTEX_CASSETTE_DIR` / `VK_CORTEX_CASSETTE_MODE`; `None` when unset.
+fn store_from_env(file_name: &str) -> anyhow::Result<Option<Arc<CassetteStore>>> {
+    let dir = match env::var("VK_CORTEX_CASSETTE_DIR") {
+        Ok(dir) if !dir.trim().is_empty() => dir,
+        _ => return Ok(None),
+    };
+    let mode = match env::var("VK_CORTEX_CASSETTE_MODE") {
+        Ok(raw) => CassetteMode::parse(&raw)?,
+        Err(_) => CassetteMode::Replay,
+    };
+
+    let path = Path::new(&dir).join(file_name);
+    info!(path = %path.display(), ?mode, "Using LLM cassette");
+    Ok(Some(Arc::new(CassetteStore::open(path, mode)?)))
+}
+
+/// Shared LLM cassette (`<dir>/llm.json`) when cassettes are enabled.
+pub fn llm_store_from_env() -> anyhow::Result<Option<Arc<CassetteStore>>> {
+    store_from_env("llm.json")
+}
+
+/// Wrap an embeddings provider with `<dir>/embeddings.json` when cassettes are enabled.
+pub fn wrap_embeddings_from_env(
+    inner: Arc<dyn EmbeddingsProvider>,
+    model: &str,
+) -> anyhow::Result<Arc<dyn EmbeddingsProvider>> {
+    match store_from_env("embeddings.json")? {
+        Some(store) => {
+            if store.mode() == CassetteMode::Strict {
+                warn!("Strict embeddings cassette: live embedding calls are disabled");
+                return Ok(Arc::new(CassetteEmbeddings::new(store, None, model)));
+            }
+            Ok(Arc::new(CassetteEmbeddings::new(store, Some(inner), model)))
+        }
+        None => Ok(inner),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm_client::EchoLlmClient;
+
+    #[tokio::test]
+    async fn records_then_replays_strictly_without_a_backend() {
+        let path = env::temp_dir().join(format!(
+            "vk-cassette-{}-{}.json",
+            std::process::id(),
+            chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        ));
+        let prompt = [ChatMessage::system("be brief"), ChatMessage::user("hello")];
+
+        let recorder = CassetteLlmClient::new(
+            Arc::new(CassetteStore::open(&path, CassetteMode::Record).unwrap()),
+            Some(EchoLlmClient::shared()),
+            "Agent",
+        );
+        let recorded = recorder.chat(&prompt).await.unwrap();
+
+        let replayer = CassetteLlmClient::new(
+            Arc::new(CassetteStore::open(&path, CassetteMode::Strict).unwrap()),
+            None,
+            "Agent",
+        );
+        assert_eq!(replayer.chat(&prompt).await.unwrap(), recorded);
+
+        let miss = replayer.chat(&[ChatMessage::user("unknown")]).await;
+        assert!(miss.unwrap_err().to_string().contains("no recording"));
+
+        // Recordings are per agent: the same prompt for another model's agent is a miss.
+        let other = CassetteLlmClient::new(
+            Arc::new(CassetteStore::open(&path, CassetteMode::Strict).unwrap()),
+            None,
+            "CTOAgent",
+        );
+        assert!(other.chat(&prompt).await.is_err());
+
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,209 @@
+use serde::de::DeserializeOwned;
+use tracing::{debug, warn};
+
+use super::{ChatMessage, LlmClient};
+
+/// Follow-up prompts allowed after the first reply fails validation.
+const MAX_JSON_REPAIRS: usize = 1;
+
+/// Outcome of a structured-output call once any repair attempts are spent.
+#[derive(Debug)]
+pub enum JsonReply<T> {
+    Valid {
+        value: T,
+        repairs: usize,
+    },
+    /// The model never produced schema-valid JSON; callers fall back and should say so.
+    Invalid {
+        reason: String,
+    },
+}
+
+/// Ask for JSON, validate it against `T`, and re-prompt with the validation error if needed.
+/// Transport failures are returned as errors; schema failures come back as `JsonReply::Invalid`.
+pub async fn chat_json<T: DeserializeOwned>(
+    llm: &dyn LlmClient,
+    messages: &[ChatMessage],
+) -> anyhow::Result<JsonReply<T>> {
+    let mut transcript = messages.to_vec();
+    let mut repairs = 0;
+
+    loop {
+        let raw = llm.chat_json_mode(&transcript).await?;
+        let reason = match parse_json_reply::<T>(&raw) {
+            Ok(value) => return Ok(JsonReply::Valid { value, repairs }),
+            Err(reason) => reason,
+        };
+
+        if repairs >= MAX_JSON_REPAIRS {
+            warn!(%reason, repairs, "LLM reply failed JSON validation; giving up");
+            return Ok(JsonReply::Invalid { reason });
+        }
+
+        debug!(%reason, "LLM reply failed JSON validation; sending repair prompt");
+        repairs += 1;
+        transcript.push(ChatMessage::assistant(raw));
+        transcript.push(ChatMessage::user(format!(
+            "Your previous reply could not be used: {reason}. Reply again with only the corrected JSON, no prose and no code fences."
+        )));
+    }
+}
+
+/// Parse the first JSON value in `raw` that deserializes into `T`, tolerating prose and fences.
+pub fn parse_json_reply<T: DeserializeOwned>(raw: &str) -> Result<T, String> {
+    let candidates = extract_json_candidates(raw);
+    if candidates.is_empty() {
+        return Err(String::from("no JSON object or array found in the reply"));
+    }
+
+    let mut last_error = String::new();
+    for candidate in candidates {
+        match serde_json::from_str::<T>(candidate) {
+            Ok(value) => return Ok(value),
+            Err(err) => last_error = format!("JSON did not match the expected schema ({err})"),
+        }
+    }
+
+    Err(last_error)
+}
+
+/// Candidate JSON spans in priority order: fenced blocks first, then balanced `{...}`/`[...]` runs.
+pub fn extract_json_candidates(raw: &str) -> Vec<&str> {
+    const MAX_CANDIDATES: usize = 8;
+    let mut candidates = Vec::new();
+
+    let mut rest = raw;
+    while let Some(open) = rest.find("```") {
+        let after = &rest[open + 3..];
+        // Skip an optional language tag such as ```json.
+        let body_start = after.find('\n').map(|i| i + 1).unwrap_or(0);
+        let body = &after[body_start..];
+        match body.find("```") {
+            Some(close) => {
+                let block = body[..close].trim();
+                if !block.is_empty() {
+                    candidates.push(block);
+                }
+                rest = &body[close + 3..];
+            }
+            None => break,
+        }
+    }
+
+    for (start, ch) in raw.char_indices() {
+        if candidates.len() >= MAX_CANDIDATES {
+            break;
+        }
+        if ch != '{' && ch != '[' {
+            continue;
+        }
+        if let Some(end) = balanced_end(&raw[start..]) {
+            let span = &raw[start..start + end];
+            if !candidates.contains(&span) {
+                candidates.push(span);
+            }
+        }
+    }
+
+    candidates
+}
+
+/// Byte length of the balanced bracket run at the start of `text`, honoring JSON strings.
+fn balanced_end(text: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (idx, ch) in text.char_indices() {
+        if in_string {
+            match ch {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+
+        match ch {
+            '"' => in_string = true,
+            '{' | '[' => depth += 1,
+            '}' | ']' => {
+                depth = depth.checked_sub(1)?;
+                if depth == 0 {
+                    return Some(idx + ch.len_utf8());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use async_trait::async_trait;
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Label {
+        topic: String,
+    }
+
+    #[test]
+    fn extracts_json_from_fences_and_prose() {
+        let fenced = "Sure!\n```json\n{\"topic\": \"rag\"}\n```\nAnything else?";
+        assert_eq!(
+            parse_json_reply::<Label>(fenced).unwrap(),
+            Label {
+                topic: "rag".into()
+            }
+        );
+
+        let prose = "Here you go: {\"note\": \"}\"} then {\"topic\": \"router\"} done.";
+        assert_eq!(parse_json_reply::<Label>(prose).unwrap().topic, "router");
+
+        assert!(parse_json_reply::<Label>("no json here").is_err());
+    }
+
+    /// First reply is unusable prose; the repair prompt gets valid JSON.
+    #[derive(Default)]
+    struct RepairingClient {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl LlmClient for RepairingClient {
+        async fn chat(&self, messages: &[ChatMessage]) -> anyhow::Result<String> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                return Ok(String::from("The topic is rag."));
+            }
+            assert!(messages
+                .last()
+                .unwrap()
+                .content
+                .contains("could not be used"));
+            Ok(String::from("{\"topic\": \"rag\"}"))
+        }
+    }
+
+    #[tokio::test]
+    async fn repairs_once_then_reports_invalid() {
+        let client = RepairingClient::default();
+        let reply = chat_json::<Label>(&client, &[ChatMessage::user("label this as JSON")])
+            .await
+            .unwrap();
+        assert!(matches!(reply, JsonReply::Valid { repairs: 1, .. }));
+
+        let stubborn = crate::llm_client::EchoLlmClient;
+        let reply = chat_json::<Label>(&stubborn, &[ChatMessage::user("label this as JSON")])
+            .await
+            .unwrap();
+        assert!(matches!(reply, JsonReply::Invalid { .. }));
+    }
+}
@@ -0,0 +1,686 @@
+mod json;
+pub mod registry;
+
+use std::env;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use anyhow::Context;
+use async_openai::types::{
+    ChatCompletionMessageToolCall, ChatCompletionRequestAssistantMessageArgs,
+    ChatCompletionRequestMessage, ChatCompletionRequestSystemMessageArgs,
+    ChatCompletionRequestToolMessageArgs, ChatCompletionRequestUserMessageArgs,
+    ChatCompletionResponseFormat, ChatCompletionResponseFormatType, ChatCompletionStreamOptions,
+    ChatCompletionTool, ChatCompletionToolChoiceOption, ChatCompletionToolType, CompletionUsage,
+    CreateChatCompletionRequest, CreateChatCompletionRequestArgs, FunctionCall, FunctionObject,
+};
+use async_openai::{config::OpenAIConfig, Client as AsyncOpenAiClient};
+use async_trait::async_trait;
+use futures::stream::{self, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::instrument;
+
+use crate::retry::{disabled_openai_backoff, RetryPolicy};
+use crate::usage::{self, TokenUsage, UsageScope};
+
+pub use json::{chat_json, parse_json_reply, JsonReply};
+pub use registry::LlmRegistry;
+
+pub type SharedLlmClient = Arc<dyn LlmClient>;
+
+/// Incremental text deltas emitted by a streaming completion.
+pub type TokenStream = Pin<Box<dyn Stream<Item = anyhow::Result<String>> + Send>>;
+
+/// Receiving end for streamed tokens (REPL printer, SSE bridge, tests).
+pub type TokenSink = UnboundedSender<String>;
+
+/// Context window assumed for models we know nothing about.
+pub const DEFAULT_CONTEXT_WINDOW: usize = 8_192;
+
+/// Best-effort context window for common model families; unknown names get the default.
+pub fn known_context_window(model: &str) -> usize {
+    let model = model.to_ascii_lowercase();
+    const FAMILIES: &[(&str, usize)] = &[
+        ("gpt-4o", 128_000),
+        ("gpt-4.1", 1_000_000),
+        ("gpt-4-turbo", 128_000),
+        ("gpt-3.5", 16_385),
+        ("llama-3.1", 128_000),
+        ("llama3.1", 128_000),
+        ("llama-3.2", 128_000),
+        ("llama3.2", 128_000),
+        ("qwen2.5", 32_768),
+        ("mistral", 32_768),
+        ("mixtral", 32_768),
+    ];
+    FAMILIES
+        .iter()
+        .find(|(family, _)| model.contains(family))
+        .map(|(_, window)| *window)
+        .unwrap_or(DEFAULT_CONTEXT_WINDOW)
+}
+
+/// Window for an endpoint: its explicit setting, then `VK_CORTEX_LLM_CONTEXT_TOKENS`, then the
+/// model family.
+pub fn resolve_context_window(explicit: Option<usize>, model: &str) -> usize {
+    explicit
+        .or_else(|| {
+            env::var("VK_CORTEX_LLM_CONTEXT_TOKENS")
+                .ok()
+                .and_then(|v| v.parse::<usize>().ok())
+                .filter(|v| *v > 0)
+        })
+        .unwrap_or_else(|| known_context_window(model))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatRole {
+    System,
+    User,
+    Assistant,
+    Tool,
+}
+
+/// One entry in a chat transcript sent to the model.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: ChatRole,
+    pub content: String,
+    /// Required for `Tool` messages so the backend can pair them with the originating call.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+    /// Calls requested by an `Assistant` turn; replayed so tool results have a parent.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+}
+
+impl ChatMessage {
+    pub fn new(role: ChatRole, content: impl Into<String>) -> Self {
+        Self {
+            role,
+            content: content.into(),
+            tool_call_id: None,
+            tool_calls: Vec::new(),
+        }
+    }
+
+    pub fn system(content: impl Into<String>) -> Self {
+        Self::new(ChatRole::System, content)
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        Self::new(ChatRole::User, content)
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self::new(ChatRole::Assistant, content)
+    }
+
+    pub fn assistant_tool_calls(content: impl Into<String>, tool_calls: Vec<ToolCall>) -> Self {
+        Self {
+            tool_calls,
+            ..Self::assistant(content)
+        }
+    }
+
+    pub fn tool(tool_call_id: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            tool_call_id: Some(tool_call_id.into()),
+            ..Self::new(ChatRole::Tool, content)
+        }
+    }
+}
+
+/// Function-style tool advertised to the model (OpenAI `tools` entry).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolSpec {
+    pub name: String,
+    pub description: String,
+    /// JSON Schema for the arguments object.
+    pub parameters: Value,
+}
+
+/// A tool invocation requested by the model; `arguments` is the raw JSON string it produced.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCall {
+    pub id: String,
+    pub name: String,
+    pub arguments: String,
+}
+
+/// One assistant turn from a tool-enabled chat: text, requested tool calls, or both.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChatTurn {
+    pub content: String,
+    pub tool_calls: Vec<ToolCall>,
+}
+
+#[async_trait]
+pub trait LlmClient: Send + Sync {
+    /// Run a multi-turn chat. Backends prepend their default system prompt only when the
+    /// transcript does not start with one.
+    async fn chat(&self, messages: &[ChatMessage]) -> anyhow::Result<String>;
+
+    /// Stream the chat reply as it is generated. Backends without native streaming
+    /// yield the finished reply as a single delta.
+    async fn chat_stream(&self, messages: &[ChatMessage]) -> anyhow::Result<TokenStream> {
+        let output = self.chat(messages).await?;
+        Ok(Box::pin(stream::once(async move { Ok(output) })))
+    }
+
+    /// Chat with tools advertised. Backends without native tool calling answer in text only.
+    async fn chat_with_tools(
+        &self,
+        messages: &[ChatMessage],
+        _tools: &[ToolSpec],
+    ) -> anyhow::Result<ChatTurn> {
+        let content = self.chat(messages).await?;
+        Ok(ChatTurn {
+            content,
+            tool_calls: Vec::new(),
+        })
+    }
+
+    /// Chat while asking the backend to constrain output to a JSON object where supported.
+    /// Prefer `chat_json`, which also extracts, validates, and repairs the reply.
+    async fn chat_json_mode(&self, messages: &[ChatMessage]) -> anyhow::Result<String> {
+        self.chat(messages).await
+    }
+
+    /// Total tokens (prompt + completion) the backing model accepts; prompt builders budget
+    /// against this.
+    fn context_window(&self) -> usize {
+        DEFAULT_CONTEXT_WINDOW
+    }
+}
+
+/// Run a chat, forwarding deltas to `sink` when present, and return the full text.
+pub async fn chat_with_sink(
+    llm: &dyn LlmClient,
+    messages: &[ChatMessage],
+    sink: Option<&TokenSink>,
+) -> anyhow::Result<String> {
+    let sink = match sink {
+        Some(sink) => sink,
+        None => return llm.chat(messages).await,
+    };
+
+    let mut stream = llm.chat_stream(messages).await?;
+    let mut output = String::new();
+    while let Some(delta) = stream.next().await {
+        let delta = delta?;
+        // A dropped receiver only means nobody is watching; keep collecting the answer.
+        let _ = sink.send(delta.clone());
+        output.push_str(&delta);
+    }
+
+    Ok(output)
+}
+
+/// Temporary stand-in until we wire a real LLM backend.
+#[derive(Debug, Default, Clone)]
+pub struct EchoLlmClient;
+
+#[async_trait]
+impl LlmClient for EchoLlmClient {
+    async fn chat(&self, messages: &[ChatMessage]) -> anyhow::Result<String> {
+        let prompt = messages
+            .iter()
+            .rev()
+            .find(|message| message.role == ChatRole::User)
+            .map(|message| message.content.as_str())
+            .unwrap_or_default();
+        Ok(format!(
+            "[stubbed Agent response]\nI received: {prompt}\nNext step: connect to LLM backend."
+        ))
+    }
+
+    async fn chat_stream(&self, messages: &[ChatMessage]) -> anyhow::Result<TokenStream> {
+        // Emit word-sized deltas so streaming consumers can be exercised offline.
+        let output = self.chat(messages).await?;
+        let deltas: Vec<anyhow::Result<String>> = output
+            .split_inclusive(char::is_whitespace)
+            .map(|piece| Ok(piece.to_string()))
+            .collect();
+        Ok(Box::pin(stream::iter(deltas)))
+    }
+}
+
+impl EchoLlmClient {
+    pub fn shared() -> SharedLlmClient {
+        Arc::new(Self)
+    }
+}
+
+/// Connection settings for one OpenAI-compatible endpoint.
+#[derive(Debug, Clone)]
+pub struct OpenAiEndpoint {
+    pub api_key: Option<String>,
+    pub base_url: Option<String>,
+    pub model: String,
+    pub temperature: Option<f32>,
+    pub system_prompt: Option<String>,
+    /// Overrides the window inferred from the model name.
+    pub context_window: Option<usize>,
+}
+
+/// OpenAI-compatible client that can point at OpenAI, vLLM, or any HTTP-compatible backend.
+pub struct OpenAiLlmClient {
+    client: AsyncOpenAiClient<OpenAIConfig>,
+    model: String,
+    system_prompt: String,
+    temperature: f32,
+    context_window: usize,
+    retry: RetryPolicy,
+}
+
+impl OpenAiLlmClient {
+    const DEFAULT_MODEL: &'static str = "llama-3-8b-instruct";
+    const DEFAULT_SYSTEM_PROMPT: &'static str =
+        "You are Agent, orchestrator of Vidkosha Cortex. Respond with crisp, actionable output.";
+    const DEFAULT_TEMPERATURE: f32 = 0.2;
+
+    pub fn shared_from_env() -> anyhow::Result<SharedLlmClient> {
+        let client = Self::from_env()?;
+        Ok(Arc::new(client))
+    }
+
+    /// Model used when no provider registry is configured.
+    pub fn env_model() -> String {
+        env::var("VK_CORTEX_LLM_MODEL").unwrap_or_else(|_| Self::DEFAULT_MODEL.to_string())
+    }
+
+    fn from_env() -> anyhow::Result<Self> {
+        Self::new(OpenAiEndpoint {
+            api_key: None,
+            base_url: None,
+            model: Self::env_model(),
+            temperature: None,
+            system_prompt: None,
+            context_window: None,
+        })
+    }
+
+    /// Build a client for one endpoint; unset fields fall back to the `OPENAI_*` / `VK_CORTEX_*` env.
+    pub fn new(endpoint: OpenAiEndpoint) -> anyhow::Result<Self> {
+        let api_key = match endpoint.api_key {
+            Some(key) => key,
+            None => env::var("OPENAI_API_KEY")
+                .or_else(|_| env::var("AIE_OPENAI_API_KEY"))
+                .context("Set OPENAI_API_KEY (or AIE_OPENAI_API_KEY) to use the OpenAI client")?,
+        };
+
+        let mut config = OpenAIConfig::new().with_api_key(api_key);
+
+        let base_url = endpoint.base_url.or_else(|| {
+            env::var("OPENAI_BASE_URL")
+                .or_else(|_| env::var("AIE_OPENAI_BASE_URL"))
+                .ok()
+        });
+        if let Some(base_url) = base_url {
+            config = config.with_api_base(base_url);
+        }
+
+        let system_prompt = endpoint.system_prompt.unwrap_or_else(|| {
+            env::var("VK_CORTEX_SYSTEM_PROMPT")
+                .unwrap_or_else(|_| Self::DEFAULT_SYSTEM_PROMPT.to_string())
+        });
+
+        let context_window = resolve_context_window(endpoint.context_window, &endpoint.model);
+
+        Ok(Self {
+            client: AsyncOpenAiClient::with_config(config).with_backoff(disabled_openai_backoff()),
+            model: endpoint.model,
+            context_window,
+            system_prompt,
+            temperature: endpoint.temperature.unwrap_or(Self::DEFAULT_TEMPERATURE),
+            retry: RetryPolicy::from_env("LLM", RetryPolicy::llm_defaults()),
+        })
+    }
+
+    fn build_request(
+        &self,
+        messages: &[ChatMessage],
+        tools: &[ToolSpec],
+        json_mode: bool,
+    ) -> anyhow::Result<CreateChatCompletionRequest> {
+        let mut request_messages: Vec<ChatCompletionRequestMessage> =
+            Vec::with_capacity(messages.len() + 1);
+
+        if messages.first().map(|m| m.role) != Some(ChatRole::System) {
+            request_messages.push(
+                ChatCompletionRequestSystemMessageArgs::default()
+                    .content(&self.system_prompt)
+                    .build()?
+                    .into(),
+            );
+        }
+
+        for message in messages {
+            request_messages.push(Self::to_request_message(message)?);
+        }
+
+        let mut args = CreateChatCompletionRequestArgs::default();
+        args.model(&self.model)
+            .temperature(self.temperature)
+            .messages(request_messages);
+
+        if json_mode {
+            args.response_format(ChatCompletionResponseFormat {
+                r#type: ChatCompletionResponseFormatType::JsonObject,
+            });
+        }
+
+        if !tools.is_empty() {
+            let tools = tools
+                .iter()
+                .map(|tool| ChatCompletionTool {
+                    r#type: ChatCompletionToolType::Function,
+                    function: FunctionObject {
+                        name: tool.name.clone(),
+                        description: Some(tool.description.clone()),
+                        parameters: Some(tool.parameters.clone()),
+                    },
+                })
+                .collect::<Vec<_>>();
+            args.tools(tools)
+                .tool_choice(ChatCompletionToolChoiceOption::Auto);
+        }
+
+        Ok(args.build()?)
+    }
+
+    /// Backend-reported usage when present, otherwise a local estimate.
+    fn usage_for(
+        reported: Option<&CompletionUsage>,
+        messages: &[ChatMessage],
+        completion: &str,
+    ) -> TokenUsage {
+        match reported {
+            Some(usage) => TokenUsage::reported(usage.prompt_tokens, usage.completion_tokens),
+            None => TokenUsage::estimated(messages, completion),
+        }
+    }
+
+    fn to_request_message(message: &ChatMessage) -> anyhow::Result<ChatCompletionRequestMessage> {
+        let converted = match message.role {
+            ChatRole::System => ChatCompletionRequestSystemMessageArgs::default()
+                .content(message.content.as_str())
+                .build()?
+                .into(),
+            ChatRole::User => ChatCompletionRequestUserMessageArgs::default()
+                .content(message.content.as_str())
+                .build()?
+                .into(),
+            ChatRole::Assistant if !message.tool_calls.is_empty() => {
+                let tool_calls = message
+                    .tool_calls
+                    .iter()
+                    .map(|call| ChatCompletionMessageToolCall {
+                        id: call.id.clone(),
+                        r#type: ChatCompletionToolType::Function,
+                        function: FunctionCall {
+                            name: call.name.clone(),
+                            arguments: call.arguments.clone(),
+                        },
+                    })
+                    .collect::<Vec<_>>();
+                let mut args = ChatCompletionRequestAssistantMessageArgs::default();
+                args.tool_calls(tool_calls);
+                if !message.content.is_empty() {
+                    args.content(message.content.as_str());
+                }
+                args.build()?.into()
+            }
+            ChatRole::Assistant => ChatCompletionRequestAssistantMessageArgs::default()
+                .content(message.content.as_str())
+                .build()?
+                .into(),
+            ChatRole::Tool => ChatCompletionRequestToolMessageArgs::default()
+                .content(message.content.as_str())
+                .tool_call_id(
+                    message
+                        .tool_call_id
+                        .clone()
+                        .context("Tool messages require a tool_call_id")?,
+                )
+                .build()?
+                .into(),
+        };
+
+        Ok(converted)
+    }
+
+    #[instrument(level = "debug", skip_all, fields(json_mode))]
+    async fn send_chat(&self, messages: &[ChatMessage], json_mode: bool) -> anyhow::Result<String> {
+        let request = self.build_request(messages, &[], json_mode)?;
+
+        let response = self
+            .retry
+            .run("LLM chat", || {
+                let request = request.clone();
+                async move { Ok(self.client.chat().create(request).await?) }
+            })
+            .await?;
+        let choice = response
+            .choices
+            .first()
+            .context("LLM response did not contain any choices")?;
+
+        let output = choice
+            .message
+            .content
+            .clone()
+            .unwrap_or_else(|| String::from("[empty LLM response]"));
+
+        let operation = if json_mode { "chat_json" } else { "chat" };
+        let usage = Self::usage_for(response.usage.as_ref(), messages, &output);
+        usage::record(&self.model, operation, usage);
+
+        Ok(output)
+    }
+
+    #[instrument(level = "debug", skip_all, fields(tools = tools.len()))]
+    async fn send_chat_with_tools(
+        &self,
+        messages: &[ChatMessage],
+        tools: &[ToolSpec],
+    ) -> anyhow::Result<ChatTurn> {
+        let request = self.build_request(messages, tools, false)?;
+
+        let response = self
+            .retry
+            .run("LLM tool chat", || {
+                let request = request.clone();
+                async move { Ok(self.client.chat().create(request).await?) }
+            })
+            .await?;
+        let reported = response.usage.clone();
+        let choice = response
+            .choices
+            .into_iter()
+            .next()
+            .context("LLM response did not contain any choices")?;
+
+        let tool_calls: Vec<ToolCall> = choice
+            .message
+            .tool_calls
+            .unwrap_or_default()
+            .into_iter()
+            .map(|call| ToolCall {
+                id: call.id,
+                name: call.function.name,
+                arguments: call.function.arguments,
+            })
+            .collect();
+
+        let content = choice.message.content.unwrap_or_default();
+        let completion = tool_calls.iter().fold(content.clone(), |acc, call| {
+            acc + &call.name + &call.arguments
+        });
+        let usage = Self::usage_for(reported.as_ref(), messages, &completion);
+        usage::record(&self.model, "chat_with_tools", usage);
+
+        Ok(ChatTurn {
+            content,
+            tool_calls,
+        })
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    async fn send_chat_stream(&self, messages: &[ChatMessage]) -> anyhow::Result<TokenStream> {
+        let mut request = self.build_request(messages, &[], false)?;
+        // Ask for the usage block on the final chunk; the estimate covers backends that skip it.
+        request.stream_options = Some(ChatCompletionStreamOptions {
+            include_usage: true,
+        });
+        // Retries only cover the window before the first chunk; once tokens reach the
+        // caller a failure is surfaced instead of replaying the answer.
+        let response = self
+            .retry
+            .run("LLM stream", || {
+                let request = request.clone();
+                async move {
+                    let mut response = self
+                        .client
+                        .chat()
+                        .create_stream(request)
+                        .await
+                        .context("LLM stream request failed")?;
+                    let first = match response.next().await {
+                        Some(Err(err)) => return Err(anyhow::Error::new(err)),
+                        first => first,
+                    };
+                    Ok(stream::iter(first).chain(response))
+                }
+            })
+            .await?;
+
+        // Usage is recorded once the stream ends, against the scope active when it started.
+        let scope = UsageScope::current();
+        let model = self.model.clone();
+        let prompt = messages.to_vec();
+        let tally = Arc::new(std::sync::Mutex::new((
+            String::new(),
+            None::<CompletionUsage>,
+        )));
+
+        let deltas = {
+            let tally = tally.clone();
+            response.filter_map(move |chunk| {
+                let tally = tally.clone();
+                async move {
+                    match chunk {
+                        Ok(chunk) => {
+                            let delta = chunk
+                                .choices
+                                .into_iter()
+                                .next()
+                                .and_then(|choice| choice.delta.content)
+                                .filter(|content| !content.is_empty());
+                            let mut tally = tally.lock().expect("stream usage lock poisoned");
+                            if let Some(delta) = &delta {
+                                tally.0.push_str(delta);
+                            }
+                            if chunk.usage.is_some() {
+                                tally.1 = chunk.usage;
+                            }
+                            delta.map(Ok)
+                        }
+                        Err(err) => Some(Err(anyhow::Error::new(err).context("LLM stream failed"))),
+                    }
+                }
+            })
+        };
+
+        let report = stream::once(async move {
+            let (completion, reported) = {
+                let tally = tally.lock().expect("stream usage lock poisoned");
+                (tally.0.clone(), tally.1.clone())
+            };
+            let usage = Self::usage_for(reported.as_ref(), &prompt, &completion);
+            usage::record_in(scope, &model, "chat_stream", usage);
+        })
+        .filter_map(|_| async { None });
+
+        Ok(Box::pin(deltas.chain(report)))
+    }
+}
+
+#[async_trait]
+impl LlmClient for OpenAiLlmClient {
+    async fn chat(&self, messages: &[ChatMessage]) -> anyhow::Result<String> {
+        self.send_chat(messages, false).await
+    }
+
+    fn context_window(&self) -> usize {
+        self.context_window
+    }
+
+    async fn chat_json_mode(&self, messages: &[ChatMessage]) -> anyhow::Result<String> {
+        self.send_chat(messages, true).await
+    }
+
+    async fn chat_stream(&self, messages: &[ChatMessage]) -> anyhow::Result<TokenStream> {
+        self.send_chat_stream(messages).await
+    }
+
+    async fn chat_with_tools(
+        &self,
+        messages: &[ChatMessage],
+        tools: &[ToolSpec],
+    ) -> anyhow::Result<ChatTurn> {
+        self.send_chat_with_tools(messages, tools).await
+    }
+}
+
+/// Attempt to build an OpenAI-compatible client, optionally falling back to the echo client.
+pub fn build_llm_client_from_env(default_to_echo: bool) -> anyhow::Result<SharedLlmClient> {
+    match OpenAiLlmClient::shared_from_env() {
+        Ok(client) => Ok(client),
+        Err(err) if default_to_echo => {
+            tracing::warn!(?err, "Falling back to EchoLlmClient");
+            Ok(EchoLlmClient::shared())
+        }
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+
+    #[tokio::test]
+    async fn echo_stream_reassembles_into_completion() {
+        let client = EchoLlmClient;
+        let (sink, mut deltas) = mpsc::unbounded_channel();
+
+        let messages = [
+            ChatMessage::system("be brief"),
+            ChatMessage::user("stream me"),
+        ];
+        let output = chat_with_sink(&client, &messages, Some(&sink))
+            .await
+            .expect("echo stream");
+        drop(sink);
+
+        let mut streamed = Vec::new();
+        while let Some(delta) = deltas.recv().await {
+            streamed.push(delta);
+        }
+
+        assert!(streamed.len() > 1);
+        assert_eq!(streamed.concat(), output);
+        assert_eq!(
+            output,
+            client
+                .chat(&[ChatMessage::user("stream me")])
+                .await
+                .unwrap()
+        );
+    }
+}
@@ -0,0 +1,406 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Context};
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::cassette::{llm_store_from_env, CassetteLlmClient, CassetteMode, CassetteStore};
+
+use super::{
+    build_llm_client_from_env, resolve_context_window, ChatMessage, ChatTurn, LlmClient,
+    OpenAiEndpoint, OpenAiLlmClient, SharedLlmClient, TokenStream, ToolSpec,
+    DEFAULT_CONTEXT_WINDOW,
+};
+
+const DEFAULT_CONFIG_PATH: &str = "llm_providers.json";
+/// Cassette key label for the default chain, shared by agents without their own entry.
+const DEFAULT_CASSETTE_AGENT: &str = "default";
+
+/// One OpenAI-compatible endpoint entry in `llm_providers.json`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProviderConfig {
+    pub model: String,
+    /// Falls back to `OPENAI_BASE_URL` when unset.
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Name of the env var holding the API key; keys never live in the file.
+    #[serde(default)]
+    pub api_key_env: Option<String>,
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    /// Context window in tokens; inferred from `model` when unset.
+    #[serde(default)]
+    pub context_window: Option<usize>,
+}
+
+/// Provider chain for one agent, tried in order.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AgentRoute {
+    pub providers: Vec<String>,
+    /// Overrides the provider temperature for this agent only.
+    #[serde(default)]
+    pub temperature: Option<f32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LlmProvidersConfig {
+    pub providers: HashMap<String, ProviderConfig>,
+    /// Chain used by agents without an entry in `agents`.
+    pub default: Vec<String>,
+    #[serde(default)]
+    pub agents: HashMap<String, AgentRoute>,
+}
+
+impl LlmProvidersConfig {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.default.is_empty() {
+            bail!("LLM provider config needs a non-empty `default` chain");
+        }
+
+        let chains = std::iter::once(("default", &self.default)).chain(
+            self.agents
+                .iter()
+                .map(|(agent, route)| (agent.as_str(), &route.providers)),
+        );
+        for (owner, chain) in chains {
+            if chain.is_empty() {
+                bail!("LLM provider chain for '{owner}' is empty");
+            }
+            if let Some(unknown) = chain
+                .iter()
+                .find(|name| !self.providers.contains_key(*name))
+            {
+                bail!("LLM provider chain for '{owner}' references unknown provider '{unknown}'");
+            }
+        }
+
+        let temperatures = self
+            .providers
+            .iter()
+            .map(|(name, p)| (name.as_str(), p.temperature))
+            .chain(
+                self.agents
+                    .iter()
+                    .map(|(name, route)| (name.as_str(), route.temperature)),
+            );
+        for (owner, temperature) in temperatures {
+            if let Some(t) = temperature {
+                if !(0.0..=2.0).contains(&t) {
+                    bail!("Temperature {t} for '{owner}' is outside 0.0..=2.0");
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Resolves the LLM client each agent should use.
+pub struct LlmRegistry {
+    default: SharedLlmClient,
+    agents: HashMap<String, SharedLlmClient>,
+}
+
+impl LlmRegistry {
+    /// Load `VK_CORTEX_LLM_CONFIG` (default `llm_providers.json`) when present; otherwise a
+    /// single env-configured client serves every agent.
+    ///
+    /// With a strict cassette no live client is built, so replay needs no credentials. A
+    /// replay cassette falls back to the same when the live clients cannot be built.
+    pub fn from_env() -> anyhow::Result<Self> {
+        let explicit = env::var("VK_CORTEX_LLM_CONFIG").ok();
+        let path = explicit
+            .clone()
+            .unwrap_or_else(|| DEFAULT_CONFIG_PATH.to_string());
+
+        let config = if Path::new(&path).exists() {
+            let raw = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read LLM provider config {path}"))?;
+            let config: LlmProvidersConfig = serde_json::from_str(&raw)
+                .with_context(|| format!("Failed to parse LLM provider config {path}"))?;
+            info!(
+                %path,
+                providers = config.providers.len(),
+                agents = config.agents.len(),
+                "Loaded LLM provider registry"
+            );
+            Some(config)
+        } else if explicit.is_some() {
+            bail!("VK_CORTEX_LLM_CONFIG points at missing file {path}");
+        } else {
+            None
+        };
+
+        let Some(store) = llm_store_from_env()? else {
+            return Self::live(config.as_ref());
+        };
+        if store.mode() == CassetteMode::Strict {
+            info!("Strict LLM cassette: live clients are not built");
+            return Self::replay_only(config.as_ref(), store);
+        }
+        match Self::live(config.as_ref()) {
+            Ok(registry) => Ok(registry.with_cassette(store)),
+            Err(err) if store.mode() == CassetteMode::Replay => {
+                warn!(
+                    ?err,
+                    "No live LLM client; replaying the cassette without recording misses"
+                );
+                Self::replay_only(config.as_ref(), store)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    fn live(config: Option<&LlmProvidersConfig>) -> anyhow::Result<Self> {
+        match config {
+            Some(config) => Self::from_config(config),
+            None => Ok(Self::single(build_llm_client_from_env(false)?)),
+        }
+    }
+
+    /// Route every client through one shared cassette so recordings land in a single fixture.
+    pub fn with_cassette(self, store: Arc<CassetteStore>) -> Self {
+        let wrap = |agent: &str, client: SharedLlmClient| -> SharedLlmClient {
+            Arc::new(CassetteLlmClient::new(store.clone(), Some(client), agent))
+        };
+        Self {
+            default: wrap(DEFAULT_CASSETTE_AGENT, self.default),
+            agents: self
+                .agents
+                .into_iter()
+                .map(|(agent, client)| {
+                    let client = wrap(&agent, client);
+                    (agent, client)
+                })
+                .collect(),
+        }
+    }
+
+    /// Cassette-only clients for the same agents `from_config` would build. Context windows
+    /// come from the same settings as the live clients, so prompts are trimmed as they were
+    /// when recorded.
+    pub fn replay_only(
+        config: Option<&LlmProvidersConfig>,
+        store: Arc<CassetteStore>,
+    ) -> anyhow::Result<Self> {
+        let replay = |agent: &str, context_window: usize| -> SharedLlmClient {
+            Arc::new(
+                CassetteLlmClient::new(store.clone(), None, agent)
+                    .with_context_window(context_window),
+            )
+        };
+
+        let Some(config) = config else {
+            let window = resolve_context_window(None, &OpenAiLlmClient::env_model());
+            return Ok(Self::single(replay(DEFAULT_CASSETTE_AGENT, window)));
+        };
+        config.validate()?;
+        Ok(Self {
+            default: replay(
+                DEFAULT_CASSETTE_AGENT,
+                chain_context_window(config, &config.default),
+            ),
+            agents: config
+                .agents
+                .iter()
+                .map(|(agent, route)| {
+                    let window = chain_context_window(config, &route.providers);
+                    (agent.clone(), replay(agent, window))
+                })
+                .collect(),
+        })
+    }
+
+    pub fn single(client: SharedLlmClient) -> Self {
+        Self {
+            default: client,
+            agents: HashMap::new(),
+        }
+    }
+
+    pub fn from_config(config: &LlmProvidersConfig) -> anyhow::Result<Self> {
+        config.validate()?;
+
+        let default = build_chain(config, &config.default, None)?;
+        let mut agents = HashMap::new();
+        for (agent, route) in &config.agents {
+            let client = build_chain(config, &route.providers, route.temperature)
+                .with_context(|| format!("Failed to build LLM chain for {agent}"))?;
+            agents.insert(agent.clone(), client);
+        }
+
+        Ok(Self { default, agents })
+    }
+
+    pub fn for_agent(&self, agent_name: &str) -> SharedLlmClient {
+        self.agents
+            .get(agent_name)
+            .cloned()
+            .unwrap_or_else(|| self.default.clone())
+    }
+
+    pub fn default_client(&self) -> SharedLlmClient {
+        self.default.clone()
+    }
+}
+
+/// What `FallbackLlmClient::context_window` would report for a chain: its smallest window.
+fn chain_context_window(config: &LlmProvidersConfig, names: &[String]) -> usize {
+    names
+        .iter()
+        .map(|name| {
+            let provider = &config.providers[name];
+            resolve_context_window(provider.context_window, &provider.model)
+        })
+        .min()
+        .unwrap_or(DEFAULT_CONTEXT_WINDOW)
+}
+
+fn build_chain(
+    config: &LlmProvidersConfig,
+    names: &[String],
+    temperature: Option<f32>,
+) -> anyhow::Result<SharedLlmClient> {
+    let mut members: Vec<(String, SharedLlmClient)> = Vec::with_capacity(names.len());
+    for name in names {
+        let provider = &config.providers[name];
+        let api_key = match provider.api_key_env.as_deref() {
+            Some(var) => Some(
+                env::var(var)
+                    .with_context(|| format!("Provider '{name}' expects its API key in {var}"))?,
+            ),
+            None => None,
+        };
+
+        let client = OpenAiLlmClient::new(OpenAiEndpoint {
+            api_key,
+            base_url: provider.base_url.clone(),
+            model: provider.model.clone(),
+            temperature: temperature.or(provider.temperature),
+            system_prompt: provider.system_prompt.clone(),
+            context_window: provider.context_window,
+        })
+        .with_context(|| format!("Failed to build LLM provider '{name}'"))?;
+        members.push((name.clone(), Arc::new(client)));
+    }
+
+    if members.len() == 1 {
+        return Ok(members.remove(0).1);
+    }
+    Ok(Arc::new(FallbackLlmClient::new(members)))
+}
+
+/// Tries each provider in order, moving on when a call fails after that provider's own retries.
+pub struct FallbackLlmClient {
+    chain: Vec<(String, SharedLlmClient)>,
+}
+
+impl FallbackLlmClient {
+    pub fn new(chain: Vec<(String, SharedLlmClient)>) -> Self {
+        Self { chain }
+    }
+
+    async fn first_ok<'a, T>(
+        &'a self,
+        op: &str,
+        call: impl Fn(&'a dyn LlmClient) -> BoxFuture<'a, anyhow::Result<T>>,
+    ) -> anyhow::Result<T> {
+        let mut last_err = None;
+        for (name, client) in &self.chain {
+            match call(client.as_ref()).await {
+                Ok(value) => return Ok(value),
+                Err(err) => {
+                    warn!(provider = %name, %op, error = %err, "LLM provider failed; trying next in chain");
+                    last_err = Some(err.context(format!("LLM provider '{name}' failed")));
+                }
+            }
+        }
+        Err(last_err.unwrap_or_else(|| anyhow!("LLM provider chain is empty")))
+    }
+}
+
+#[async_trait]
+impl LlmClient for FallbackLlmClient {
+    async fn chat(&self, messages: &[ChatMessage]) -> anyhow::Result<String> {
+        self.first_ok("chat", |client| client.chat(messages)).await
+    }
+
+    async fn chat_stream(&self, messages: &[ChatMessage]) -> anyhow::Result<TokenStream> {
+        self.first_ok("chat_stream", |client| client.chat_stream(messages))
+            .await
+    }
+
+    async fn chat_with_tools(
+        &self,
+        messages: &[ChatMessage],
+        tools: &[ToolSpec],
+    ) -> anyhow::Result<ChatTurn> {
+        self.first_ok("chat_with_tools", |client| {
+            client.chat_with_tools(messages, tools)
+        })
+        .await
+    }
+
+    async fn chat_json_mode(&self, messages: &[ChatMessage]) -> anyhow::Result<String> {
+        self.first_ok("chat_json_mode", |client| client.chat_json_mode(messages))
+            .await
+    }
+
+    /// The smallest window in the chain, so a prompt sized for it fits every fallback.
+    fn context_window(&self) -> usize {
+        self.chain
+            .iter()
+            .map(|(_, client)| client.context_window())
+            .min()
+            .unwrap_or(DEFAULT_CONTEXT_WINDOW)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm_client::EchoLlmClient;
+
+    struct DownClient;
+
+    #[async_trait]
+    impl LlmClient for DownClient {
+        async fn chat(&self, _messages: &[ChatMessage]) -> anyhow::Result<String> {
+            bail!("connection refused")
+        }
+    }
+
+    #[tokio::test]
+    async fn fallback_chain_degrades_to_next_provider() {
+        let chain = FallbackLlmClient::new(vec![
+            ("primary".into(), Arc::new(DownClient)),
+            ("secondary".into(), EchoLlmClient::shared()),
+        ]);
+
+        let output = chain.chat(&[ChatMessage::user("ping")]).await.unwrap();
+        assert!(output.contains("I received: ping"));
+    }
+
+    #[test]
+    fn validation_rejects_unknown_providers() {
+        let config: LlmProvidersConfig = serde_json::from_str(
+            r#"{
+                "providers": {"local": {"model": "llama3-local"}},
+                "default": ["local"],
+                "agents": {"CTOAgent": {"providers": ["gpu", "local"]}}
+            }"#,
+        )
+        .unwrap();
+
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.contains("unknown provider 'gpu'"));
+    }
+}
@@ -1,17 +1,22 @@
 mod agents;
+mod cassette;
 mod llm_client;
 mod orchestrator;
 mod rag;
+mod retry;
+mod usage;
 
 use agents::{
-    Agent, AgentRequest, AgentResponse, CTOAgent, OpsChainAgent, ResearcherAgent,
-    SeniorEngineerAgent,
+    Agent, AgentCatalog, AgentResponse, MemoryAgent, Reflection, Specialist, TopicClassifier,
 };
 use anyhow::{bail, Context};
 use chrono::Utc;
 use clap::{Parser, Subcommand};
-use llm_client::{build_llm_client_from_env, LlmClient, SharedLlmClient};
-use orchestrator::{routing::SemanticRouter, OrchestratorRouter};
+use llm_client::{chat_json, ChatMessage, JsonReply, LlmClient, LlmRegistry, SharedLlmClient};
+use orchestrator::{
+    routing::{IntentClassifier, RoutingRulesHandle, SemanticRouter},
+    ConversationSession, FanOut, MegaBrain, OrchestratorRouter, SessionConfig,
+};
 use rag::config::RagConfig;
 use rag::embed::{EmbeddingsProvider, OpenAiEmbeddingsClient};
 use rag::topic_registry::TopicRegistry;
@@ -25,6 +30,7 @@ use std::fs;
 use std::io::{self, Write};
 use std::path::Path;
 use std::process::Command;
+use tokio::sync::mpsc;
 use tracing::{error, info, warn};
 use tree_sitter::{Language as TsLanguage, Node, Parser as TsParser};
 
@@ -38,6 +44,10 @@ struct Cli {
     #[arg(short, long)]
     prompt: Option<String>,
 
+    /// Answer every prompt with the deep reasoning pipeline (same as prefixing `@megabrain`).
+    #[arg(long)]
+    deep: bool,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -105,8 +115,20 @@ async fn main() -> anyhow::Result<()> {
     init_tracing();
     let cli = Cli::parse();
 
-    let llm_client =
-        build_llm_client_from_env(false).context("LLM client initialization failed")?;
+    if let Some(sink) = usage::sink_from_env().context("Usage sink initialization failed")? {
+        usage::install(sink);
+    }
+
+    let result = run(cli).await;
+    // Usage events are written in the background; let the queue drain before exiting.
+    usage::flush().await;
+    result
+}
+
+async fn run(cli: Cli) -> anyhow::Result<()> {
+    let llm_registry = LlmRegistry::from_env().context("LLM client initialization failed")?;
+    let llm_client = llm_registry.default_client();
+    let indexer_llm = llm_registry.for_agent("Indexer");
     let rag_agent = build_rag_agent_from_env(false)
         .await
         .context("Failed to initialize RAG agent")?;
@@ -116,31 +138,120 @@ async fn main() -> anyhow::Result<()> {
         .and_then(|cfg| TopicRegistry::new(cfg).ok())
         .map(std::sync::Arc::new);
 
-    let agent = Agent::new(llm_client.clone(), rag_agent.clone(), topic_registry);
+    let confirmation_ttl = std::env::var("SAVE_CONFIRM_TTL_SECS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .map(std::time::Duration::from_secs)
+        .unwrap_or(agents::agent::DEFAULT_CONFIRMATION_TTL);
+    let topic_classifier = match topic_registry.clone() {
+        Some(registry) => TopicClassifier::from_env(registry, llm_registry.for_agent("Agent"))
+            .unwrap_or_else(|err| {
+                warn!(?err, "Save-time topic classification disabled");
+                None
+            }),
+        None => None,
+    };
+    let mut agent = Agent::new(
+        llm_registry.for_agent("Agent"),
+        rag_agent.clone(),
+        topic_registry,
+    )
+    .with_confirmation_ttl(confirmation_ttl);
+    if let Some(classifier) = topic_classifier {
+        info!("Save-time topic classification enabled against registered topics");
+        let classifier = std::sync::Arc::new(classifier);
+        let warming = classifier.clone();
+        // Embedding the taxonomy can take a while; saves before it finishes embed inline.
+        tokio::spawn(async move {
+            match warming.warm_up().await {
+                Ok(count) => info!(topics = count, "Topic vectors warmed"),
+                Err(err) => warn!(?err, "Topic vectors not warmed; first save embeds them"),
+            }
+        });
+        agent = agent.with_topic_classifier(classifier);
+    }
+    let catalog = AgentCatalog::from_env().context("Agent definitions failed to load")?;
+    let routing_rules = std::sync::Arc::new(
+        RoutingRulesHandle::from_env()
+            .and_then(|rules| rules.with_agent_rules(catalog.routing_rules()))
+            .context("Routing rules initialization failed")?,
+    );
+    routing_rules.spawn_hot_reload();
+
+    let max_handoffs = std::env::var("ROUTING_HANDOFF_MAX_DEPTH")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(orchestrator::router::DEFAULT_MAX_HANDOFFS);
+
     let mut router = OrchestratorRouter::new(agent)
-        .with_specialist("CTOAgent", CTOAgent::new(llm_client.clone()))
-        .with_specialist(
-            "SeniorEngineerAgent",
-            SeniorEngineerAgent::new(llm_client.clone()),
-        )
-        .with_specialist("ResearcherAgent", ResearcherAgent::new(llm_client.clone()))
-        .with_specialist("OpsChainAgent", OpsChainAgent::new(llm_client.clone()));
+        .with_routing_rules(routing_rules)
+        .with_max_handoffs(max_handoffs);
+    for definition in catalog.definitions() {
+        let llm = llm_registry.for_agent(definition.model_entry());
+        let specialist = Specialist::new(definition, llm)
+            .with_peers(&catalog)
+            .with_rag(rag_agent.clone());
+        router = router.with_specialist(&definition.name, specialist);
+    }
+
+    let mut specialist_profiles = catalog.profiles();
+    if rag_agent.is_some() {
+        specialist_profiles.push(MemoryAgent::profile());
+    }
+
+    // Built before the RAG handle moves into the router so citations can be checked against it.
+    let deep = MegaBrain::from_env(llm_registry.for_agent("MegaBrain"), rag_agent.clone());
 
     match rag_agent {
         Some(rag_agent_handle) => {
             info!("RAG enabled (Helix + embeddings)");
-            router = router.with_rag_agent(rag_agent_handle);
+            router = router
+                .with_specialist("RagAgent", MemoryAgent::new(rag_agent_handle.clone()))
+                .with_rag_agent(rag_agent_handle);
         }
         None => {
             warn!("RAG configuration not detected; continuing without persistent memory");
         }
     }
 
-    if let Ok(Some(semantic_router)) = SemanticRouter::from_env() {
-        info!("Semantic routing enabled via ROUTING_SEMANTIC_ENABLED");
-        router = router.with_semantic_router(semantic_router);
+    match SemanticRouter::from_env() {
+        Ok(Some(semantic_router)) => {
+            let semantic_router = semantic_router.with_prototypes(
+                catalog
+                    .definitions()
+                    .iter()
+                    .filter_map(|definition| definition.prototype()),
+            );
+            info!("Semantic routing enabled via ROUTING_SEMANTIC_ENABLED");
+            // A cold embeddings backend is retried lazily on the first routed request.
+            if let Err(err) = semantic_router.warm_up().await {
+                warn!(?err, "Semantic routing prototypes not embedded yet");
+            }
+            router = router.with_semantic_router(semantic_router);
+        }
+        Ok(None) => {}
+        Err(err) => warn!(?err, "Semantic routing disabled"),
+    }
+
+    if let Some(classifier) =
+        IntentClassifier::from_env(llm_registry.for_agent("Router"), specialist_profiles)
+    {
+        info!("LLM intent classifier enabled via ROUTING_CLASSIFIER_ENABLED");
+        router = router.with_intent_classifier(classifier);
+    }
+
+    if let Some(reflection) = Reflection::from_env(llm_registry.for_agent("Critic")) {
+        info!("Critique pass enabled via REFLECTION_ENABLED");
+        router = router.with_reflection(reflection);
+    }
+
+    if let Some(fan_out) = FanOut::from_env(llm_registry.for_agent("Synthesizer")) {
+        info!("Multi-specialist fan-out enabled via ROUTING_FANOUT_ENABLED");
+        router = router.with_fan_out(fan_out);
     }
 
+    router = router.with_deep_reasoning(deep);
+
     if let Some(command) = cli.command {
         match command {
             Commands::RagSmoke => {
@@ -162,7 +273,14 @@ async fn main() -> anyhow::Result<()> {
                 let rag_agent = build_rag_agent_from_env(false)
                     .await?
                     .context("RAG configuration required for indexing")?;
-                run_index_chunk(rag_agent, llm_client.clone(), path, max_bytes).await?;
+                indexer_scope()
+                    .run(run_index_chunk(
+                        rag_agent,
+                        indexer_llm.clone(),
+                        path,
+                        max_bytes,
+                    ))
+                    .await?;
                 return Ok(());
             }
             Commands::IndexFile {
@@ -174,15 +292,16 @@ async fn main() -> anyhow::Result<()> {
                 let rag_agent = build_rag_agent_from_env(false)
                     .await?
                     .context("RAG configuration required for indexing")?;
-                run_index_file(
-                    rag_agent,
-                    llm_client.clone(),
-                    path,
-                    chunk_bytes,
-                    overlap_bytes,
-                    !no_llm_labels,
-                )
-                .await?;
+                indexer_scope()
+                    .run(run_index_file(
+                        rag_agent,
+                        indexer_llm.clone(),
+                        path,
+                        chunk_bytes,
+                        overlap_bytes,
+                        !no_llm_labels,
+                    ))
+                    .await?;
                 return Ok(());
             }
             Commands::IndexRepo {
@@ -206,18 +325,30 @@ async fn main() -> anyhow::Result<()> {
                     allow_binary,
                     use_llm_labels: !no_llm_labels,
                 };
-                run_index_repo(rag_agent, llm_client.clone(), opts).await?;
+                indexer_scope()
+                    .run(run_index_repo(rag_agent, indexer_llm.clone(), opts))
+                    .await?;
                 return Ok(());
             }
         }
     }
 
+    let mut session = ConversationSession::new(SessionConfig::from_env(), Some(llm_client.clone()));
+
     if let Some(prompt) = cli.prompt {
-        run_single(&router, prompt).await?;
+        run_single(&router, &mut session, prompt, cli.deep).await?;
         return Ok(());
     }
 
-    run_repl(&router).await
+    run_repl(&router, &mut session, cli.deep).await
+}
+
+/// Bill labeling and embedding calls from one indexing run to the Indexer.
+fn indexer_scope() -> usage::UsageScope {
+    usage::UsageScope::new(
+        format!("index-{}", Utc::now().timestamp_millis()),
+        "Indexer",
+    )
 }
 
 fn init_tracing() {
@@ -227,17 +358,42 @@ fn init_tracing() {
         .try_init();
 }
 
-async fn run_single(router: &OrchestratorRouter, prompt: String) -> anyhow::Result<()> {
-    let response: AgentResponse = router
-        .dispatch(AgentRequest::new(prompt))
-        .await
+async fn run_single(
+    router: &OrchestratorRouter,
+    session: &mut ConversationSession,
+    prompt: String,
+    deep: bool,
+) -> anyhow::Result<()> {
+    let (sink, mut deltas) = mpsc::unbounded_channel::<String>();
+    let request = session.prepare(prompt).with_deep_reasoning(deep);
+    let turn_request = request.clone();
+    let request = request.with_stream(sink);
+
+    println!("\nAgent:");
+    let printer = async {
+        let mut streamed = false;
+        while let Some(delta) = deltas.recv().await {
+            print!("{delta}");
+            let _ = io::stdout().flush();
+            streamed = true;
+        }
+        streamed
+    };
+
+    let (dispatched, streamed) = tokio::join!(router.dispatch(request), printer);
+    let response: AgentResponse = dispatched
         .map_err(|err| {
             error!(?err, "Agent request failed");
             err
         })?
         .into_output();
 
-    println!("\nAgent:\n{}\n", response.output);
+    if streamed {
+        println!("\n");
+    } else {
+        println!("{}\n", response.output);
+    }
+    session.record(&turn_request, &response.output).await;
     Ok(())
 }
 
@@ -311,8 +467,15 @@ async fn run_memory_smoke(rag_agent: SharedRagAgent) -> anyhow::Result<()> {
     Ok(())
 }
 
-async fn run_repl(router: &OrchestratorRouter) -> anyhow::Result<()> {
-    println!("Vidkosha Cortex CLI ready. Type 'exit' to quit.\n");
+async fn run_repl(
+    router: &OrchestratorRouter,
+    session: &mut ConversationSession,
+    deep: bool,
+) -> anyhow::Result<()> {
+    println!(
+        "Vidkosha Cortex CLI ready (conversation {}). Type 'exit' to quit.\n",
+        session.conversation_id()
+    );
     let stdin = io::stdin();
 
     loop {
@@ -332,7 +495,7 @@ async fn run_repl(router: &OrchestratorRouter) -> anyhow::Result<()> {
             continue;
         }
 
-        run_single(router, trimmed.to_owned()).await?;
+        run_single(router, session, trimmed.to_owned(), deep).await?;
     }
 
     Ok(())
@@ -765,6 +928,19 @@ struct LabeledChunk {
     project: String,
     summary: String,
     open_questions: Vec<String>,
+    /// Recorded as `label_source`: llm_indexer, llm_repaired, llm_fallback, or heuristic.
+    source: &'static str,
+}
+
+/// Schema the labeling prompt asks the LLM to fill.
+#[derive(Debug, serde::Deserialize)]
+struct ChunkLabelReply {
+    topic: String,
+    #[serde(default)]
+    project: Option<String>,
+    summary: String,
+    #[serde(default)]
+    open_questions: Vec<String>,
 }
 
 #[derive(Debug, Clone, Default, serde::Deserialize)]
@@ -868,7 +1044,7 @@ async fn run_index_chunk(
             "path": path,
             "hash": format!("sha256:{}", hash),
             "chunk_bytes": content.len(),
-            "label_source": "llm_indexer",
+            "label_source": labels.source,
             "body": content,
         })),
     };
@@ -935,7 +1111,7 @@ async fn run_index_file(
                 "path": path,
                 "hash": format!("sha256:{}", hash),
                 "chunk_bytes": chunk.len(),
-                "label_source": "llm_indexer",
+                "label_source": labels.source,
                 "body": chunk,
                 "chunk_index": idx,
                 "chunk_id": chunk_id,
@@ -1004,6 +1180,7 @@ async fn run_index_repo(
     let mut seen_hashes: HashSet<String> = HashSet::new();
     let mut files_processed = 0usize;
     let mut chunks_stored = 0usize;
+    let mut chunks_failed = 0usize;
     for path in files {
         if let Some(changed) = changed_only.as_ref() {
             if !changed.contains(&path) {
@@ -1051,6 +1228,7 @@ async fn run_index_repo(
         }
 
         let mut chunk_ids_for_manifest = Vec::new();
+        let mut file_complete = true;
         for (idx, prepared) in prepared_chunks.iter().enumerate() {
             let chunk = &prepared.text;
             let hash = blake3::hash(chunk.as_bytes()).to_hex().to_string();
@@ -1058,9 +1236,23 @@ async fn run_index_repo(
                 continue;
             }
 
-            let labels =
-                label_chunk_with_mode(llm_client.as_ref(), &path, chunk, opts.use_llm_labels)
-                    .await?;
+            // Labeling failures (after retries) degrade to heuristic labels rather than aborting.
+            let labels = match label_chunk_with_mode(
+                llm_client.as_ref(),
+                &path,
+                chunk,
+                opts.use_llm_labels,
+            )
+            .await
+            {
+                Ok(labels) => labels,
+                Err(err) => {
+                    warn!(?err, %path, chunk = idx, "LLM labeling failed; using heuristic labels");
+                    let mut labels = label_chunk_heuristic(&path, chunk);
+                    labels.source = "llm_fallback";
+                    labels
+                }
+            };
             let timestamp = Utc::now();
 
             let chunk_id = prepared.chunk_id_hint.clone().unwrap_or_else(|| {
@@ -1087,13 +1279,7 @@ async fn run_index_repo(
                 .or_insert_with(|| json!(chunk.len()));
             metadata
                 .entry("label_source".to_string())
-                .or_insert_with(|| {
-                    json!(if opts.use_llm_labels {
-                        "llm_indexer"
-                    } else {
-                        "heuristic"
-                    })
-                });
+                .or_insert_with(|| json!(labels.source));
             metadata
                 .entry("body".to_string())
                 .or_insert_with(|| json!(chunk));
@@ -1125,9 +1311,24 @@ async fn run_index_repo(
                 metadata: Some(Value::Object(metadata.clone())),
             };
 
-            let response = rag_agent
+            let response = match rag_agent
                 .handle(MemoryRequest::Write(MemoryWriteRequest { record }))
-                .await?;
+                .await
+            {
+                Ok(response) => response,
+                Err(err) => {
+                    warn!(?err, %path, chunk = idx, "Chunk write failed; continuing");
+                    println!(
+                        "✖ {} [{}] chunk {} failed: {err:#}",
+                        path,
+                        handler.name(),
+                        idx
+                    );
+                    chunks_failed += 1;
+                    file_complete = false;
+                    continue;
+                }
+            };
 
             chunks_stored += 1;
             println!(
@@ -1140,14 +1341,17 @@ async fn run_index_repo(
             chunk_ids_for_manifest.push(chunk_id);
         }
 
-        manifest.files.insert(
-            path.clone(),
-            ManifestEntry {
-                hash: file_hash,
-                mtime,
-                chunk_ids: chunk_ids_for_manifest,
-            },
-        );
+        // Leave partially stored files out of the manifest so the next run retries them.
+        if file_complete {
+            manifest.files.insert(
+                path.clone(),
+                ManifestEntry {
+                    hash: file_hash,
+                    mtime,
+                    chunk_ids: chunk_ids_for_manifest,
+                },
+            );
+        }
 
         files_processed += 1;
     }
@@ -1155,8 +1359,8 @@ async fn run_index_repo(
     save_manifest(ingest_config.manifest_path.as_deref(), &manifest)?;
 
     println!(
-        "Indexing complete. Files processed: {}. Chunks stored: {} (unique by hash).",
-        files_processed, chunks_stored
+        "Indexing complete. Files processed: {}. Chunks stored: {} (unique by hash). Chunks failed: {}.",
+        files_processed, chunks_stored, chunks_failed
     );
 
     Ok(())
@@ -2033,43 +2237,39 @@ async fn label_chunk_llm(
          Path: {path}\n---\n{content}\n---\nJSON:"
     );
 
-    let raw = llm.complete(&prompt).await?;
-    let parsed: Value = serde_json::from_str(&raw).unwrap_or_else(|_| json!({}));
+    let reply = chat_json::<ChunkLabelReply>(llm, &[ChatMessage::user(prompt)]).await?;
+    let (parsed, source) = match reply {
+        JsonReply::Valid { value, repairs: 0 } => (value, "llm_indexer"),
+        JsonReply::Valid { value, .. } => (value, "llm_repaired"),
+        JsonReply::Invalid { reason, .. } => {
+            warn!(%path, %reason, "LLM labels unusable; falling back to heuristic labels");
+            let mut labels = label_chunk_heuristic(path, content);
+            labels.source = "llm_fallback";
+            return Ok(labels);
+        }
+    };
 
-    let topic = parsed
-        .get("topic")
-        .and_then(Value::as_str)
+    let topic = Some(parsed.topic.trim())
+        .filter(|t| !t.is_empty())
         .unwrap_or("code")
         .to_string();
 
     let project = parsed
-        .get("project")
-        .and_then(Value::as_str)
-        .unwrap_or("vidkosha_cortex")
-        .to_string();
+        .project
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+        .unwrap_or_else(|| "vidkosha_cortex".to_string());
 
-    let summary = parsed
-        .get("summary")
-        .and_then(Value::as_str)
-        .map(|s| s.to_string())
+    let summary = Some(parsed.summary)
         .filter(|s| !s.trim().is_empty())
         .unwrap_or_else(|| fallback_summary(content));
 
-    let open_questions = parsed
-        .get("open_questions")
-        .and_then(Value::as_array)
-        .map(|arr| {
-            arr.iter()
-                .filter_map(|v| v.as_str().map(|s| s.to_string()))
-                .collect::<Vec<String>>()
-        })
-        .unwrap_or_default();
-
     Ok(LabeledChunk {
         topic,
         project,
         summary,
-        open_questions,
+        open_questions: parsed.open_questions,
+        source,
     })
 }
 
@@ -2092,6 +2292,7 @@ fn label_chunk_heuristic(path: &str, content: &str) -> LabeledChunk {
         project,
         summary,
         open_questions: Vec::new(),
+        source: "heuristic",
     }
 }
 
@@ -2298,6 +2499,10 @@ class Bar:
         let cli = Cli::parse_from(["nervos-cortex", "--prompt", "hello"]);
         assert_eq!(cli.prompt.as_deref(), Some("hello"));
         assert!(cli.command.is_none());
+        assert!(!cli.deep);
+
+        let cli = Cli::parse_from(["nervos-cortex", "--deep", "--prompt", "hello"]);
+        assert!(cli.deep);
     }
 
     #[test]
@@ -0,0 +1,579 @@
+use std::collections::{BTreeMap, HashSet};
+use std::env;
+use std::future::Future;
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use futures::future::join_all;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+use crate::agents::prompt::{truncate_to_tokens, PromptBudget, PromptBuilder};
+use crate::agents::AgentRequest;
+use crate::llm_client::{chat_json, chat_with_sink, ChatMessage, JsonReply, SharedLlmClient};
+use crate::rag::{
+    MemoryFilters, MemoryGetRequest, MemoryQuery, MemoryRecord, MemoryRequest, PerspectiveView,
+    SharedRagAgent,
+};
+
+const DEFAULT_BRANCHES: usize = 3;
+const DEFAULT_STAGE_TIMEOUT_SECS: u64 = 60;
+const GROUNDING_LIMIT: usize = 5;
+/// Prompt tokens that switch a single request into deep reasoning.
+const DEEP_TOKENS: &[&str] = &["@megabrain", "/deep"];
+
+/// Each branch answers from a different stance so the vote has real alternatives.
+const ANGLES: &[&str] = &[
+    "the most pragmatic answer that can ship soonest",
+    "the most thorough answer, covering edge cases and failure modes",
+    "a skeptical answer that questions the premise and the plan",
+    "the simplest answer with the fewest moving parts",
+];
+
+const PLAN_DIRECTIVE: &str = "You plan answers for Vidkosha Cortex's deep reasoning mode. Break the request into 3-6 numbered steps: what must be established, which stored memories matter, and what a complete answer must contain. Output the plan only.";
+const CANDIDATE_DIRECTIVE: &str = "You draft one candidate answer for Vidkosha Cortex's deep reasoning mode. Follow the plan. When you rely on a stored memory, cite it inline as [mem:<id>] using only ids from the memory list; never invent ids. Take this angle:";
+const REVIEW_DIRECTIVE: &str = "You review candidate answers for Vidkosha Cortex's deep reasoning mode. Critique each candidate you are shown for errors, gaps and unsupported claims, then vote for the single best one. Reply with JSON only: {\"critique\": \"<short critique covering every candidate>\", \"vote\": <candidate number>}.";
+const SYNTHESIS_DIRECTIVE: &str = "You write the final answer for Vidkosha Cortex's deep reasoning mode. Start from the winning candidate, fold in valid points raised by the reviewers, and follow the plan. Keep [mem:<id>] citations that are verified; drop unverified ones or mark the claim as unsupported. End with a short 'Sources' list of the memory ids you cite.";
+
+#[derive(Debug, Deserialize)]
+struct Review {
+    critique: String,
+    vote: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Candidate {
+    number: usize,
+    angle: &'static str,
+    text: String,
+}
+
+/// Result of a deep reasoning run: the final answer plus every stage as a perspective.
+#[derive(Debug, Clone)]
+pub struct DeepOutcome {
+    pub output: String,
+    pub perspectives: Vec<PerspectiveView>,
+    /// Votes, winner and citation checks, recorded under `router.deep`.
+    pub trace: Value,
+}
+
+/// Quality-over-latency pipeline: plan → branch → cross-critique and vote → verify citations
+/// against the RAG store → synthesize.
+pub struct MegaBrain {
+    llm: SharedLlmClient,
+    rag: Option<SharedRagAgent>,
+    branches: usize,
+    stage_timeout: Duration,
+}
+
+impl MegaBrain {
+    pub fn new(llm: SharedLlmClient) -> Self {
+        Self {
+            llm,
+            rag: None,
+            branches: DEFAULT_BRANCHES,
+            stage_timeout: Duration::from_secs(DEFAULT_STAGE_TIMEOUT_SECS),
+        }
+    }
+
+    pub fn with_rag(mut self, rag: Option<SharedRagAgent>) -> Self {
+        self.rag = rag;
+        self
+    }
+
+    pub fn with_branches(mut self, branches: usize) -> Self {
+        self.branches = branches.clamp(1, ANGLES.len());
+        self
+    }
+
+    pub fn with_stage_timeout(mut self, stage_timeout: Duration) -> Self {
+        self.stage_timeout = stage_timeout;
+        self
+    }
+
+    /// Tuned by `MEGABRAIN_BRANCHES` and `MEGABRAIN_STAGE_TIMEOUT_SECS`.
+    pub fn from_env(llm: SharedLlmClient, rag: Option<SharedRagAgent>) -> Self {
+        let branches = env::var("MEGABRAIN_BRANCHES")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(DEFAULT_BRANCHES);
+        let stage_timeout = env::var("MEGABRAIN_STAGE_TIMEOUT_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_STAGE_TIMEOUT_SECS);
+
+        Self::new(llm)
+            .with_rag(rag)
+            .with_branches(branches)
+            .with_stage_timeout(Duration::from_secs(stage_timeout))
+    }
+
+    /// The input with a deep reasoning token removed, or `None` when it carries none.
+    pub fn strip_token(input: &str) -> Option<String> {
+        let mut found = false;
+        let kept: Vec<&str> = input
+            .split_whitespace()
+            .filter(|word| {
+                let token = DEEP_TOKENS.iter().any(|t| word.eq_ignore_ascii_case(t));
+                found |= token;
+                !token
+            })
+            .collect();
+        found.then(|| kept.join(" "))
+    }
+
+    pub async fn run(&self, request: &AgentRequest) -> anyhow::Result<DeepOutcome> {
+        let grounding = self.ground(&request.input).await;
+        let memory_block = self.render_grounding(&grounding);
+        let brief = format!(
+            "User request:\n{}\n\nStored memories:\n{memory_block}",
+            request.input.trim()
+        );
+
+        let plan = self
+            .stage(
+                "plan",
+                self.llm
+                    .chat(&self.messages(PLAN_DIRECTIVE, &brief, &request.history)),
+            )
+            .await?;
+        info!(branches = self.branches, "Deep reasoning plan ready");
+
+        let candidates = self.branch(&brief, &plan).await?;
+        let (reviews, votes, winner) = self.review(&request.input, &candidates).await;
+        let citations = self.verify(&candidates, &grounding).await;
+
+        let mut synthesis_brief = format!(
+            "{brief}\n\nPlan:\n{plan}\n\nWinning candidate ({} votes):\n{}",
+            votes.get(&winner.number).copied().unwrap_or_default(),
+            winner.text.trim()
+        );
+        if !reviews.is_empty() {
+            synthesis_brief.push_str("\n\nReviewer critiques:");
+            for (reviewer, review) in &reviews {
+                synthesis_brief.push_str(&format!("\n- Reviewer {reviewer}: {}", review.trim()));
+            }
+        }
+        synthesis_brief.push_str(&format!("\n\nCitation check:\n{}", citations.report()));
+
+        let output = self
+            .stage(
+                "synthesize",
+                chat_with_sink(
+                    self.llm.as_ref(),
+                    &self.messages(SYNTHESIS_DIRECTIVE, &synthesis_brief, &request.history),
+                    request.stream.as_ref(),
+                ),
+            )
+            .await?;
+
+        let mut perspectives = vec![perspective("MegaBrain:plan", &plan)];
+        for candidate in &candidates {
+            let votes = votes.get(&candidate.number).copied().unwrap_or_default();
+            let mut view = perspective(
+                &format!("MegaBrain:candidate-{}", candidate.number),
+                &candidate.text,
+            );
+            view.summary = format!("{} ({votes} votes)", candidate.angle);
+            perspectives.push(view);
+        }
+        for (reviewer, review) in &reviews {
+            perspectives.push(perspective(
+                &format!("MegaBrain:critique-{reviewer}"),
+                review,
+            ));
+        }
+        perspectives.push(perspective("MegaBrain:verify", &citations.report()));
+
+        Ok(DeepOutcome {
+            output,
+            perspectives,
+            trace: json!({
+                "candidates": candidates.len(),
+                "votes": votes,
+                "winner": winner.number,
+                "citations": {
+                    "verified": citations.verified,
+                    "unverified": citations.unverified,
+                    "checked": citations.checked,
+                },
+            }),
+        })
+    }
+
+    async fn stage<F>(&self, name: &str, fut: F) -> anyhow::Result<String>
+    where
+        F: Future<Output = anyhow::Result<String>>,
+    {
+        tokio::time::timeout(self.stage_timeout, fut)
+            .await
+            .with_context(|| format!("Deep reasoning stage '{name}' timed out"))?
+            .with_context(|| format!("Deep reasoning stage '{name}' failed"))
+    }
+
+    fn messages(&self, system: &str, content: &str, history: &[ChatMessage]) -> Vec<ChatMessage> {
+        PromptBuilder::new(PromptBudget::for_window(self.llm.context_window()), system)
+            .history(history)
+            .request(content)
+            .build()
+    }
+
+    async fn ground(&self, input: &str) -> Vec<MemoryRecord> {
+        let Some(rag) = self.rag.as_ref() else {
+            return Vec::new();
+        };
+        let query = MemoryQuery {
+            query: input.to_string(),
+            filters: MemoryFilters::default(),
+            limit: GROUNDING_LIMIT,
+        };
+        match rag.handle(MemoryRequest::Retrieve(query)).await {
+            Ok(response) => response.records,
+            Err(err) => {
+                warn!(
+                    ?err,
+                    "Deep reasoning grounding failed; continuing without memories"
+                );
+                Vec::new()
+            }
+        }
+    }
+
+    fn render_grounding(&self, records: &[MemoryRecord]) -> String {
+        if records.is_empty() {
+            return String::from("(none)");
+        }
+        let budget = PromptBudget::for_window(self.llm.context_window()).memory_tokens();
+        let per_record = budget / records.len();
+        records
+            .iter()
+            .filter_map(|record| {
+                let id = record.id.as_deref()?;
+                Some(format!(
+                    "[mem:{id}] {} ({}): {}\n{}",
+                    record.topic,
+                    record.agent_name,
+                    record.summary.trim(),
+                    truncate_to_tokens(record.full_content.trim(), per_record.saturating_sub(30))
+                ))
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    async fn branch(&self, brief: &str, plan: &str) -> anyhow::Result<Vec<Candidate>> {
+        let content = format!("{brief}\n\nPlan:\n{plan}");
+        let runs = ANGLES
+            .iter()
+            .take(self.branches)
+            .enumerate()
+            .map(|(idx, angle)| {
+                let messages =
+                    self.messages(&format!("{CANDIDATE_DIRECTIVE} {angle}."), &content, &[]);
+                async move {
+                    let text = self.stage("branch", self.llm.chat(&messages)).await;
+                    (idx + 1, *angle, text)
+                }
+            });
+
+        let mut candidates = Vec::new();
+        for (number, angle, text) in join_all(runs).await {
+            match text {
+                Ok(text) if !text.trim().is_empty() => candidates.push(Candidate {
+                    number,
+                    angle,
+                    text,
+                }),
+                Ok(_) => warn!(number, "Deep reasoning candidate was empty"),
+                Err(err) => warn!(?err, number, "Deep reasoning candidate failed"),
+            }
+        }
+        if candidates.is_empty() {
+            bail!("Deep reasoning produced no candidate answers");
+        }
+        Ok(candidates)
+    }
+
+    /// Every candidate's author reviews the others and votes for one of them. Ties go to the
+    /// lower candidate number; with a single candidate there is nothing to vote on.
+    async fn review(
+        &self,
+        input: &str,
+        candidates: &[Candidate],
+    ) -> (Vec<(usize, String)>, BTreeMap<usize, usize>, Candidate) {
+        let mut votes: BTreeMap<usize, usize> = candidates.iter().map(|c| (c.number, 0)).collect();
+        if candidates.len() < 2 {
+            return (Vec::new(), votes, candidates[0].clone());
+        }
+
+        let runs = candidates.iter().map(|reviewer| {
+            let others: Vec<&Candidate> = candidates
+                .iter()
+                .filter(|c| c.number != reviewer.number)
+                .collect();
+            let mut content = format!("User request:\n{}\n", input.trim());
+            for other in &others {
+                content.push_str(&format!(
+                    "\nCandidate {}:\n{}\n",
+                    other.number,
+                    other.text.trim()
+                ));
+            }
+            let messages = self.messages(REVIEW_DIRECTIVE, &content, &[]);
+            async move {
+                let reply = tokio::time::timeout(
+                    self.stage_timeout,
+                    chat_json::<Review>(self.llm.as_ref(), &messages),
+                )
+                .await;
+                (reviewer.number, others, reply)
+            }
+        });
+
+        let mut reviews = Vec::new();
+        for (reviewer, others, reply) in join_all(runs).await {
+            match reply {
+                Ok(Ok(JsonReply::Valid { value, .. })) => {
+                    if others.iter().any(|c| c.number == value.vote) {
+                        *votes.entry(value.vote).or_default() += 1;
+                    }
+                    reviews.push((reviewer, value.critique));
+                }
+                Ok(Ok(JsonReply::Invalid { reason })) => {
+                    warn!(%reason, reviewer, "Deep reasoning review was not valid JSON")
+                }
+                Ok(Err(err)) => warn!(?err, reviewer, "Deep reasoning review failed"),
+                Err(_) => warn!(reviewer, "Deep reasoning review timed out"),
+            }
+        }
+
+        let best = votes.values().copied().max().unwrap_or_default();
+        let winner = candidates
+            .iter()
+            .find(|c| votes.get(&c.number) == Some(&best))
+            .unwrap_or(&candidates[0])
+            .clone();
+        (reviews, votes, winner)
+    }
+
+    /// Check every `[mem:<id>]` the candidates cite: ids from the grounding set are known,
+    /// anything else is looked up in the RAG store.
+    async fn verify(&self, candidates: &[Candidate], grounding: &[MemoryRecord]) -> Citations {
+        let mut cited: Vec<String> = Vec::new();
+        for candidate in candidates {
+            for id in cited_ids(&candidate.text) {
+                if !cited.contains(&id) {
+                    cited.push(id);
+                }
+            }
+        }
+
+        let known: HashSet<&str> = grounding
+            .iter()
+            .filter_map(|record| record.id.as_deref())
+            .collect();
+        let mut citations = Citations {
+            checked: self.rag.is_some(),
+            ..Citations::default()
+        };
+        for id in cited {
+            let exists = known.contains(id.as_str()) || self.lookup(&id).await;
+            if exists {
+                citations.verified.push(id);
+            } else {
+                citations.unverified.push(id);
+            }
+        }
+        citations
+    }
+
+    async fn lookup(&self, id: &str) -> bool {
+        let Some(rag) = self.rag.as_ref() else {
+            return false;
+        };
+        let request = MemoryRequest::Get(MemoryGetRequest { id: id.to_string() });
+        match rag.handle(request).await {
+            Ok(response) => !response.records.is_empty(),
+            Err(err) => {
+                warn!(?err, id, "Citation lookup failed");
+                false
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Citations {
+    verified: Vec<String>,
+    unverified: Vec<String>,
+    /// False when no RAG store is attached, so nothing could be verified.
+    checked: bool,
+}
+
+impl Citations {
+    fn report(&self) -> String {
+        if !self.checked {
+            return String::from("Memory is disabled; no citation could be verified.");
+        }
+        if self.verified.is_empty() && self.unverified.is_empty() {
+            return String::from("No memory ids were cited.");
+        }
+        let list = |ids: &[String]| {
+            if ids.is_empty() {
+                String::from("none")
+            } else {
+                ids.join(", ")
+            }
+        };
+        format!(
+            "Verified: {}\nUnverified (not in the RAG store): {}",
+            list(&self.verified),
+            list(&self.unverified)
+        )
+    }
+}
+
+/// Ids cited as `[mem:<id>]`, in order of appearance.
+fn cited_ids(text: &str) -> Vec<String> {
+    text.split("[mem:")
+        .skip(1)
+        .filter_map(|tail| tail.split_once(']'))
+        .map(|(id, _)| id.trim().to_string())
+        .filter(|id| !id.is_empty())
+        .collect()
+}
+
+fn perspective(role: &str, body: &str) -> PerspectiveView {
+    PerspectiveView {
+        role: role.to_string(),
+        summary: body.lines().next().unwrap_or_default().to_string(),
+        body: body.to_string(),
+        ..PerspectiveView::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use async_trait::async_trait;
+    use chrono::Utc;
+
+    use super::*;
+    use crate::llm_client::{ChatRole, LlmClient};
+    use crate::rag::agent::RagAgent;
+    use crate::rag::client::SharedRagClient;
+    use crate::rag::mock::MockRagClient;
+    use crate::rag::MemoryWriteRequest;
+
+    /// Answers by stage, keyed off the system prompt; reviewers all vote for candidate 2.
+    struct StageScript;
+
+    #[async_trait]
+    impl LlmClient for StageScript {
+        async fn chat(&self, messages: &[ChatMessage]) -> anyhow::Result<String> {
+            let system = messages
+                .iter()
+                .find(|m| m.role == ChatRole::System)
+                .map(|m| m.content.as_str())
+                .unwrap_or_default();
+            let user = messages
+                .last()
+                .map(|m| m.content.as_str())
+                .unwrap_or_default();
+            Ok(if system.starts_with("You plan") {
+                String::from("1. Check the stored design")
+            } else if system.starts_with("You draft") {
+                if system.contains("thorough") {
+                    String::from("Thorough answer citing [mem:mock-memory-1] and [mem:ghost-9]")
+                } else {
+                    String::from("Short answer [mem:mock-memory-1]")
+                }
+            } else if system.starts_with("You review") {
+                let vote = if user.contains("Candidate 2") { 2 } else { 1 };
+                format!(r#"{{"critique": "fine", "vote": {vote}}}"#)
+            } else {
+                format!("FINAL\n{user}")
+            })
+        }
+    }
+
+    #[test]
+    fn strips_deep_tokens_and_parses_citations() {
+        assert_eq!(
+            MegaBrain::strip_token("@MegaBrain plan the migration").as_deref(),
+            Some("plan the migration")
+        );
+        assert_eq!(MegaBrain::strip_token("plan the migration"), None);
+        assert_eq!(
+            cited_ids("see [mem:a-1] and [mem: b2 ] but not [mem:]"),
+            ["a-1", "b2"]
+        );
+    }
+
+    #[tokio::test]
+    async fn votes_verifies_citations_and_traces_every_stage() {
+        let client: SharedRagClient = Arc::new(MockRagClient::default());
+        let rag = Arc::new(RagAgent::new(client));
+        rag.handle(MemoryRequest::Write(MemoryWriteRequest {
+            record: MemoryRecord {
+                id: None,
+                agent_name: String::from("CTOAgent"),
+                topic: String::from("architecture"),
+                project: None,
+                conversation_id: None,
+                timestamp: Utc::now(),
+                summary: String::from("Router design"),
+                full_content: String::from("Tiered router"),
+                confidence: 0.8,
+                open_questions: Vec::new(),
+                perspectives: Vec::new(),
+                messages: Vec::new(),
+                artifacts: Vec::new(),
+                tool_calls: Vec::new(),
+                metadata: None,
+            },
+        }))
+        .await
+        .unwrap();
+
+        let brain = MegaBrain::new(Arc::new(StageScript))
+            .with_rag(Some(rag))
+            .with_branches(3);
+        let outcome = brain
+            .run(&AgentRequest::new("redesign the router"))
+            .await
+            .unwrap();
+
+        assert_eq!(outcome.trace["winner"], 2);
+        assert_eq!(
+            outcome.trace["citations"]["verified"],
+            json!(["mock-memory-1"])
+        );
+        assert_eq!(outcome.trace["citations"]["unverified"], json!(["ghost-9"]));
+        assert!(outcome.output.starts_with("FINAL"));
+        assert!(outcome
+            .output
+            .contains("Unverified (not in the RAG store): ghost-9"));
+
+        let roles: Vec<_> = outcome
+            .perspectives
+            .iter()
+            .map(|p| p.role.as_str())
+            .collect();
+        assert_eq!(
+            roles,
+            [
+                "MegaBrain:plan",
+                "MegaBrain:candidate-1",
+                "MegaBrain:candidate-2",
+                "MegaBrain:candidate-3",
+                "MegaBrain:critique-1",
+                "MegaBrain:critique-2",
+                "MegaBrain:critique-3",
+                "MegaBrain:verify",
+            ]
+        );
+    }
+}
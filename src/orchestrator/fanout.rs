@@ -0,0 +1,217 @@
+use std::collections::HashSet;
+use std::env;
+use std::time::Duration;
+
+use crate::agents::prompt::{truncate_to_tokens, PromptBudget, PromptBuilder};
+use crate::agents::AgentRequest;
+use crate::llm_client::{chat_with_sink, SharedLlmClient};
+use crate::orchestrator::router::RoutingDecision;
+use crate::rag::PerspectiveView;
+use crate::usage::estimate_tokens;
+
+const DEFAULT_MAX_AGENTS: usize = 3;
+const DEFAULT_MIN_SCORE: f32 = 0.6;
+const DEFAULT_TIMEOUT_MS: u64 = 30_000;
+
+const SYNTHESIZER_DIRECTIVE: &str = "You are the synthesizer of Vidkosha Cortex. Several specialists answered the same request from their own angle. Merge them into one answer: state shared conclusions once, surface disagreements and trade-offs explicitly, attribute key points to the specialist that raised them, and finish with one combined list of next steps. Do not add claims none of them made.";
+
+/// One specialist's answer to a fanned-out request.
+#[derive(Debug, Clone)]
+pub struct Contribution {
+    pub agent: String,
+    pub output: String,
+}
+
+impl Contribution {
+    pub fn perspective(&self) -> PerspectiveView {
+        PerspectiveView {
+            role: self.agent.clone(),
+            summary: self.output.lines().next().unwrap_or_default().to_string(),
+            body: self.output.clone(),
+            ..PerspectiveView::default()
+        }
+    }
+}
+
+/// Runs cross-cutting requests past several specialists at once, then merges their answers
+/// with a synthesizer pass.
+pub struct FanOut {
+    synthesizer: SharedLlmClient,
+    max_agents: usize,
+    min_score: f32,
+    timeout: Duration,
+}
+
+impl FanOut {
+    pub fn new(synthesizer: SharedLlmClient) -> Self {
+        Self {
+            synthesizer,
+            max_agents: DEFAULT_MAX_AGENTS,
+            min_score: DEFAULT_MIN_SCORE,
+            timeout: Duration::from_millis(DEFAULT_TIMEOUT_MS),
+        }
+    }
+
+    pub fn with_max_agents(mut self, max_agents: usize) -> Self {
+        self.max_agents = max_agents;
+        self
+    }
+
+    pub fn with_min_score(mut self, min_score: f32) -> Self {
+        self.min_score = min_score;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Enabled by `ROUTING_FANOUT_ENABLED`; tuned by `ROUTING_FANOUT_MAX_AGENTS`,
+    /// `ROUTING_FANOUT_MIN_SCORE` and `ROUTING_FANOUT_TIMEOUT_MS`.
+    pub fn from_env(synthesizer: SharedLlmClient) -> Option<Self> {
+        let enabled = env::var("ROUTING_FANOUT_ENABLED")
+            .ok()
+            .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+        if !enabled {
+            return None;
+        }
+
+        let max_agents = env::var("ROUTING_FANOUT_MAX_AGENTS")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(DEFAULT_MAX_AGENTS);
+        let min_score = env::var("ROUTING_FANOUT_MIN_SCORE")
+            .ok()
+            .and_then(|v| v.parse::<f32>().ok())
+            .unwrap_or(DEFAULT_MIN_SCORE);
+        let timeout_ms = env::var("ROUTING_FANOUT_TIMEOUT_MS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_TIMEOUT_MS);
+
+        Some(
+            Self::new(synthesizer)
+                .with_max_agents(max_agents)
+                .with_min_score(min_score)
+                .with_timeout(Duration::from_millis(timeout_ms)),
+        )
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Distinct registered specialists among the decision's candidates that clear the score
+    /// floor, best first. Fewer than two means the request is not cross-cutting.
+    pub fn select(
+        &self,
+        decision: &RoutingDecision,
+        is_registered: impl Fn(&str) -> bool,
+    ) -> Vec<String> {
+        let mut candidates: Vec<_> = decision
+            .candidates
+            .iter()
+            .filter(|candidate| candidate.score >= self.min_score)
+            .collect();
+        candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+        let mut seen = HashSet::new();
+        let agents: Vec<String> = candidates
+            .into_iter()
+            .filter(|candidate| is_registered(&candidate.agent))
+            .filter(|candidate| seen.insert(candidate.agent.clone()))
+            .map(|candidate| candidate.agent.clone())
+            .take(self.max_agents)
+            .collect();
+
+        if agents.len() < 2 {
+            Vec::new()
+        } else {
+            agents
+        }
+    }
+
+    /// Merge the contributions into one answer, streaming it when the request has a sink.
+    pub async fn synthesize(
+        &self,
+        request: &AgentRequest,
+        contributions: &[Contribution],
+    ) -> anyhow::Result<String> {
+        let budget = PromptBudget::for_window(self.synthesizer.context_window());
+        let framing = format!("User request:\n{}\n\n", request.input.trim());
+        // Split what is left evenly so a verbose specialist cannot crowd out the others.
+        let share = budget
+            .prompt_tokens()
+            .saturating_sub(
+                estimate_tokens(SYNTHESIZER_DIRECTIVE) as usize
+                    + estimate_tokens(&framing) as usize,
+            )
+            .checked_div(contributions.len())
+            .unwrap_or_default();
+
+        let mut brief = framing;
+        brief.push_str("Specialist answers:");
+        for contribution in contributions {
+            brief.push_str(&format!(
+                "\n\n## {}\n{}",
+                contribution.agent,
+                truncate_to_tokens(contribution.output.trim(), share.saturating_sub(8))
+            ));
+        }
+
+        let messages = PromptBuilder::new(budget, SYNTHESIZER_DIRECTIVE)
+            .history(&request.history)
+            .request(brief)
+            .build();
+        chat_with_sink(
+            self.synthesizer.as_ref(),
+            &messages,
+            request.stream.as_ref(),
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm_client::EchoLlmClient;
+    use crate::orchestrator::router::{RouteCandidate, RouterIntent};
+
+    fn candidate(agent: &str, score: f32) -> RouteCandidate {
+        RouteCandidate {
+            agent: agent.to_string(),
+            source: String::from("rule:test"),
+            score,
+            detail: None,
+        }
+    }
+
+    #[test]
+    fn selects_distinct_registered_candidates_above_floor() {
+        let fan_out = FanOut::new(EchoLlmClient::shared()).with_max_agents(2);
+        let decision = RoutingDecision::new(
+            RouterIntent::Engineering,
+            0.8,
+            String::from("test"),
+            "CTOAgent",
+        )
+        .with_candidates(vec![
+            candidate("CTOAgent", 0.8),
+            candidate("CTOAgent", 0.7),
+            candidate("Unregistered", 0.78),
+            candidate("OpsChainAgent", 0.74),
+            candidate("ResearcherAgent", 0.72),
+        ]);
+
+        let agents = fan_out.select(&decision, |agent| agent != "Unregistered");
+        assert_eq!(agents, ["CTOAgent", "OpsChainAgent"]);
+
+        let strict = FanOut::new(EchoLlmClient::shared()).with_min_score(0.75);
+        assert!(strict.select(&decision, |_| true).len() == 2);
+        let stricter = FanOut::new(EchoLlmClient::shared()).with_min_score(0.79);
+        assert!(stricter.select(&decision, |_| true).is_empty());
+    }
+}
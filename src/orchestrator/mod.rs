@@ -1,4 +1,10 @@
+pub mod deep;
+pub mod fanout;
 pub mod router;
 pub mod routing;
+pub mod session;
 
+pub use deep::MegaBrain;
+pub use fanout::FanOut;
 pub use router::OrchestratorRouter;
+pub use session::{ConversationSession, SessionConfig};
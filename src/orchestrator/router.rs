@@ -2,22 +2,38 @@ use std::collections::HashMap;
 use std::fmt;
 use std::sync::Arc;
 
-use crate::agents::{Agent, AgentBehavior, AgentRequest, AgentResponse};
-use crate::orchestrator::routing::SemanticRouter;
-use crate::rag::{MemoryRecord, MemoryRequest, MemoryResponse, MemoryWriteRequest, SharedRagAgent};
+use crate::agents::{Agent, AgentBehavior, AgentRequest, AgentResponse, Reflection, Reflective};
+use crate::orchestrator::deep::MegaBrain;
+use crate::orchestrator::fanout::{Contribution, FanOut};
+use crate::orchestrator::routing::{IntentClassifier, RoutingRulesHandle, SemanticRouter};
+use crate::rag::{
+    MemoryRecord, MemoryRequest, MemoryResponse, MemoryWriteRequest, MessageRecord,
+    PerspectiveView, SharedRagAgent,
+};
+use crate::usage::UsageScope;
 use chrono::Utc;
+use futures::future::join_all;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use tracing::{instrument, warn};
 
 type SpecialistHandle = Arc<dyn AgentBehavior>;
 
+/// Specialist-to-specialist handoffs followed per request before the chain is cut off.
+pub const DEFAULT_MAX_HANDOFFS: usize = 2;
+
 /// Wrapper around the primary Agent; later this will select specialist agents or pipelines.
 pub struct OrchestratorRouter {
     front_desk: Agent,
     specialists: HashMap<String, SpecialistHandle>,
     rag_agent: Option<SharedRagAgent>,
+    rules: Arc<RoutingRulesHandle>,
     semantic_router: Option<SemanticRouter>,
+    classifier: Option<IntentClassifier>,
+    fan_out: Option<FanOut>,
+    max_handoffs: usize,
+    reflection: Option<Arc<Reflection>>,
+    deep: Option<MegaBrain>,
 }
 
 impl OrchestratorRouter {
@@ -26,7 +42,13 @@ impl OrchestratorRouter {
             front_desk,
             specialists: HashMap::new(),
             rag_agent: None,
+            rules: Arc::new(RoutingRulesHandle::default()),
             semantic_router: None,
+            classifier: None,
+            fan_out: None,
+            max_handoffs: DEFAULT_MAX_HANDOFFS,
+            reflection: None,
+            deep: None,
         }
     }
 
@@ -34,29 +56,286 @@ impl OrchestratorRouter {
     where
         A: AgentBehavior + 'static,
     {
-        self.specialists.insert(name.into(), Arc::new(agent));
+        let name = name.into();
+        let handle = self.reflect(&name, Arc::new(agent));
+        self.specialists.insert(name, handle);
+        self
+    }
+
+    /// Wrap every specialist, registered before or after this call, in a critique pass.
+    pub fn with_reflection(mut self, reflection: Reflection) -> Self {
+        self.reflection = Some(Arc::new(reflection));
+        let specialists = std::mem::take(&mut self.specialists);
+        self.specialists = specialists
+            .into_iter()
+            .map(|(name, agent)| {
+                let agent = self.reflect(&name, agent);
+                (name, agent)
+            })
+            .collect();
         self
     }
 
+    fn reflect(&self, name: &str, agent: SpecialistHandle) -> SpecialistHandle {
+        match &self.reflection {
+            Some(reflection) if reflection.applies_to(name) => {
+                Arc::new(Reflective::new(name, agent, reflection.clone()))
+            }
+            _ => agent,
+        }
+    }
+
     pub fn with_rag_agent(mut self, rag_agent: SharedRagAgent) -> Self {
         self.rag_agent = Some(rag_agent);
         self
     }
 
+    pub fn with_routing_rules(mut self, rules: Arc<RoutingRulesHandle>) -> Self {
+        self.rules = rules;
+        self
+    }
+
     pub fn with_semantic_router(mut self, router: SemanticRouter) -> Self {
         self.semantic_router = Some(router);
         self
     }
 
+    pub fn with_intent_classifier(mut self, classifier: IntentClassifier) -> Self {
+        self.classifier = Some(classifier);
+        self
+    }
+
+    pub fn with_fan_out(mut self, fan_out: FanOut) -> Self {
+        self.fan_out = Some(fan_out);
+        self
+    }
+
+    /// Pipeline for requests that carry `@megabrain`/`/deep` or set `deep_reasoning`.
+    pub fn with_deep_reasoning(mut self, deep: MegaBrain) -> Self {
+        self.deep = Some(deep);
+        self
+    }
+
+    /// Cap on handoffs followed per request; 0 ignores handoff requests.
+    pub fn with_max_handoffs(mut self, max_handoffs: usize) -> Self {
+        self.max_handoffs = max_handoffs;
+        self
+    }
+
     #[instrument(skip_all, fields(input = %request.input))]
     pub async fn dispatch(&self, request: AgentRequest) -> anyhow::Result<RoutedAgentResponse> {
-        let decision = self.classify_intent(&request);
-        let (mut response, executed_agent) =
-            self.route_to_agent(&decision, request.clone()).await?;
-        response.metadata = Some(
-            self.build_metadata(&request, &response, &decision, &executed_agent, None)
-                .await,
+        let request_id = Self::request_id(&request);
+
+        if let Some(deep) = &self.deep {
+            let stripped = MegaBrain::strip_token(&request.input);
+            if request.deep_reasoning || stripped.is_some() {
+                let mut request = request;
+                if let Some(input) = stripped {
+                    request.input = input;
+                }
+                return self.dispatch_deep(deep, request, &request_id).await;
+            }
+        }
+
+        // A reply to a save the front desk is holding must not reach a specialist.
+        let decision = if self.front_desk.answers_pending_save(&request) {
+            RoutingDecision::pending_save()
+        } else {
+            self.classify_intent(&request, &request_id).await
+        };
+
+        if let Some(fan_out) = &self.fan_out {
+            let agents = fan_out.select(&decision, |agent| self.specialists.contains_key(agent));
+            if !agents.is_empty() {
+                return self
+                    .dispatch_fan_out(fan_out, request, decision, &request_id, agents)
+                    .await;
+            }
+        }
+
+        let scope = UsageScope::new(request_id.clone(), self.agent_for(&decision));
+
+        scope
+            .run(async {
+                let (mut response, executed_agent) =
+                    self.route_to_agent(&decision, request.clone()).await?;
+                let handoffs = self
+                    .follow_handoffs(&request, &mut response, &executed_agent, &request_id)
+                    .await;
+                let agent_meta = response.metadata.take();
+                let mut metadata = self
+                    .build_metadata(&request, &response, &decision, &executed_agent, &[])
+                    .await;
+                // Agent-supplied sections such as `reflection` sit beside `router` and `memory`.
+                if let (Some(serde_json::Value::Object(extra)), Some(map)) =
+                    (agent_meta, metadata.as_object_mut())
+                {
+                    for (key, value) in extra {
+                        map.entry(key).or_insert(value);
+                    }
+                }
+                if !handoffs.is_empty() {
+                    metadata["router"]["handoffs"] = json!(handoffs);
+                }
+                response.metadata = Some(metadata);
+
+                Ok(RoutedAgentResponse {
+                    response,
+                    decision,
+                    executed_agent,
+                })
+            })
+            .await
+    }
+
+    /// Run several specialists concurrently, each under its own timeout and usage scope, then
+    /// merge what came back. A lone survivor is returned as-is; if none answer, the front desk
+    /// takes the request.
+    async fn dispatch_fan_out(
+        &self,
+        fan_out: &FanOut,
+        request: AgentRequest,
+        decision: RoutingDecision,
+        request_id: &str,
+        agents: Vec<String>,
+    ) -> anyhow::Result<RoutedAgentResponse> {
+        // Specialists answer in parallel, so only the merged answer is streamed.
+        let mut quiet = request.clone();
+        quiet.stream = None;
+
+        // A specialist's handoffs are followed inside its own timeout, so the chain's answers
+        // land in its contribution.
+        let runs = agents.iter().map(|name| {
+            let agent = self.specialists[name.as_str()].clone();
+            let scope = UsageScope::new(request_id, name.as_str());
+            let request = quiet.clone();
+            async move {
+                let outcome = tokio::time::timeout(fan_out.timeout(), async {
+                    let mut response = scope.run(agent.handle(request.clone())).await?;
+                    let handoffs = self
+                        .follow_handoffs(&request, &mut response, name, request_id)
+                        .await;
+                    anyhow::Ok((response, handoffs))
+                })
+                .await;
+                (name, outcome)
+            }
+        });
+
+        let mut contributions = Vec::new();
+        let mut tool_calls = Vec::new();
+        let mut handoffs = Vec::new();
+        let mut agent_meta = serde_json::Map::new();
+        let mut failed = Vec::new();
+        for (name, outcome) in join_all(runs).await {
+            match outcome {
+                Ok(Ok((mut response, chain))) => {
+                    tool_calls.extend(response.tool_calls);
+                    handoffs.extend(chain);
+                    if let Some(meta) = response.metadata.take() {
+                        agent_meta.insert(name.clone(), meta);
+                    }
+                    contributions.push(Contribution {
+                        agent: name.clone(),
+                        output: response.output,
+                    });
+                }
+                Ok(Err(err)) => {
+                    warn!(?err, agent = %name, "Fan-out specialist failed");
+                    failed.push(json!({ "agent": name, "reason": err.to_string() }));
+                }
+                Err(_) => {
+                    warn!(agent = %name, "Fan-out specialist timed out");
+                    failed.push(json!({ "agent": name, "reason": "timeout" }));
+                }
+            }
+        }
+
+        let (output, executed_agent) = match contributions.as_slice() {
+            [] => {
+                let scope = UsageScope::new(request_id, "Agent");
+                let response = scope.run(self.front_desk.handle(request.clone())).await?;
+                tool_calls.extend(response.tool_calls);
+                (response.output, String::from("Agent"))
+            }
+            [only] => {
+                request.emit(&only.output);
+                (only.output.clone(), only.agent.clone())
+            }
+            _ => {
+                let scope = UsageScope::new(request_id, "Synthesizer");
+                let merged = scope
+                    .run(fan_out.synthesize(&request, &contributions))
+                    .await?;
+                (merged, String::from("Synthesizer"))
+            }
+        };
+
+        let mut response = AgentResponse::new(output).with_tool_calls(tool_calls);
+        let perspectives: Vec<PerspectiveView> = contributions
+            .iter()
+            .map(Contribution::perspective)
+            .collect();
+        let mut metadata = self
+            .build_metadata(
+                &request,
+                &response,
+                &decision,
+                &executed_agent,
+                &perspectives,
+            )
+            .await;
+        metadata["router"]["fan_out"] = json!({
+            "agents": agents,
+            "answered": contributions.iter().map(|c| c.agent.as_str()).collect::<Vec<_>>(),
+            "failed": failed,
+        });
+        // Agent-supplied sections such as `reflection` are kept per specialist.
+        if !agent_meta.is_empty() {
+            metadata["router"]["fan_out"]["metadata"] = serde_json::Value::Object(agent_meta);
+        }
+        if !handoffs.is_empty() {
+            metadata["router"]["handoffs"] = json!(handoffs);
+        }
+        response.metadata = Some(metadata);
+
+        Ok(RoutedAgentResponse {
+            response,
+            decision,
+            executed_agent,
+        })
+    }
+
+    /// Answer through the deep reasoning pipeline, skipping intent classification. Every stage
+    /// is kept as a perspective on the stored transcript.
+    async fn dispatch_deep(
+        &self,
+        deep: &MegaBrain,
+        request: AgentRequest,
+        request_id: &str,
+    ) -> anyhow::Result<RoutedAgentResponse> {
+        let decision = RoutingDecision::new(
+            RouterIntent::GeneralSupport,
+            1.0,
+            String::from("Deep reasoning requested"),
+            "MegaBrain",
         );
+        let executed_agent = String::from("MegaBrain");
+        let scope = UsageScope::new(request_id, executed_agent.as_str());
+        let outcome = scope.run(deep.run(&request)).await?;
+
+        let mut response = AgentResponse::new(outcome.output);
+        let mut metadata = self
+            .build_metadata(
+                &request,
+                &response,
+                &decision,
+                &executed_agent,
+                &outcome.perspectives,
+            )
+            .await;
+        metadata["router"]["deep"] = outcome.trace;
+        response.metadata = Some(metadata);
 
         Ok(RoutedAgentResponse {
             response,
@@ -65,10 +344,108 @@ impl OrchestratorRouter {
         })
     }
 
-    fn classify_intent(&self, request: &AgentRequest) -> RoutingDecision {
+    /// Follow `AgentResponse::handoff` from specialist to specialist, appending each answer to
+    /// `response`. Unknown targets, revisits and chains past `max_handoffs` are refused and
+    /// recorded; every hop lands in the returned chain.
+    async fn follow_handoffs(
+        &self,
+        request: &AgentRequest,
+        response: &mut AgentResponse,
+        first_agent: &str,
+        request_id: &str,
+    ) -> Vec<HandoffHop> {
+        let mut chain = Vec::new();
+        let mut visited = vec![first_agent.to_string()];
+        let mut from = first_agent.to_string();
+
+        while let Some(handoff) = response.handoff.take() {
+            let mut hop = HandoffHop {
+                from: from.clone(),
+                to: handoff.target.clone(),
+                reason: handoff.reason.clone(),
+                task: handoff.task.clone(),
+                status: HandoffStatus::Completed,
+            };
+            let target = self.specialists.get(handoff.target.as_str());
+            let refused = if chain.len() >= self.max_handoffs {
+                Some(HandoffStatus::DepthLimit)
+            } else if visited.contains(&handoff.target) {
+                Some(HandoffStatus::Cycle)
+            } else if target.is_none() {
+                Some(HandoffStatus::UnknownAgent)
+            } else {
+                None
+            };
+            let (Some(agent), None) = (target, refused) else {
+                hop.status = refused.unwrap_or(HandoffStatus::UnknownAgent);
+                warn!(from = %hop.from, to = %hop.to, status = ?hop.status, "Handoff refused");
+                chain.push(hop);
+                break;
+            };
+
+            let sub_request = AgentRequest {
+                input: format!(
+                    "{}\n\nHanded off by {from} ({}). Original request:\n{}",
+                    handoff.task.trim(),
+                    handoff.reason.trim(),
+                    request.input.trim()
+                ),
+                ..request.clone()
+            };
+            let header = format!(
+                "\n\n---\n{} (handoff from {from}): {}\n\n",
+                handoff.target,
+                handoff.reason.trim()
+            );
+            request.emit(&header);
+
+            let scope = UsageScope::new(request_id, handoff.target.as_str());
+            match scope.run(agent.handle(sub_request)).await {
+                Ok(next) => {
+                    response.output.push_str(&header);
+                    response.output.push_str(next.output.trim());
+                    response.tool_calls.extend(next.tool_calls);
+                    response.handoff = next.handoff;
+                    visited.push(handoff.target.clone());
+                    from = handoff.target;
+                }
+                Err(err) => {
+                    warn!(?err, to = %hop.to, "Handoff target failed");
+                    hop.status = HandoffStatus::Failed;
+                }
+            }
+            chain.push(hop);
+        }
+
+        chain
+    }
+
+    /// Usage attribution id: the session message id when present, otherwise a fresh one.
+    fn request_id(request: &AgentRequest) -> String {
+        request
+            .turn
+            .as_ref()
+            .map(|turn| turn.message_id.clone())
+            .unwrap_or_else(|| format!("req-{}", Utc::now().timestamp_millis()))
+    }
+
+    /// Name of the agent `route_to_agent` will run for this decision.
+    fn agent_for(&self, decision: &RoutingDecision) -> String {
+        if self
+            .specialists
+            .contains_key(decision.suggested_agent.as_str())
+        {
+            decision.suggested_agent.clone()
+        } else {
+            String::from("Agent")
+        }
+    }
+
+    async fn classify_intent(&self, request: &AgentRequest, request_id: &str) -> RoutingDecision {
         let normalized = request.input.to_lowercase();
+        let rules = self.rules.snapshot();
 
-        if let Some(explicit) = Self::explicit_specialist(&normalized) {
+        if let Some(explicit) = rules.explicit_specialist(&normalized) {
             return RoutingDecision::new(
                 RouterIntent::GeneralSupport,
                 0.95,
@@ -77,14 +454,19 @@ impl OrchestratorRouter {
             );
         }
 
-        for rule in ROUTING_RULES {
-            if let Some(decision) = rule.evaluate(&normalized) {
+        if let Some(decision) = rules.evaluate(&normalized) {
+            return decision;
+        }
+
+        if let Some(semantic) = &self.semantic_router {
+            if let Some(decision) = semantic.classify(&normalized).await {
                 return decision;
             }
         }
 
-        if let Some(semantic) = &self.semantic_router {
-            if let Some(decision) = semantic.classify(&normalized) {
+        if let Some(classifier) = &self.classifier {
+            let scope = UsageScope::new(request_id, "Router");
+            if let Some(decision) = scope.run(classifier.classify(&normalized)).await {
                 return decision;
             }
         }
@@ -92,29 +474,6 @@ impl OrchestratorRouter {
         RoutingDecision::general_default()
     }
 
-    fn explicit_specialist(normalized_input: &str) -> Option<String> {
-        // Allow users to target a specialist directly, e.g., "@ctoagent" or "specialist:researcher".
-        const TOKENS: &[(&str, &str)] = &[
-            ("@ctoagent", "CTOAgent"),
-            ("specialist:cto", "CTOAgent"),
-            ("@seniorengineeragent", "SeniorEngineerAgent"),
-            ("specialist:seniorengineer", "SeniorEngineerAgent"),
-            ("@researcheragent", "ResearcherAgent"),
-            ("specialist:researcher", "ResearcherAgent"),
-            ("@opscostagent", "OpsChainAgent"),
-            ("specialist:opscost", "OpsChainAgent"),
-            ("@opschainagent", "OpsChainAgent"),
-            ("specialist:opschain", "OpsChainAgent"),
-            ("@ragagent", "RagAgent"),
-            ("specialist:rag", "RagAgent"),
-        ];
-
-        TOKENS
-            .iter()
-            .find(|(token, _)| normalized_input.contains(*token))
-            .map(|(_, agent)| agent.to_string())
-    }
-
     async fn route_to_agent(
         &self,
         decision: &RoutingDecision,
@@ -135,28 +494,19 @@ impl OrchestratorRouter {
         response: &AgentResponse,
         decision: &RoutingDecision,
         executed_agent: &str,
-        prefill_meta: Option<serde_json::Value>,
+        perspectives: &[PerspectiveView],
     ) -> serde_json::Value {
         let router_meta = decision.metadata_payload(executed_agent);
         let memory_meta = self
-            .capture_transcript(request, response, decision, executed_agent)
+            .capture_transcript(request, response, decision, executed_agent, perspectives)
             .await;
 
-        match (prefill_meta, memory_meta) {
-            (Some(prefill), Some(memory)) => json!({
-                "router": router_meta,
-                "memory_prefill": prefill,
-                "memory": memory,
-            }),
-            (Some(prefill), None) => json!({
-                "router": router_meta,
-                "memory_prefill": prefill,
-            }),
-            (None, Some(memory)) => json!({
+        match memory_meta {
+            Some(memory) => json!({
                 "router": router_meta,
                 "memory": memory,
             }),
-            (None, None) => json!({ "router": router_meta }),
+            None => json!({ "router": router_meta }),
         }
     }
 
@@ -166,6 +516,7 @@ impl OrchestratorRouter {
         response: &AgentResponse,
         decision: &RoutingDecision,
         executed_agent: &str,
+        perspectives: &[PerspectiveView],
     ) -> Option<serde_json::Value> {
         let rag_agent = self.rag_agent.as_ref()?;
         let summary = response
@@ -179,22 +530,50 @@ impl OrchestratorRouter {
             request.input.trim(),
             response.output.trim()
         );
+        let now = Utc::now();
+        let conversation_id = request
+            .turn
+            .as_ref()
+            .map(|turn| turn.conversation_id.clone());
+        let messages = match request.turn.as_ref() {
+            Some(turn) => vec![
+                MessageRecord {
+                    message_id: Some(turn.message_id.clone()),
+                    role: "user".into(),
+                    content: request.input.trim().to_string(),
+                    created_at: Some(now),
+                    conversation_id: conversation_id.clone(),
+                    reply_to: turn.reply_to.clone(),
+                    metadata: None,
+                },
+                MessageRecord {
+                    message_id: Some(turn.reply_message_id.clone()),
+                    role: "assistant".into(),
+                    content: response.output.trim().to_string(),
+                    created_at: Some(now),
+                    conversation_id: conversation_id.clone(),
+                    reply_to: Some(turn.message_id.clone()),
+                    metadata: Some(json!({ "agent": executed_agent })),
+                },
+            ],
+            None => Vec::new(),
+        };
 
         let record = MemoryRecord {
             id: None,
             agent_name: executed_agent.to_string(),
             topic: format!("router.{}", decision.intent),
             project: None,
-            conversation_id: None,
-            timestamp: Utc::now(),
+            conversation_id,
+            timestamp: now,
             summary,
             full_content,
             confidence: decision.confidence,
             open_questions: Vec::new(),
-            perspectives: Vec::new(),
-            messages: Vec::new(),
+            perspectives: perspectives.to_vec(),
+            messages,
             artifacts: Vec::new(),
-            tool_calls: Vec::new(),
+            tool_calls: response.tool_calls.clone(),
             metadata: Some(json!({
                 "rationale": decision.rationale,
                 "suggested_agent": decision.suggested_agent,
@@ -249,6 +628,7 @@ impl RoutedAgentResponse {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum RouterIntent {
     GeneralSupport,
     Engineering,
@@ -271,12 +651,46 @@ impl fmt::Display for RouterIntent {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HandoffStatus {
+    Completed,
+    Failed,
+    UnknownAgent,
+    Cycle,
+    DepthLimit,
+}
+
+/// One specialist-to-specialist handoff, as recorded under `router.handoffs`.
+#[derive(Debug, Clone, Serialize)]
+pub struct HandoffHop {
+    pub from: String,
+    pub to: String,
+    pub reason: String,
+    pub task: String,
+    pub status: HandoffStatus,
+}
+
+/// A scored alternative considered while routing, kept for misroute debugging.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteCandidate {
+    pub agent: String,
+    /// Tier that produced the score, e.g. `semantic` or `rule:architecture`.
+    pub source: String,
+    pub score: f32,
+    /// Matched keywords, veto reason, or other tier-specific notes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RoutingDecision {
     pub intent: RouterIntent,
     pub confidence: f32,
     pub rationale: String,
     pub suggested_agent: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub candidates: Vec<RouteCandidate>,
 }
 
 impl RoutingDecision {
@@ -291,9 +705,15 @@ impl RoutingDecision {
             confidence,
             rationale,
             suggested_agent: suggested_agent.to_string(),
+            candidates: Vec::new(),
         }
     }
 
+    pub(crate) fn with_candidates(mut self, candidates: Vec<RouteCandidate>) -> Self {
+        self.candidates = candidates;
+        self
+    }
+
     fn general_default() -> Self {
         Self {
             intent: RouterIntent::GeneralSupport,
@@ -302,143 +722,313 @@ impl RoutingDecision {
                 "Defaulting to Agent until specialist routing rules match the request.",
             ),
             suggested_agent: "Agent".to_string(),
+            candidates: Vec::new(),
+        }
+    }
+
+    fn pending_save() -> Self {
+        Self {
+            intent: RouterIntent::GeneralSupport,
+            confidence: 1.0,
+            rationale: String::from("Reply to a save awaiting confirmation in this session."),
+            suggested_agent: "Agent".to_string(),
+            candidates: Vec::new(),
         }
     }
 
     fn metadata_payload(&self, executed_agent: &str) -> serde_json::Value {
-        json!({
+        let mut payload = json!({
             "router_intent": self.intent.to_string(),
             "confidence": self.confidence,
             "rationale": self.rationale,
             "suggested_agent": self.suggested_agent,
             "executed_agent": executed_agent,
-        })
+        });
+        if !self.candidates.is_empty() {
+            payload["candidates"] = json!(self.candidates);
+        }
+        payload
     }
 }
 
-#[derive(Debug)]
-struct RoutingRule {
-    intent: RouterIntent,
-    keywords: &'static [&'static str],
-    rationale: &'static str,
-    suggested_agent: &'static str,
-    confidence: f32,
-}
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::sync::Arc;
+    use std::time::Duration;
 
-impl RoutingRule {
-    const fn new(
-        intent: RouterIntent,
-        keywords: &'static [&'static str],
-        rationale: &'static str,
-        suggested_agent: &'static str,
-        confidence: f32,
-    ) -> Self {
-        Self {
-            intent,
-            keywords,
-            rationale,
-            suggested_agent,
-            confidence,
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::agents::{AgentCatalog, Specialist, TurnContext};
+    use crate::cassette::{CassetteMode, CassetteStore};
+    use crate::llm_client::registry::LlmProvidersConfig;
+    use crate::llm_client::{EchoLlmClient, LlmRegistry};
+    use crate::rag::agent::RagAgent;
+    use crate::rag::client::SharedRagClient;
+    use crate::rag::mock::MockRagClient;
+    use crate::rag::{MemoryFilters, MemoryQuery};
+
+    struct Fixed {
+        reply: &'static str,
+        delay: Duration,
+        metadata: Option<serde_json::Value>,
+    }
+
+    #[async_trait]
+    impl AgentBehavior for Fixed {
+        async fn handle(&self, _request: AgentRequest) -> anyhow::Result<AgentResponse> {
+            tokio::time::sleep(self.delay).await;
+            let mut response = AgentResponse::from_model_output(self.reply);
+            response.metadata = self.metadata.clone();
+            Ok(response)
         }
     }
 
-    fn evaluate(&self, normalized_input: &str) -> Option<RoutingDecision> {
-        self.keywords
+    fn fixed(reply: &'static str, delay_ms: u64) -> Fixed {
+        Fixed {
+            reply,
+            delay: Duration::from_millis(delay_ms),
+            metadata: None,
+        }
+    }
+
+    /// Replays `fixtures/cassettes/router/llm.json` with no live model. Run with
+    /// `VK_CORTEX_CASSETTE_MODE=record` and provider credentials to re-record it.
+    #[tokio::test]
+    async fn replays_recorded_turns_through_the_router() {
+        let config: LlmProvidersConfig = serde_json::from_str(
+            r#"{
+                "providers": {"main": {"model": "gpt-4o-mini", "context_window": 16000}},
+                "default": ["main"],
+                "agents": {"CTOAgent": {"providers": ["main"], "temperature": 0.2}}
+            }"#,
+        )
+        .unwrap();
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/cassettes/router/llm.json");
+        let recording = std::env::var("VK_CORTEX_CASSETTE_MODE").is_ok_and(|m| m == "record");
+        let registry = if recording {
+            let store = Arc::new(CassetteStore::open(path, CassetteMode::Record).unwrap());
+            LlmRegistry::from_config(&config)
+                .unwrap()
+                .with_cassette(store)
+        } else {
+            let store = Arc::new(CassetteStore::open(path, CassetteMode::Strict).unwrap());
+            LlmRegistry::replay_only(Some(&config), store).unwrap()
+        };
+
+        let catalog = AgentCatalog::default();
+        let mut router =
+            OrchestratorRouter::new(Agent::new(registry.for_agent("Agent"), None, None));
+        for definition in catalog.definitions() {
+            let llm = registry.for_agent(definition.model_entry());
+            router = router.with_specialist(
+                &definition.name,
+                Specialist::new(definition, llm).with_peers(&catalog),
+            );
+        }
+
+        let routed = router
+            .dispatch(AgentRequest::new(
+                "What architecture should the billing service use for nightly invoicing?",
+            ))
+            .await
+            .unwrap();
+        assert_eq!(routed.executed_agent(), "CTOAgent");
+        assert!(!routed.into_output().output.trim().is_empty());
+
+        let routed = router
+            .dispatch(AgentRequest::new("Hi! What can you help me with?"))
+            .await
+            .unwrap();
+        assert_eq!(routed.executed_agent(), "Agent");
+        assert!(!routed.into_output().output.trim().is_empty());
+    }
+
+    #[tokio::test]
+    async fn pending_save_replies_stay_with_the_front_desk() {
+        let rag: SharedRagAgent = Arc::new(RagAgent::new(Arc::new(MockRagClient::default())));
+        let router =
+            OrchestratorRouter::new(Agent::new(EchoLlmClient::shared(), Some(rag.clone()), None))
+                .with_specialist("CTOAgent", fixed("Architecture answer.", 0));
+        let turn = |conversation_id: &str| TurnContext {
+            conversation_id: conversation_id.to_string(),
+            message_id: String::from("m"),
+            reply_message_id: String::from("r"),
+            reply_to: None,
+        };
+        let reply = "yes topic=architecture";
+
+        let routed = router
+            .dispatch(AgentRequest::new("remember milk").with_turn(turn("c1")))
+            .await
+            .unwrap();
+        assert!(routed.into_output().output.contains("Say 'save it'"));
+
+        // Without a parked save the same words are an architecture request.
+        let routed = router
+            .dispatch(AgentRequest::new(reply).with_turn(turn("c2")))
+            .await
+            .unwrap();
+        assert_eq!(routed.executed_agent(), "CTOAgent");
+
+        let routed = router
+            .dispatch(AgentRequest::new(reply).with_turn(turn("c1")))
+            .await
+            .unwrap();
+        assert_eq!(routed.executed_agent(), "Agent");
+        assert!(routed
+            .into_output()
+            .output
+            .starts_with("Saved. id=mock-memory-1 topic=architecture."));
+    }
+
+    #[tokio::test]
+    async fn deep_token_or_flag_bypasses_specialists() {
+        let router = OrchestratorRouter::new(Agent::new(EchoLlmClient::shared(), None, None))
+            .with_specialist("CTOAgent", fixed("Single answer.", 0))
+            .with_deep_reasoning(MegaBrain::new(EchoLlmClient::shared()).with_branches(2));
+
+        let routed = router
+            .dispatch(AgentRequest::new("@megabrain architecture for billing"))
+            .await
+            .unwrap();
+        assert_eq!(routed.executed_agent(), "MegaBrain");
+        let deep = &routed.into_output().metadata.unwrap()["router"]["deep"];
+        assert_eq!(deep["candidates"], 2);
+
+        let routed = router
+            .dispatch(AgentRequest::new("architecture for billing").with_deep_reasoning(true))
+            .await
+            .unwrap();
+        assert_eq!(routed.executed_agent(), "MegaBrain");
+
+        let routed = router
+            .dispatch(AgentRequest::new("architecture for billing"))
+            .await
+            .unwrap();
+        assert_eq!(routed.executed_agent(), "CTOAgent");
+    }
+
+    #[tokio::test]
+    async fn fan_out_merges_answers_and_stores_perspectives() {
+        let client: SharedRagClient = Arc::new(MockRagClient::default());
+        let rag = Arc::new(RagAgent::new(client));
+        let router = OrchestratorRouter::new(Agent::new(EchoLlmClient::shared(), None, None))
+            .with_specialist("CTOAgent", fixed("Split the service in two.", 0))
+            .with_specialist("OpsChainAgent", fixed("Two GPU nodes cost more.", 0))
+            .with_specialist("ResearcherAgent", fixed("Never arrives.", 500))
+            .with_rag_agent(rag.clone())
+            .with_fan_out(
+                FanOut::new(EchoLlmClient::shared()).with_timeout(Duration::from_millis(50)),
+            );
+
+        let routed = router
+            .dispatch(AgentRequest::new(
+                "compare a roadmap for splitting the gpu cluster",
+            ))
+            .await
+            .unwrap();
+        assert_eq!(routed.executed_agent(), "Synthesizer");
+        let output = routed.into_output();
+        assert!(output
+            .output
+            .contains("## CTOAgent\nSplit the service in two."));
+        assert!(output.output.contains("## OpsChainAgent"));
+
+        let fan_out = &output.metadata.unwrap()["router"]["fan_out"];
+        assert_eq!(fan_out["answered"], json!(["CTOAgent", "OpsChainAgent"]));
+        assert_eq!(fan_out["failed"][0]["agent"], "ResearcherAgent");
+        assert_eq!(fan_out["failed"][0]["reason"], "timeout");
+
+        let stored = rag
+            .handle(MemoryRequest::Retrieve(MemoryQuery {
+                query: String::from("roadmap"),
+                filters: MemoryFilters::default(),
+                limit: 5,
+            }))
+            .await
+            .unwrap()
+            .records;
+        let roles: Vec<_> = stored[0]
+            .perspectives
             .iter()
-            .copied()
-            .find(|keyword| normalized_input.contains(keyword))
-            .map(|keyword| {
-                let rationale = format!("{} (matched '{}')", self.rationale, keyword);
-                RoutingDecision::new(
-                    self.intent,
-                    self.confidence,
-                    rationale,
-                    self.suggested_agent,
-                )
-            })
+            .map(|p| p.role.as_str())
+            .collect();
+        assert_eq!(roles, ["CTOAgent", "OpsChainAgent"]);
     }
-}
 
-const ROUTING_RULES: &[RoutingRule] = &[
-    RoutingRule::new(
-        RouterIntent::Engineering,
-        &[
-            "architecture",
-            "architect",
-            "system design",
-            "design doc",
-            "roadmap",
-            "diagram",
-            "blueprint",
-        ],
-        "Request focuses on systems architecture or roadmapping",
-        "CTOAgent",
-        0.8,
-    ),
-    RoutingRule::new(
-        RouterIntent::Engineering,
-        &[
-            "rust",
-            "code",
-            "implement",
-            "function",
-            "struct",
-            "compile",
-            "bug",
-            "refactor",
-        ],
-        "Request mentions engineering or code-level work",
-        "SeniorEngineerAgent",
-        0.82,
-    ),
-    RoutingRule::new(
-        RouterIntent::Research,
-        &[
-            "research",
-            "summarize",
-            "compare",
-            "investigate",
-            "source",
-            "paper",
-            "article",
-            "report",
-        ],
-        "Request leans toward research or synthesis",
-        "ResearcherAgent",
-        0.78,
-    ),
-    RoutingRule::new(
-        RouterIntent::Operations,
-        &[
-            "deploy",
-            "infrastructure",
-            "infra",
-            "cost",
-            "budget",
-            "capacity",
-            "pipeline",
-            "gpu",
-            "cluster",
-        ],
-        "Request references operational planning or cost work",
-        "OpsChainAgent",
-        0.74,
-    ),
-    RoutingRule::new(
-        RouterIntent::Memory,
-        &[
-            "memory",
-            "remember",
-            "recall",
-            "save",
-            "helix",
-            "rag",
-            "vector",
-            "embedding",
-            "knowledge base",
-        ],
-        "Request mentions memory/Helix/RAG operations",
-        "RagAgent",
-        0.78,
-    ),
-];
+    #[tokio::test]
+    async fn fan_out_keeps_agent_metadata_and_follows_handoffs() {
+        let router = OrchestratorRouter::new(Agent::new(EchoLlmClient::shared(), None, None))
+            .with_specialist(
+                "CTOAgent",
+                Fixed {
+                    metadata: Some(json!({ "reflection": { "status": "revised" } })),
+                    ..fixed(
+                        "Split the service in two.\nHANDOFF: {\"agent\": \"PlannerAgent\", \"reason\": \"sequencing\", \"task\": \"Order the migration\"}",
+                        0,
+                    )
+                },
+            )
+            .with_specialist("OpsChainAgent", fixed("Two GPU nodes cost more.", 0))
+            .with_specialist("PlannerAgent", fixed("Move reads first.", 0))
+            .with_fan_out(FanOut::new(EchoLlmClient::shared()));
+
+        let routed = router
+            .dispatch(AgentRequest::new(
+                "compare a roadmap for splitting the gpu cluster",
+            ))
+            .await
+            .unwrap();
+        let output = routed.into_output();
+        assert!(output.output.contains("Move reads first."));
+        assert!(!output.output.contains("HANDOFF:"));
+
+        let metadata = output.metadata.unwrap();
+        assert_eq!(
+            metadata["router"]["fan_out"]["metadata"]["CTOAgent"]["reflection"]["status"],
+            "revised"
+        );
+        let handoffs = &metadata["router"]["handoffs"];
+        assert_eq!(handoffs[0]["from"], "CTOAgent");
+        assert_eq!(handoffs[0]["to"], "PlannerAgent");
+        assert_eq!(handoffs[0]["status"], "completed");
+    }
+
+    #[tokio::test]
+    async fn follows_handoffs_and_stops_on_cycles() {
+        let router = OrchestratorRouter::new(Agent::new(EchoLlmClient::shared(), None, None))
+            .with_specialist(
+                "SeniorEngineerAgent",
+                fixed(
+                    "Add a cache layer.\nHANDOFF: {\"agent\": \"OpsChainAgent\", \"reason\": \"cost question\", \"task\": \"Price a Redis node\"}",
+                    0,
+                ),
+            )
+            .with_specialist(
+                "OpsChainAgent",
+                fixed(
+                    "About $40/month.\nHANDOFF: {\"agent\": \"SeniorEngineerAgent\", \"reason\": \"back to you\", \"task\": \"Implement it\"}",
+                    0,
+                ),
+            );
+
+        let routed = router
+            .dispatch(AgentRequest::new("@seniorengineeragent add caching"))
+            .await
+            .unwrap();
+        assert_eq!(routed.executed_agent(), "SeniorEngineerAgent");
+        let output = routed.into_output();
+        assert!(output.output.starts_with("Add a cache layer."));
+        assert!(output
+            .output
+            .contains("OpsChainAgent (handoff from SeniorEngineerAgent): cost question"));
+        assert!(output.output.ends_with("About $40/month."));
+
+        let handoffs = &output.metadata.unwrap()["router"]["handoffs"];
+        assert_eq!(handoffs[0]["to"], "OpsChainAgent");
+        assert_eq!(handoffs[0]["status"], "completed");
+        assert_eq!(handoffs[1]["status"], "cycle");
+    }
+}
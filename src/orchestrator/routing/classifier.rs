@@ -0,0 +1,306 @@
+use std::collections::HashMap;
+use std::env;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Deserialize;
+use tracing::{debug, warn};
+
+use crate::llm_client::{chat_json, ChatMessage, JsonReply, SharedLlmClient};
+use crate::orchestrator::router::{RouteCandidate, RouterIntent, RoutingDecision};
+
+const DEFAULT_TIMEOUT_MS: u64 = 1_500;
+const DEFAULT_MIN_CONFIDENCE: f32 = 0.5;
+const CACHE_CAPACITY: usize = 256;
+/// Name the model uses to decline every specialist.
+const FRONT_DESK: &str = "Agent";
+
+const CLASSIFIER_DIRECTIVE: &str = "You route requests for Vidkosha Cortex. Pick the single best agent for the request from the list. Choose Agent (the front desk) when no specialist clearly fits. Reply with JSON only: {\"agent\": \"<name>\", \"confidence\": <0-1>, \"reason\": \"<short reason>\"}.";
+
+/// A routable specialist as described to the classifier.
+#[derive(Debug, Clone)]
+pub struct SpecialistProfile {
+    pub name: String,
+    pub intent: RouterIntent,
+    pub description: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ClassifierReply {
+    agent: String,
+    confidence: f32,
+    #[serde(default)]
+    reason: Option<String>,
+}
+
+/// Last routing tier: asks an LLM to pick a specialist when rules and prototypes did not match.
+/// Each call is bounded by a latency budget, and verdicts are cached by input hash.
+pub struct IntentClassifier {
+    llm: SharedLlmClient,
+    specialists: Vec<SpecialistProfile>,
+    timeout: Duration,
+    min_confidence: f32,
+    cache: Mutex<HashMap<String, Option<RoutingDecision>>>,
+}
+
+impl IntentClassifier {
+    pub fn new(llm: SharedLlmClient, specialists: Vec<SpecialistProfile>) -> Self {
+        Self {
+            llm,
+            specialists,
+            timeout: Duration::from_millis(DEFAULT_TIMEOUT_MS),
+            min_confidence: DEFAULT_MIN_CONFIDENCE,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_min_confidence(mut self, min_confidence: f32) -> Self {
+        self.min_confidence = min_confidence;
+        self
+    }
+
+    /// Enabled by `ROUTING_CLASSIFIER_ENABLED`; tuned by `ROUTING_CLASSIFIER_TIMEOUT_MS` and
+    /// `ROUTING_CLASSIFIER_MIN_CONFIDENCE`. `specialists` is the registered roster.
+    pub fn from_env(llm: SharedLlmClient, specialists: Vec<SpecialistProfile>) -> Option<Self> {
+        let enabled = env::var("ROUTING_CLASSIFIER_ENABLED")
+            .ok()
+            .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+        if !enabled {
+            return None;
+        }
+
+        let timeout_ms = env::var("ROUTING_CLASSIFIER_TIMEOUT_MS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_TIMEOUT_MS);
+        let min_confidence = env::var("ROUTING_CLASSIFIER_MIN_CONFIDENCE")
+            .ok()
+            .and_then(|v| v.parse::<f32>().ok())
+            .unwrap_or(DEFAULT_MIN_CONFIDENCE);
+
+        Some(
+            Self::new(llm, specialists)
+                .with_timeout(Duration::from_millis(timeout_ms))
+                .with_min_confidence(min_confidence),
+        )
+    }
+
+    fn messages(&self, input: &str) -> Vec<ChatMessage> {
+        let roster = self
+            .specialists
+            .iter()
+            .map(|profile| format!("- {}: {}", profile.name, profile.description))
+            .chain(std::iter::once(format!(
+                "- {FRONT_DESK}: front desk for general questions, chit-chat and anything else"
+            )))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        vec![
+            ChatMessage::system(CLASSIFIER_DIRECTIVE),
+            ChatMessage::user(format!("Agents:\n{roster}\n\nRequest:\n{}", input.trim())),
+        ]
+    }
+
+    /// Specialist picked by the model, or `None` for the front desk, a low-confidence verdict,
+    /// an unusable reply, or a blown latency budget.
+    pub async fn classify(&self, normalized_input: &str) -> Option<RoutingDecision> {
+        if normalized_input.trim().is_empty() {
+            return None;
+        }
+
+        let key = blake3::hash(normalized_input.as_bytes())
+            .to_hex()
+            .to_string();
+        if let Some(hit) = self
+            .cache
+            .lock()
+            .expect("classifier cache poisoned")
+            .get(&key)
+            .cloned()
+        {
+            debug!("Intent classifier cache hit");
+            return hit;
+        }
+
+        let messages = self.messages(normalized_input);
+        let call = chat_json::<ClassifierReply>(self.llm.as_ref(), &messages);
+        let reply = match tokio::time::timeout(self.timeout, call).await {
+            Ok(Ok(reply)) => reply,
+            Ok(Err(err)) => {
+                warn!(?err, "Intent classifier call failed");
+                return None;
+            }
+            Err(_) => {
+                warn!(
+                    timeout_ms = self.timeout.as_millis() as u64,
+                    "Intent classifier exceeded its latency budget"
+                );
+                return None;
+            }
+        };
+
+        let decision = match reply {
+            JsonReply::Valid { value, .. } => self.decide(value),
+            JsonReply::Invalid { reason } => {
+                // Not cached: a garbled reply says nothing about the input.
+                warn!(%reason, "Intent classifier returned unusable JSON");
+                return None;
+            }
+        };
+
+        let mut cache = self.cache.lock().expect("classifier cache poisoned");
+        if cache.len() >= CACHE_CAPACITY {
+            cache.clear();
+        }
+        cache.insert(key, decision.clone());
+
+        decision
+    }
+
+    fn decide(&self, reply: ClassifierReply) -> Option<RoutingDecision> {
+        let confidence = reply.confidence.clamp(0.0, 1.0);
+        let profile = self
+            .specialists
+            .iter()
+            .find(|profile| profile.name.eq_ignore_ascii_case(reply.agent.trim()));
+
+        let Some(profile) = profile else {
+            if !reply.agent.trim().eq_ignore_ascii_case(FRONT_DESK) {
+                warn!(agent = %reply.agent, "Intent classifier named an unknown agent");
+            }
+            return None;
+        };
+
+        if confidence < self.min_confidence {
+            debug!(
+                agent = %profile.name,
+                confidence, "Intent classifier verdict below confidence floor"
+            );
+            return None;
+        }
+
+        let reason = reply
+            .reason
+            .filter(|reason| !reason.trim().is_empty())
+            .unwrap_or_else(|| String::from("no reason given"));
+        let decision = RoutingDecision::new(
+            profile.intent,
+            confidence,
+            format!("LLM classifier chose {} ({})", profile.name, reason.trim()),
+            &profile.name,
+        );
+        Some(decision.with_candidates(vec![RouteCandidate {
+            agent: profile.name.clone(),
+            source: String::from("classifier"),
+            score: confidence,
+            detail: Some(reason.trim().to_string()),
+        }]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::agents::AgentCatalog;
+    use crate::llm_client::LlmClient;
+
+    fn profiles() -> Vec<SpecialistProfile> {
+        AgentCatalog::default().profiles()
+    }
+
+    struct FixedReply {
+        reply: &'static str,
+        delay: Duration,
+        calls: AtomicUsize,
+    }
+
+    impl FixedReply {
+        fn new(reply: &'static str, delay: Duration) -> Arc<Self> {
+            Arc::new(Self {
+                reply,
+                delay,
+                calls: AtomicUsize::new(0),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl LlmClient for FixedReply {
+        async fn chat(&self, _messages: &[ChatMessage]) -> anyhow::Result<String> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(self.delay).await;
+            Ok(self.reply.to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn routes_to_named_specialist_and_caches_verdict() {
+        let llm = FixedReply::new(
+            r#"{"agent": "opschainagent", "confidence": 0.8, "reason": "cost question"}"#,
+            Duration::ZERO,
+        );
+        let classifier = IntentClassifier::new(llm.clone(), profiles());
+
+        let decision = classifier.classify("what will this cost us").await.unwrap();
+        assert_eq!(decision.suggested_agent, "OpsChainAgent");
+        assert_eq!(decision.intent, RouterIntent::Operations);
+
+        classifier.classify("what will this cost us").await.unwrap();
+        assert_eq!(llm.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn front_desk_low_confidence_and_timeouts_fall_through() {
+        let front_desk =
+            FixedReply::new(r#"{"agent": "Agent", "confidence": 0.9}"#, Duration::ZERO);
+        let classifier = IntentClassifier::new(front_desk, profiles());
+        assert!(classifier.classify("hi there").await.is_none());
+
+        let unsure = FixedReply::new(
+            r#"{"agent": "CTOAgent", "confidence": 0.2}"#,
+            Duration::ZERO,
+        );
+        let classifier = IntentClassifier::new(unsure, profiles());
+        assert!(classifier.classify("maybe design?").await.is_none());
+
+        let slow = FixedReply::new(
+            r#"{"agent": "CTOAgent", "confidence": 0.9}"#,
+            Duration::from_millis(200),
+        );
+        let classifier =
+            IntentClassifier::new(slow.clone(), profiles()).with_timeout(Duration::from_millis(10));
+        assert!(classifier.classify("design this").await.is_none());
+        // Timeouts are not cached, so the next request tries again.
+        assert!(classifier.classify("design this").await.is_none());
+        assert_eq!(slow.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn unusable_replies_are_not_cached() {
+        let garbled = FixedReply::new("I think ops, probably", Duration::ZERO);
+        let classifier = IntentClassifier::new(garbled.clone(), profiles());
+
+        assert!(classifier
+            .classify("what will this cost us")
+            .await
+            .is_none());
+        let first = garbled.calls.load(Ordering::SeqCst);
+        assert!(classifier
+            .classify("what will this cost us")
+            .await
+            .is_none());
+        assert_eq!(garbled.calls.load(Ordering::SeqCst), first * 2);
+    }
+}
@@ -1,3 +1,7 @@
+pub mod classifier;
+pub mod rules;
 pub mod semantic;
 
+pub use classifier::IntentClassifier;
+pub use rules::RoutingRulesHandle;
 pub use semantic::SemanticRouter;
@@ -0,0 +1,840 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashSet};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+
+use anyhow::{bail, Context};
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::orchestrator::router::{RouteCandidate, RouterIntent, RoutingDecision};
+
+const DEFAULT_RULES_PATH: &str = "routing_rules.json";
+const DEFAULT_RELOAD_SECS: u64 = 5;
+/// Score added for each distinct keyword matched beyond the first.
+const EXTRA_MATCH_BONUS: f32 = 0.05;
+/// Rule scores stay below an explicit `@agent` request (0.95) unless configured higher.
+const MAX_RULE_SCORE: f32 = 0.95;
+
+/// One keyword rule in `routing_rules.json`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RuleConfig {
+    /// Stable id; a file rule named like a built-in replaces it.
+    pub name: String,
+    pub agent: String,
+    pub intent: RouterIntent,
+    /// Whole words or phrases; a trailing `*` also matches longer words (`deploy*`).
+    pub keywords: Vec<String>,
+    /// Any match vetoes the rule, e.g. `open source` for a research rule keyed on `source`.
+    #[serde(default)]
+    pub negative_keywords: Vec<String>,
+    #[serde(default)]
+    pub rationale: Option<String>,
+    pub confidence: f32,
+    /// Breaks ties between equal scores; higher wins, then declaration order.
+    #[serde(default)]
+    pub priority: i32,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RoutingRulesConfig {
+    /// Drop the built-in rules and aliases instead of layering the file on top of them.
+    #[serde(default)]
+    pub replace_defaults: bool,
+    /// Explicit targeting tokens, e.g. `"@opscostagent": "OpsChainAgent"`.
+    #[serde(default)]
+    pub aliases: BTreeMap<String, String>,
+    #[serde(default)]
+    pub rules: Vec<RuleConfig>,
+}
+
+impl RoutingRulesConfig {
+    /// Parse a rules file as TOML when it ends in `.toml`, and as JSON otherwise.
+    pub fn parse(path: &Path, raw: &str) -> anyhow::Result<Self> {
+        if path.extension().is_some_and(|ext| ext == "toml") {
+            Ok(toml::from_str(raw)?)
+        } else {
+            Ok(serde_json::from_str(raw)?)
+        }
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let mut names = HashSet::new();
+        for rule in &self.rules {
+            let name = rule.name.trim();
+            if name.is_empty() {
+                bail!("Routing rule names must not be empty");
+            }
+            if !names.insert(name) {
+                bail!("Routing rule '{name}' is defined more than once");
+            }
+            if rule.agent.trim().is_empty() {
+                bail!("Routing rule '{name}' has no agent");
+            }
+            if rule.keywords.is_empty() || rule.keywords.iter().any(|k| tokenize(k).is_empty()) {
+                bail!("Routing rule '{name}' needs at least one non-empty keyword");
+            }
+            if rule
+                .negative_keywords
+                .iter()
+                .any(|k| tokenize(k).is_empty())
+            {
+                bail!("Routing rule '{name}' has an empty negative keyword");
+            }
+            if !(0.0..=1.0).contains(&rule.confidence) {
+                bail!(
+                    "Routing rule '{name}' confidence {} is outside 0.0..=1.0",
+                    rule.confidence
+                );
+            }
+        }
+
+        for (alias, agent) in &self.aliases {
+            if alias.trim().is_empty() || alias.chars().any(char::is_whitespace) {
+                bail!("Routing alias '{alias}' must be a single non-empty token");
+            }
+            if agent.trim().is_empty() {
+                bail!("Routing alias '{alias}' has no agent");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A keyword or phrase matched against whole words of the input.
+#[derive(Debug, Clone)]
+struct Keyword {
+    label: String,
+    words: Vec<String>,
+    /// Trailing `*`: the last word may be a prefix of the input word.
+    prefix: bool,
+}
+
+impl Keyword {
+    fn parse(raw: &str) -> Self {
+        let label = raw.trim().to_lowercase();
+        let prefix = label.ends_with('*');
+        Self {
+            words: tokenize(label.trim_end_matches('*')),
+            label,
+            prefix,
+        }
+    }
+
+    fn matches(&self, input: &[String]) -> bool {
+        let Some((last, head)) = self.words.split_last() else {
+            return false;
+        };
+        input.windows(self.words.len()).any(|window| {
+            let (window_last, window_head) = window.split_last().expect("non-empty window");
+            window_head == head && self.word_matches(window_last, last)
+        })
+    }
+
+    /// Exact word, a simple plural (`bug` → `bugs`), or a prefix for `*` keywords.
+    fn word_matches(&self, word: &str, keyword: &str) -> bool {
+        if word == keyword || (self.prefix && word.starts_with(keyword)) {
+            return true;
+        }
+        word.strip_prefix(keyword)
+            .is_some_and(|suffix| suffix == "s" || suffix == "es")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct RoutingRule {
+    name: String,
+    intent: RouterIntent,
+    keywords: Vec<Keyword>,
+    negative_keywords: Vec<Keyword>,
+    rationale: String,
+    suggested_agent: String,
+    confidence: f32,
+    priority: i32,
+}
+
+/// How one rule fared against an input.
+enum RuleOutcome {
+    Matched { score: f32, keywords: Vec<String> },
+    Vetoed { keyword: String },
+}
+
+impl RoutingRule {
+    fn builtin(
+        name: &str,
+        intent: RouterIntent,
+        keywords: &[&str],
+        negative_keywords: &[&str],
+        rationale: &str,
+        suggested_agent: &str,
+        confidence: f32,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            intent,
+            keywords: keywords.iter().map(|k| Keyword::parse(k)).collect(),
+            negative_keywords: negative_keywords
+                .iter()
+                .map(|k| Keyword::parse(k))
+                .collect(),
+            rationale: rationale.to_string(),
+            suggested_agent: suggested_agent.to_string(),
+            confidence,
+            priority: 0,
+        }
+    }
+
+    fn from_config(config: &RuleConfig) -> Self {
+        let name = config.name.trim().to_string();
+        Self {
+            rationale: config
+                .rationale
+                .clone()
+                .unwrap_or_else(|| format!("Matched routing rule '{name}'")),
+            name,
+            intent: config.intent,
+            keywords: config.keywords.iter().map(|k| Keyword::parse(k)).collect(),
+            negative_keywords: config
+                .negative_keywords
+                .iter()
+                .map(|k| Keyword::parse(k))
+                .collect(),
+            suggested_agent: config.agent.trim().to_string(),
+            confidence: config.confidence,
+            priority: config.priority,
+        }
+    }
+
+    fn evaluate(&self, words: &[String]) -> Option<RuleOutcome> {
+        let matched: Vec<String> = self
+            .keywords
+            .iter()
+            .filter(|keyword| keyword.matches(words))
+            .map(|keyword| keyword.label.clone())
+            .collect();
+        if matched.is_empty() {
+            return None;
+        }
+
+        if let Some(veto) = self
+            .negative_keywords
+            .iter()
+            .find(|keyword| keyword.matches(words))
+        {
+            return Some(RuleOutcome::Vetoed {
+                keyword: veto.label.clone(),
+            });
+        }
+
+        let extra = (matched.len() - 1) as f32 * EXTRA_MATCH_BONUS;
+        let score = (self.confidence + extra).min(self.confidence.max(MAX_RULE_SCORE));
+        Some(RuleOutcome::Matched {
+            score,
+            keywords: matched,
+        })
+    }
+}
+
+/// Compiled keyword rules and explicit-target aliases.
+#[derive(Debug, Clone)]
+pub struct RoutingRules {
+    rules: Vec<RoutingRule>,
+    /// Longest alias first so `@opschainagent` wins over any shorter prefix.
+    aliases: Vec<(String, String)>,
+}
+
+impl Default for RoutingRules {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl RoutingRules {
+    pub fn builtin() -> Self {
+        Self::assemble(builtin_rules(), builtin_aliases())
+    }
+
+    /// Layer a validated config over the built-ins (or replace them when asked to). `agents`
+    /// holds rules and aliases from agent definition files; they count as built-ins.
+    pub fn layered(
+        agents: &RoutingRulesConfig,
+        config: &RoutingRulesConfig,
+    ) -> anyhow::Result<Self> {
+        config.validate()?;
+
+        let (mut rules, mut aliases) = if config.replace_defaults {
+            (Vec::new(), Vec::new())
+        } else {
+            let (mut rules, mut aliases) = (builtin_rules(), builtin_aliases());
+            merge(&mut rules, &mut aliases, agents);
+            (rules, aliases)
+        };
+        merge(&mut rules, &mut aliases, config);
+
+        Ok(Self::assemble(rules, aliases))
+    }
+
+    fn load(path: &Path, agents: &RoutingRulesConfig) -> anyhow::Result<Self> {
+        let raw = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read routing rules {}", path.display()))?;
+        let config = RoutingRulesConfig::parse(path, &raw)
+            .with_context(|| format!("Failed to parse routing rules {}", path.display()))?;
+        Self::layered(agents, &config)
+            .with_context(|| format!("Invalid routing rules in {}", path.display()))
+    }
+
+    fn assemble(mut rules: Vec<RoutingRule>, mut aliases: Vec<(String, String)>) -> Self {
+        // Stable sorts: equal priorities keep declaration order.
+        rules.sort_by_key(|rule| Reverse(rule.priority));
+        aliases.sort_by_key(|(alias, _)| Reverse(alias.len()));
+        Self { rules, aliases }
+    }
+
+    pub fn rule_count(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Agent named by an explicit token such as `@ctoagent` or `specialist:researcher`.
+    pub fn explicit_specialist(&self, normalized_input: &str) -> Option<String> {
+        self.aliases
+            .iter()
+            .find(|(alias, _)| normalized_input.contains(alias.as_str()))
+            .map(|(_, agent)| agent.clone())
+    }
+
+    /// Score every rule and pick the best; ties go to priority, then declaration order.
+    /// Every matched or vetoed rule is kept as a candidate on the decision.
+    pub fn evaluate(&self, normalized_input: &str) -> Option<RoutingDecision> {
+        let words = tokenize(normalized_input);
+        let mut candidates = Vec::new();
+        let mut best: Option<(f32, &RoutingRule, Vec<String>)> = None;
+
+        // Rules are sorted by priority, so a strict `>` keeps the earlier rule on ties.
+        for rule in &self.rules {
+            match rule.evaluate(&words) {
+                None => {}
+                Some(RuleOutcome::Vetoed { keyword }) => candidates.push(RouteCandidate {
+                    agent: rule.suggested_agent.clone(),
+                    source: format!("rule:{}", rule.name),
+                    score: 0.0,
+                    detail: Some(format!("vetoed by '{keyword}'")),
+                }),
+                Some(RuleOutcome::Matched { score, keywords }) => {
+                    candidates.push(RouteCandidate {
+                        agent: rule.suggested_agent.clone(),
+                        source: format!("rule:{}", rule.name),
+                        score,
+                        detail: Some(format!("matched {}", quote_all(&keywords))),
+                    });
+                    if best.as_ref().is_none_or(|(top, _, _)| score > *top) {
+                        best = Some((score, rule, keywords));
+                    }
+                }
+            }
+        }
+
+        let (score, rule, keywords) = best?;
+        candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+        let rationale = format!("{} (matched {})", rule.rationale, quote_all(&keywords));
+        Some(
+            RoutingDecision::new(rule.intent, score, rationale, &rule.suggested_agent)
+                .with_candidates(candidates),
+        )
+    }
+}
+
+/// Routing rules shared with the router; file-backed instances reload when the file changes.
+pub struct RoutingRulesHandle {
+    path: Option<PathBuf>,
+    /// Rules contributed by agent definition files, re-applied on every reload.
+    agents: RoutingRulesConfig,
+    current: RwLock<Arc<RoutingRules>>,
+    modified: Mutex<Option<SystemTime>>,
+}
+
+impl Default for RoutingRulesHandle {
+    fn default() -> Self {
+        Self::fixed(RoutingRules::builtin())
+    }
+}
+
+impl RoutingRulesHandle {
+    pub fn fixed(rules: RoutingRules) -> Self {
+        Self {
+            path: None,
+            agents: RoutingRulesConfig::default(),
+            current: RwLock::new(Arc::new(rules)),
+            modified: Mutex::new(None),
+        }
+    }
+
+    /// Load `path`; an invalid file is an error here, unlike during hot reload.
+    pub fn from_file(path: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let path = path.into();
+        let modified = modified_at(&path);
+        let rules = RoutingRules::load(&path, &RoutingRulesConfig::default())?;
+        info!(
+            path = %path.display(),
+            rules = rules.rule_count(),
+            "Loaded routing rules"
+        );
+        Ok(Self {
+            path: Some(path),
+            agents: RoutingRulesConfig::default(),
+            current: RwLock::new(Arc::new(rules)),
+            modified: Mutex::new(modified),
+        })
+    }
+
+    /// Use `ROUTING_RULES_PATH` (default `routing_rules.json`) when present; built-ins otherwise.
+    pub fn from_env() -> anyhow::Result<Self> {
+        let explicit = env::var("ROUTING_RULES_PATH").ok();
+        let path = explicit
+            .clone()
+            .unwrap_or_else(|| DEFAULT_RULES_PATH.to_string());
+
+        if Path::new(&path).exists() {
+            Self::from_file(path)
+        } else if explicit.is_some() {
+            bail!("ROUTING_RULES_PATH points at missing file {path}");
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    /// Layer rules and aliases from agent definition files under the rules file. Unlike a hot
+    /// reload, an invalid combination is an error.
+    pub fn with_agent_rules(mut self, agents: RoutingRulesConfig) -> anyhow::Result<Self> {
+        agents
+            .validate()
+            .context("Invalid routing rules in agent definitions")?;
+        let rules = match self.path.as_deref() {
+            Some(path) => RoutingRules::load(path, &agents)?,
+            None => RoutingRules::layered(&agents, &RoutingRulesConfig::default())?,
+        };
+        self.current = RwLock::new(Arc::new(rules));
+        self.agents = agents;
+        Ok(self)
+    }
+
+    pub fn snapshot(&self) -> Arc<RoutingRules> {
+        self.current
+            .read()
+            .expect("routing rules lock poisoned")
+            .clone()
+    }
+
+    /// Re-read the file when its mtime moved. Invalid edits keep the previous rules.
+    pub fn reload_if_changed(&self) -> bool {
+        let Some(path) = self.path.as_ref() else {
+            return false;
+        };
+
+        let modified = modified_at(path);
+        {
+            let mut last = self.modified.lock().expect("routing rules lock poisoned");
+            if *last == modified {
+                return false;
+            }
+            *last = modified;
+        }
+
+        match RoutingRules::load(path, &self.agents) {
+            Ok(rules) => {
+                info!(
+                    path = %path.display(),
+                    rules = rules.rule_count(),
+                    "Reloaded routing rules"
+                );
+                *self.current.write().expect("routing rules lock poisoned") = Arc::new(rules);
+                true
+            }
+            Err(err) => {
+                warn!(?err, path = %path.display(), "Keeping previous routing rules");
+                false
+            }
+        }
+    }
+
+    /// Poll the rules file every `ROUTING_RULES_RELOAD_SECS` (default 5; 0 disables).
+    pub fn spawn_hot_reload(self: &Arc<Self>) {
+        if self.path.is_none() {
+            return;
+        }
+        let secs = env::var("ROUTING_RULES_RELOAD_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_RELOAD_SECS);
+        if secs == 0 {
+            return;
+        }
+
+        let handle = Arc::downgrade(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(Duration::from_secs(secs));
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                match handle.upgrade() {
+                    Some(handle) => {
+                        handle.reload_if_changed();
+                    }
+                    None => break,
+                }
+            }
+        });
+    }
+}
+
+/// Apply `config` on top: rules and aliases with a known name are replaced, new ones appended.
+fn merge(
+    rules: &mut Vec<RoutingRule>,
+    aliases: &mut Vec<(String, String)>,
+    config: &RoutingRulesConfig,
+) {
+    for rule in config.rules.iter().map(RoutingRule::from_config) {
+        match rules.iter_mut().find(|existing| existing.name == rule.name) {
+            Some(existing) => *existing = rule,
+            None => rules.push(rule),
+        }
+    }
+
+    for (alias, agent) in &config.aliases {
+        let alias = alias.trim().to_lowercase();
+        let agent = agent.trim().to_string();
+        match aliases.iter_mut().find(|(existing, _)| *existing == alias) {
+            Some(existing) => existing.1 = agent,
+            None => aliases.push((alias, agent)),
+        }
+    }
+}
+
+/// Lowercase alphanumeric words; everything else is a boundary.
+fn tokenize(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn quote_all(keywords: &[String]) -> String {
+    keywords
+        .iter()
+        .map(|keyword| format!("'{keyword}'"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn modified_at(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+fn builtin_aliases() -> Vec<(String, String)> {
+    [
+        ("@ctoagent", "CTOAgent"),
+        ("specialist:cto", "CTOAgent"),
+        ("@seniorengineeragent", "SeniorEngineerAgent"),
+        ("specialist:seniorengineer", "SeniorEngineerAgent"),
+        ("@researcheragent", "ResearcherAgent"),
+        ("specialist:researcher", "ResearcherAgent"),
+        ("@opscostagent", "OpsChainAgent"),
+        ("specialist:opscost", "OpsChainAgent"),
+        ("@opschainagent", "OpsChainAgent"),
+        ("specialist:opschain", "OpsChainAgent"),
+        ("@ragagent", "RagAgent"),
+        ("specialist:rag", "RagAgent"),
+    ]
+    .into_iter()
+    .map(|(alias, agent)| (alias.to_string(), agent.to_string()))
+    .collect()
+}
+
+fn builtin_rules() -> Vec<RoutingRule> {
+    vec![
+        RoutingRule::builtin(
+            "architecture",
+            RouterIntent::Engineering,
+            &[
+                "architecture",
+                "architect",
+                "system design",
+                "design doc",
+                "roadmap",
+                "diagram",
+                "blueprint",
+            ],
+            &[],
+            "Request focuses on systems architecture or roadmapping",
+            "CTOAgent",
+            0.8,
+        ),
+        RoutingRule::builtin(
+            "engineering",
+            RouterIntent::Engineering,
+            &[
+                "rust",
+                "code",
+                "implement*",
+                "function",
+                "struct",
+                "compile*",
+                "bug",
+                "refactor*",
+            ],
+            &[],
+            "Request mentions engineering or code-level work",
+            "SeniorEngineerAgent",
+            0.82,
+        ),
+        RoutingRule::builtin(
+            "research",
+            RouterIntent::Research,
+            &[
+                "research",
+                "summarize",
+                "compare",
+                "investigate",
+                "source",
+                "paper",
+                "article",
+                "report",
+            ],
+            &["open source"],
+            "Request leans toward research or synthesis",
+            "ResearcherAgent",
+            0.78,
+        ),
+        RoutingRule::builtin(
+            "operations",
+            RouterIntent::Operations,
+            &[
+                "deploy*",
+                "infrastructure",
+                "infra",
+                "cost",
+                "budget",
+                "capacity",
+                "pipeline",
+                "gpu",
+                "cluster",
+            ],
+            &[],
+            "Request references operational planning or cost work",
+            "OpsChainAgent",
+            0.74,
+        ),
+        RoutingRule::builtin(
+            "memory",
+            RouterIntent::Memory,
+            &[
+                "memory",
+                "memories",
+                "recall",
+                "retag",
+                "helix",
+                "rag",
+                "vector",
+                "embedding",
+                "knowledge base",
+            ],
+            // Saving and reminders are front-desk commands.
+            &["save", "remember", "remind", "jot", "write down"],
+            "Request mentions memory/Helix/RAG operations",
+            "RagAgent",
+            0.78,
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(raw: &str) -> RoutingRulesConfig {
+        serde_json::from_str(raw).expect("routing config")
+    }
+
+    #[test]
+    fn file_rules_layer_over_builtins_by_name_and_priority() {
+        let rules = RoutingRules::layered(
+            &RoutingRulesConfig::default(),
+            &config(
+                r#"{
+                "aliases": {"@finops": "OpsChainAgent"},
+                "rules": [
+                    {"name": "operations", "agent": "OpsChainAgent", "intent": "operations",
+                     "keywords": ["spend"], "confidence": 0.7},
+                    {"name": "incidents", "agent": "OpsChainAgent", "intent": "operations",
+                     "keywords": ["Outage"], "confidence": 0.9, "priority": 10}
+                ]
+            }"#,
+            ),
+        )
+        .unwrap();
+
+        assert_eq!(
+            rules.explicit_specialist("ask @finops").as_deref(),
+            Some("OpsChainAgent")
+        );
+        assert_eq!(
+            rules.explicit_specialist("@ctoagent please").as_deref(),
+            Some("CTOAgent")
+        );
+        // Replaced built-in no longer matches its old keywords.
+        assert!(rules.evaluate("gpu spend review").is_some());
+        assert!(rules.evaluate("cluster capacity").is_none());
+        // The higher-scoring file rule beats the built-in architecture match.
+        let decision = rules.evaluate("roadmap after the outage").unwrap();
+        assert_eq!(decision.confidence, 0.9);
+    }
+
+    #[test]
+    fn keywords_match_whole_words_and_best_score_wins() {
+        let rules = RoutingRules::builtin();
+
+        assert!(rules.evaluate("please encode this string").is_none());
+        assert!(rules.evaluate("the unrecalled draft").is_none());
+        assert_eq!(
+            rules.evaluate("fix these bugs").unwrap().suggested_agent,
+            "SeniorEngineerAgent"
+        );
+        assert_eq!(
+            rules.evaluate("deploying tonight").unwrap().suggested_agent,
+            "OpsChainAgent"
+        );
+
+        // Memory matches `recall` too, but architecture scores higher.
+        let decision = rules.evaluate("recall the roadmap").unwrap();
+        assert_eq!(decision.suggested_agent, "CTOAgent");
+        let sources: Vec<_> = decision
+            .candidates
+            .iter()
+            .map(|c| c.source.as_str())
+            .collect();
+        assert_eq!(sources, ["rule:architecture", "rule:memory"]);
+
+        // Each extra keyword adds a little confidence.
+        let decision = rules.evaluate("refactor the rust code").unwrap();
+        assert!((decision.confidence - 0.92).abs() < 1e-6);
+    }
+
+    #[test]
+    fn negative_keywords_veto_a_rule() {
+        let rules = RoutingRules::builtin();
+
+        let decision = rules.evaluate("open source architecture options").unwrap();
+        assert_eq!(decision.suggested_agent, "CTOAgent");
+        let vetoed = decision
+            .candidates
+            .iter()
+            .find(|c| c.source == "rule:research")
+            .unwrap();
+        assert_eq!(vetoed.score, 0.0);
+        assert_eq!(vetoed.detail.as_deref(), Some("vetoed by 'open source'"));
+
+        assert!(rules.evaluate("any open source projects?").is_none());
+        assert_eq!(
+            rules.evaluate("cite a source").unwrap().suggested_agent,
+            "ResearcherAgent"
+        );
+    }
+
+    #[test]
+    fn validation_rejects_bad_confidence_and_duplicates() {
+        let bad = config(
+            r#"{"rules": [{"name": "x", "agent": "CTOAgent", "intent": "engineering",
+                "keywords": ["k"], "confidence": 1.5}]}"#,
+        );
+        assert!(bad
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .contains("0.0..=1.0"));
+
+        let dup = config(
+            r#"{"rules": [
+                {"name": "x", "agent": "CTOAgent", "intent": "engineering", "keywords": ["a"], "confidence": 0.5},
+                {"name": "x", "agent": "CTOAgent", "intent": "engineering", "keywords": ["b"], "confidence": 0.5}
+            ]}"#,
+        );
+        assert!(dup.validate().is_err());
+    }
+
+    #[test]
+    fn toml_files_parse_like_json() {
+        let toml = RoutingRulesConfig::parse(
+            Path::new("routing_rules.toml"),
+            r#"
+            [aliases]
+            "@finops" = "OpsChainAgent"
+
+            [[rules]]
+            name = "incidents"
+            agent = "OpsChainAgent"
+            intent = "operations"
+            keywords = ["outage"]
+            negative_keywords = ["outage drill"]
+            confidence = 0.9
+            priority = 10
+            "#,
+        )
+        .unwrap();
+        let rules = RoutingRules::layered(&RoutingRulesConfig::default(), &toml).unwrap();
+        assert_eq!(
+            rules.explicit_specialist("ask @finops").as_deref(),
+            Some("OpsChainAgent")
+        );
+        assert_eq!(rules.evaluate("after the outage").unwrap().confidence, 0.9);
+        assert!(rules.evaluate("outage drill friday").is_none());
+
+        // Anything else is read as JSON.
+        assert!(RoutingRulesConfig::parse(Path::new("rules.json"), "aliases = {}").is_err());
+    }
+
+    #[test]
+    fn reload_picks_up_edits_and_keeps_rules_on_invalid_file() {
+        let path = env::temp_dir().join(format!(
+            "vk_routing_rules_{}_{}.json",
+            std::process::id(),
+            SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        fs::write(&path, r#"{"aliases": {"@one": "CTOAgent"}}"#).unwrap();
+        let handle = RoutingRulesHandle::from_file(&path).unwrap();
+        assert!(handle.snapshot().explicit_specialist("@one").is_some());
+
+        fs::write(&path, r#"{"aliases": {"@two": "CTOAgent"}}"#).unwrap();
+        *handle.modified.lock().unwrap() = None;
+        assert!(handle.reload_if_changed());
+        assert!(handle.snapshot().explicit_specialist("@two").is_some());
+
+        fs::write(&path, "{ not json").unwrap();
+        *handle.modified.lock().unwrap() = None;
+        assert!(!handle.reload_if_changed());
+        assert!(handle.snapshot().explicit_specialist("@two").is_some());
+
+        // Agent definition rules survive reloads of the file.
+        fs::write(&path, r#"{"aliases": {"@two": "CTOAgent"}}"#).unwrap();
+        let handle = handle
+            .with_agent_rules(config(r#"{"aliases": {"@docsagent": "DocsAgent"}}"#))
+            .unwrap();
+        fs::write(&path, r#"{"aliases": {"@three": "CTOAgent"}}"#).unwrap();
+        *handle.modified.lock().unwrap() = None;
+        assert!(handle.reload_if_changed());
+        let rules = handle.snapshot();
+        assert!(rules.explicit_specialist("@three").is_some());
+        assert_eq!(
+            rules.explicit_specialist("@docsagent").as_deref(),
+            Some("DocsAgent")
+        );
+
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -1,24 +1,76 @@
-use std::collections::HashSet;
 use std::env;
+use std::sync::Arc;
 
-use crate::orchestrator::router::{RouterIntent, RoutingDecision};
+use anyhow::Context;
+use tokio::sync::OnceCell;
+use tracing::{info, warn};
 
-/// Lightweight semantic router scaffold using token overlap scoring.
-/// This is a placeholder until we wire real embeddings; kept flag-gated.
+use crate::cassette::wrap_embeddings_from_env;
+use crate::orchestrator::router::{RouteCandidate, RouterIntent, RoutingDecision};
+use crate::rag::config::RagConfig;
+use crate::rag::embed::{EmbeddingsProvider, OpenAiEmbeddingsClient};
+
+const DEFAULT_THRESHOLD: f32 = 0.35;
+const DEFAULT_TOP_K: usize = 3;
+
+/// Embedding-based router: scores a request by cosine similarity against per-agent example
+/// utterances. Prototype vectors are embedded once, on first use or `warm_up`, and cached.
 pub struct SemanticRouter {
+    embedder: Arc<dyn EmbeddingsProvider>,
     prototypes: Vec<SemanticPrototype>,
+    vectors: OnceCell<Vec<Vec<Vec<f32>>>>,
     threshold: f32,
-    enabled: bool,
+    top_k: usize,
 }
 
 #[derive(Clone, Debug)]
 pub struct SemanticPrototype {
     pub agent_name: String,
     pub intent: RouterIntent,
-    pub text: String,
+    /// Example requests this agent should receive; the best-matching one sets its score.
+    pub examples: Vec<String>,
 }
 
 impl SemanticRouter {
+    pub fn new(embedder: Arc<dyn EmbeddingsProvider>, prototypes: Vec<SemanticPrototype>) -> Self {
+        Self {
+            embedder,
+            prototypes,
+            vectors: OnceCell::new(),
+            threshold: DEFAULT_THRESHOLD,
+            top_k: DEFAULT_TOP_K,
+        }
+    }
+
+    pub fn with_threshold(mut self, threshold: f32) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    pub fn with_top_k(mut self, top_k: usize) -> Self {
+        self.top_k = top_k.max(1);
+        self
+    }
+
+    /// Add prototypes, replacing any existing ones for the same agent. Call before `warm_up`.
+    pub fn with_prototypes(
+        mut self,
+        prototypes: impl IntoIterator<Item = SemanticPrototype>,
+    ) -> Self {
+        for proto in prototypes {
+            match self
+                .prototypes
+                .iter_mut()
+                .find(|existing| existing.agent_name == proto.agent_name)
+            {
+                Some(existing) => *existing = proto,
+                None => self.prototypes.push(proto),
+            }
+        }
+        self
+    }
+
+    /// Enabled by `ROUTING_SEMANTIC_ENABLED`; embeds through the `RAG_EMBEDDING_*` backend.
     pub fn from_env() -> anyhow::Result<Option<Self>> {
         let enabled = env::var("ROUTING_SEMANTIC_ENABLED")
             .ok()
@@ -32,108 +84,259 @@ impl SemanticRouter {
         let threshold = env::var("ROUTING_SEMANTIC_THRESHOLD")
             .ok()
             .and_then(|v| v.parse::<f32>().ok())
-            .unwrap_or(0.35);
+            .unwrap_or(DEFAULT_THRESHOLD);
+        let top_k = env::var("ROUTING_SEMANTIC_TOPK")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(DEFAULT_TOP_K);
 
-        let prototypes = default_prototypes();
-        if prototypes.is_empty() {
-            return Ok(None);
-        }
+        let embed_config =
+            RagConfig::from_env().context("Semantic routing needs RAG_EMBEDDING_* settings")?;
+        let embedder = wrap_embeddings_from_env(
+            Arc::new(OpenAiEmbeddingsClient::from_config(&embed_config)?),
+            &embed_config.embedding_model,
+        )?;
 
-        Ok(Some(Self {
-            prototypes,
-            threshold,
-            enabled,
-        }))
+        Ok(Some(
+            Self::new(embedder, default_prototypes())
+                .with_threshold(threshold)
+                .with_top_k(top_k),
+        ))
     }
 
-    pub fn classify(&self, input: &str) -> Option<RoutingDecision> {
-        if !self.enabled {
-            return None;
-        }
+    /// Embed the prototypes now so the first routed request does not pay for it.
+    pub async fn warm_up(&self) -> anyhow::Result<()> {
+        let vectors = self.prototype_vectors().await?;
+        info!(
+            agents = self.prototypes.len(),
+            examples = vectors.iter().map(Vec::len).sum::<usize>(),
+            "Semantic routing prototypes embedded"
+        );
+        Ok(())
+    }
+
+    async fn prototype_vectors(&self) -> anyhow::Result<&Vec<Vec<Vec<f32>>>> {
+        self.vectors
+            .get_or_try_init(|| async {
+                let mut vectors = Vec::with_capacity(self.prototypes.len());
+                for proto in &self.prototypes {
+                    let mut examples = Vec::with_capacity(proto.examples.len());
+                    for example in &proto.examples {
+                        let vector = self.embedder.embed(example).await.with_context(|| {
+                            format!("Failed to embed routing prototype for {}", proto.agent_name)
+                        })?;
+                        examples.push(vector);
+                    }
+                    vectors.push(examples);
+                }
+                Ok(vectors)
+            })
+            .await
+    }
+
+    /// Top-k agents by best cosine similarity, highest first.
+    pub async fn candidates(&self, input: &str) -> anyhow::Result<Vec<RouteCandidate>> {
+        let prototypes = self.prototype_vectors().await?;
+        let query = self.embedder.embed(input).await?;
+
+        let mut scored: Vec<RouteCandidate> = self
+            .prototypes
+            .iter()
+            .zip(prototypes)
+            .map(|(proto, examples)| RouteCandidate {
+                agent: proto.agent_name.clone(),
+                source: String::from("semantic"),
+                detail: None,
+                score: examples
+                    .iter()
+                    .map(|example| cosine_similarity(&query, example))
+                    .fold(0.0, f32::max),
+            })
+            .collect();
+
+        scored.sort_by(|a, b| b.score.total_cmp(&a.score));
+        scored.truncate(self.top_k);
+        Ok(scored)
+    }
 
-        let input_tokens = tokenize(input);
-        if input_tokens.is_empty() {
+    /// Route to the best candidate when it clears the threshold. Embedding failures are logged
+    /// and treated as no match so routing falls through to the front desk.
+    pub async fn classify(&self, input: &str) -> Option<RoutingDecision> {
+        if input.trim().is_empty() {
             return None;
         }
 
-        let mut best: Option<(f32, &SemanticPrototype)> = None;
-
-        for proto in &self.prototypes {
-            let score = overlap_score(&input_tokens, &tokenize(&proto.text));
-            match best {
-                Some((best_score, _)) if score <= best_score => {}
-                _ => best = Some((score, proto)),
+        let candidates = match self.candidates(input).await {
+            Ok(candidates) => candidates,
+            Err(err) => {
+                warn!(?err, "Semantic routing unavailable; skipping");
+                return None;
             }
-        }
+        };
 
-        let (score, proto) = best?;
-        if score < self.threshold {
+        let best = candidates.first()?;
+        if best.score < self.threshold {
             return None;
         }
 
-        Some(RoutingDecision::new(
-            proto.intent,
-            score,
+        let intent = self
+            .prototypes
+            .iter()
+            .find(|proto| proto.agent_name == best.agent)
+            .map(|proto| proto.intent)?;
+
+        let decision = RoutingDecision::new(
+            intent,
+            best.score,
             format!(
                 "Semantic match for {} (score={:.2})",
-                proto.agent_name, score
+                best.agent, best.score
             ),
-            &proto.agent_name,
-        ))
+            &best.agent,
+        );
+        Some(decision.with_candidates(candidates))
     }
 }
 
-fn tokenize(text: &str) -> Vec<String> {
-    text.to_lowercase()
-        .split(|c: char| !c.is_alphanumeric())
-        .filter(|t| !t.is_empty())
-        .map(|t| t.to_string())
-        .collect()
-}
-
-fn overlap_score(input: &[String], proto: &[String]) -> f32 {
-    if input.is_empty() || proto.is_empty() {
+pub(crate) fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
         return 0.0;
     }
 
-    let input_set: HashSet<&String> = input.iter().collect();
-    let proto_set: HashSet<&String> = proto.iter().collect();
-    let intersection = input_set.intersection(&proto_set).count() as f32;
-    let denom = ((input_set.len() * proto_set.len()) as f32).sqrt();
-    if denom == 0.0 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
         0.0
     } else {
-        intersection / denom
+        dot / (norm_a * norm_b)
+    }
+}
+
+fn prototype(agent_name: &str, intent: RouterIntent, examples: &[&str]) -> SemanticPrototype {
+    SemanticPrototype {
+        agent_name: agent_name.to_string(),
+        intent,
+        examples: examples.iter().map(|e| e.to_string()).collect(),
     }
 }
 
 fn default_prototypes() -> Vec<SemanticPrototype> {
     vec![
-        SemanticPrototype {
-            agent_name: "CTOAgent".to_string(),
-            intent: RouterIntent::Engineering,
-            text: "architecture system design roadmap blueprint diagram mvp".to_string(),
-        },
-        SemanticPrototype {
-            agent_name: "SeniorEngineerAgent".to_string(),
-            intent: RouterIntent::Engineering,
-            text: "rust code implement bug compile refactor function struct tests".to_string(),
-        },
-        SemanticPrototype {
-            agent_name: "ResearcherAgent".to_string(),
-            intent: RouterIntent::Research,
-            text: "research summarize compare investigate source paper article report".to_string(),
-        },
-        SemanticPrototype {
-            agent_name: "OpsChainAgent".to_string(),
-            intent: RouterIntent::Operations,
-            text: "deploy infrastructure infra cost gpu cluster budget scaling reliability"
-                .to_string(),
-        },
-        SemanticPrototype {
-            agent_name: "RagAgent".to_string(),
-            intent: RouterIntent::Memory,
-            text: "rag memory store recall context vector embeddings helix".to_string(),
-        },
+        prototype(
+            "CTOAgent",
+            RouterIntent::Engineering,
+            &[
+                "Sketch the system architecture and service boundaries for this feature",
+                "What should the roadmap for the next quarter look like?",
+                "Compare two designs and recommend one with trade-offs",
+                "Draw a blueprint of how the components talk to each other",
+            ],
+        ),
+        prototype(
+            "SeniorEngineerAgent",
+            RouterIntent::Engineering,
+            &[
+                "Implement this function in Rust and add tests",
+                "Why does this code fail to compile?",
+                "Refactor this module to remove the duplicated logic",
+                "Track down the bug in the request handler",
+            ],
+        ),
+        prototype(
+            "ResearcherAgent",
+            RouterIntent::Research,
+            &[
+                "Summarize what recent papers say about retrieval-augmented generation",
+                "Investigate how other projects solve this and cite sources",
+                "Write a short report comparing the available options",
+                "What is known about this topic and what is still open?",
+            ],
+        ),
+        prototype(
+            "OpsChainAgent",
+            RouterIntent::Operations,
+            &[
+                "How much will it cost to run this on a GPU cluster each month?",
+                "Plan the deployment and scaling for production",
+                "Which infrastructure setup gives us the best reliability for the budget?",
+                "Estimate capacity for ten times the current traffic",
+            ],
+        ),
+        prototype(
+            "RagAgent",
+            RouterIntent::Memory,
+            &[
+                "What do we have stored in memory about this?",
+                "Recall the notes we saved on the vector store",
+                "Search the knowledge base for context on Helix",
+                "Which embeddings are indexed for this repository?",
+            ],
+        ),
     ]
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use async_trait::async_trait;
+
+    use super::*;
+
+    /// Fixed axes per keyword, so scores are deterministic; counts calls to check caching.
+    #[derive(Default)]
+    struct AxisEmbedder {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl EmbeddingsProvider for AxisEmbedder {
+        async fn embed(&self, text: &str) -> anyhow::Result<Vec<f32>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let text = text.to_lowercase();
+            Ok(["deploy", "rust", "paper"]
+                .iter()
+                .map(|axis| if text.contains(axis) { 1.0 } else { 0.0 })
+                .collect())
+        }
+    }
+
+    fn router(embedder: Arc<AxisEmbedder>) -> SemanticRouter {
+        SemanticRouter::new(
+            embedder,
+            vec![
+                prototype(
+                    "OpsChainAgent",
+                    RouterIntent::Operations,
+                    &["deploy it", "deploy rust"],
+                ),
+                prototype("SeniorEngineerAgent", RouterIntent::Engineering, &["rust"]),
+                prototype("ResearcherAgent", RouterIntent::Research, &["paper"]),
+            ],
+        )
+        .with_threshold(0.5)
+        .with_top_k(2)
+    }
+
+    #[tokio::test]
+    async fn picks_best_example_and_embeds_prototypes_once() {
+        let embedder = Arc::new(AxisEmbedder::default());
+        let router = router(embedder.clone());
+
+        let decision = router.classify("please deploy").await.unwrap();
+        assert_eq!(decision.suggested_agent, "OpsChainAgent");
+        assert!((decision.confidence - 1.0).abs() < 1e-6);
+        assert_eq!(decision.candidates.len(), 2);
+
+        router.classify("rust again").await.unwrap();
+        // 4 prototype examples once, plus one embed per request.
+        assert_eq!(embedder.calls.load(Ordering::SeqCst), 6);
+    }
+
+    #[tokio::test]
+    async fn below_threshold_returns_none() {
+        let router = router(Arc::new(AxisEmbedder::default()));
+        assert!(router.classify("hello there").await.is_none());
+    }
+}
@@ -0,0 +1,235 @@
+use std::collections::VecDeque;
+use std::env;
+
+use chrono::Utc;
+use tracing::warn;
+
+use crate::agents::{AgentRequest, TurnContext};
+use crate::llm_client::{ChatMessage, SharedLlmClient};
+use crate::usage::UsageScope;
+
+const DEFAULT_MAX_TURNS: usize = 8;
+const DEFAULT_SUMMARY_MAX_CHARS: usize = 1_500;
+
+const SUMMARY_DIRECTIVE: &str = "You maintain a running summary of a conversation between a user and the Vidkosha Cortex agents. Merge the previous summary with the new turns. Keep decisions, facts, open questions, and names; drop pleasantries. Reply with the summary only, as short plain prose or bullets.";
+
+/// Limits for the rolling turn buffer kept by a REPL session.
+#[derive(Debug, Clone)]
+pub struct SessionConfig {
+    /// Verbatim turns kept before older ones are folded into the summary.
+    pub max_turns: usize,
+    /// Upper bound on the running summary length.
+    pub summary_max_chars: usize,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            max_turns: DEFAULT_MAX_TURNS,
+            summary_max_chars: DEFAULT_SUMMARY_MAX_CHARS,
+        }
+    }
+}
+
+impl SessionConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            max_turns: env::var("SESSION_MAX_TURNS")
+                .ok()
+                .and_then(|v| v.parse::<usize>().ok())
+                .filter(|v| *v > 0)
+                .unwrap_or(defaults.max_turns),
+            summary_max_chars: env::var("SESSION_SUMMARY_MAX_CHARS")
+                .ok()
+                .and_then(|v| v.parse::<usize>().ok())
+                .filter(|v| *v > 0)
+                .unwrap_or(defaults.summary_max_chars),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct SessionTurn {
+    user: String,
+    assistant: String,
+}
+
+/// One conversation thread: assigns message ids and replays recent turns to agents.
+pub struct ConversationSession {
+    conversation_id: String,
+    config: SessionConfig,
+    summarizer: Option<SharedLlmClient>,
+    turns: VecDeque<SessionTurn>,
+    summary: Option<String>,
+    next_message: u64,
+    last_reply_id: Option<String>,
+}
+
+impl ConversationSession {
+    pub fn new(config: SessionConfig, summarizer: Option<SharedLlmClient>) -> Self {
+        Self {
+            conversation_id: format!("conv-{}", Utc::now().timestamp_millis()),
+            config,
+            summarizer,
+            turns: VecDeque::new(),
+            summary: None,
+            next_message: 1,
+            last_reply_id: None,
+        }
+    }
+
+    pub fn conversation_id(&self) -> &str {
+        &self.conversation_id
+    }
+
+    /// Build the next request in this thread with history and message ids attached.
+    pub fn prepare(&mut self, input: impl Into<String>) -> AgentRequest {
+        let message_id = self.next_message_id();
+        let reply_message_id = self.next_message_id();
+        let turn = TurnContext {
+            conversation_id: self.conversation_id.clone(),
+            message_id,
+            reply_message_id,
+            reply_to: self.last_reply_id.clone(),
+        };
+
+        AgentRequest::new(input)
+            .with_history(self.history())
+            .with_turn(turn)
+    }
+
+    /// Prior context as chat messages: the running summary first, then verbatim turns.
+    pub fn history(&self) -> Vec<ChatMessage> {
+        let mut messages = Vec::with_capacity(self.turns.len() * 2 + 1);
+        if let Some(summary) = self.summary.as_ref() {
+            messages.push(ChatMessage::system(format!(
+                "Summary of the earlier conversation:\n{summary}"
+            )));
+        }
+        for turn in &self.turns {
+            messages.push(ChatMessage::user(turn.user.clone()));
+            messages.push(ChatMessage::assistant(turn.assistant.clone()));
+        }
+        messages
+    }
+
+    /// Append a completed turn, folding the oldest turns into the summary when the buffer is full.
+    pub async fn record(&mut self, request: &AgentRequest, output: &str) {
+        if let Some(turn) = request.turn.as_ref() {
+            self.last_reply_id = Some(turn.reply_message_id.clone());
+        }
+        self.turns.push_back(SessionTurn {
+            user: request.input.trim().to_string(),
+            assistant: output.trim().to_string(),
+        });
+
+        if self.turns.len() > self.config.max_turns {
+            self.compact().await;
+        }
+    }
+
+    async fn compact(&mut self) {
+        let keep = (self.config.max_turns / 2).max(1);
+        let fold = self.turns.len().saturating_sub(keep);
+        if fold == 0 {
+            return;
+        }
+        let folded: Vec<SessionTurn> = self.turns.drain(..fold).collect();
+
+        let summary = match self.summarizer.as_ref() {
+            Some(llm) => {
+                let messages = vec![
+                    ChatMessage::system(SUMMARY_DIRECTIVE),
+                    ChatMessage::user(self.summary_prompt(&folded)),
+                ];
+                let scope = UsageScope::new(self.conversation_id.clone(), "SessionSummarizer");
+                match scope.run(llm.chat(&messages)).await {
+                    Ok(text) if !text.trim().is_empty() => text.trim().to_string(),
+                    Ok(_) => self.fallback_summary(&folded),
+                    Err(err) => {
+                        warn!(?err, conversation_id = %self.conversation_id, "Session summarization failed; using transcript excerpt");
+                        self.fallback_summary(&folded)
+                    }
+                }
+            }
+            None => self.fallback_summary(&folded),
+        };
+
+        self.summary = Some(truncate_chars(&summary, self.config.summary_max_chars));
+    }
+
+    fn summary_prompt(&self, folded: &[SessionTurn]) -> String {
+        let previous = self.summary.as_deref().unwrap_or("(none)");
+        format!(
+            "Previous summary:\n{previous}\n\nNew turns:\n{}\n\nKeep the summary under {} characters.",
+            render_turns(folded),
+            self.config.summary_max_chars
+        )
+    }
+
+    fn fallback_summary(&self, folded: &[SessionTurn]) -> String {
+        match self.summary.as_deref() {
+            Some(previous) => format!("{previous}\n{}", render_turns(folded)),
+            None => render_turns(folded),
+        }
+    }
+
+    fn next_message_id(&mut self) -> String {
+        let id = format!("{}-msg-{}", self.conversation_id, self.next_message);
+        self.next_message += 1;
+        id
+    }
+}
+
+fn render_turns(turns: &[SessionTurn]) -> String {
+    turns
+        .iter()
+        .map(|turn| format!("User: {}\nAgent: {}", turn.user, turn.assistant))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Keep the most recent `max_chars` characters; older summary content is the least relevant.
+fn truncate_chars(text: &str, max_chars: usize) -> String {
+    let count = text.chars().count();
+    if count <= max_chars {
+        return text.to_string();
+    }
+    text.chars().skip(count - max_chars).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn threads_replies_and_folds_old_turns_into_summary() {
+        let config = SessionConfig {
+            max_turns: 2,
+            summary_max_chars: 500,
+        };
+        let mut session = ConversationSession::new(config, None);
+
+        let first = session.prepare("hello");
+        let first_turn = first.turn.clone().expect("turn context");
+        assert!(first_turn.reply_to.is_none());
+        session.record(&first, "hi there").await;
+
+        let second = session.prepare("what did I say?");
+        let second_turn = second.turn.clone().expect("turn context");
+        assert_eq!(
+            second_turn.reply_to.as_deref(),
+            Some(first_turn.reply_message_id.as_str())
+        );
+        assert_eq!(second.history.len(), 2);
+        session.record(&second, "you said hello").await;
+
+        let third = session.prepare("and then?");
+        session.record(&third, "then you asked").await;
+
+        let history = session.history();
+        assert!(history[0].content.contains("User: hello"));
+        assert_eq!(history.len(), 3);
+    }
+}
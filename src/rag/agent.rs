@@ -9,8 +9,10 @@ use super::embed::OpenAiEmbeddingsClient;
 use super::helix::{HelixClient, HelixQueryRagClient};
 use super::mock::MockRagClient;
 use super::types::{
-    MemoryDeleteRequest, MemoryQuery, MemoryRequest, MemoryResponse, MemoryWriteRequest,
+    MemoryDeleteRequest, MemoryGetRequest, MemoryPatchRequest, MemoryQuery, MemoryRequest,
+    MemoryResponse, MemoryUpdateRequest, MemoryWriteRequest,
 };
+use crate::cassette::wrap_embeddings_from_env;
 
 pub type SharedRagAgent = Arc<RagAgent>;
 
@@ -30,6 +32,9 @@ impl RagAgent {
             MemoryRequest::Write(payload) => self.handle_write(payload).await,
             MemoryRequest::Retrieve(query) => self.handle_retrieve(query).await,
             MemoryRequest::Delete(payload) => self.handle_delete(payload).await,
+            MemoryRequest::Get(payload) => self.handle_get(payload).await,
+            MemoryRequest::Update(payload) => self.handle_update(payload).await,
+            MemoryRequest::Patch(payload) => self.handle_patch(payload).await,
         }
     }
 
@@ -75,6 +80,69 @@ impl RagAgent {
             memory_ids: vec![request.id],
         })
     }
+
+    async fn handle_get(&self, request: MemoryGetRequest) -> anyhow::Result<MemoryResponse> {
+        let record = self
+            .client
+            .get(&request.id)
+            .await
+            .with_context(|| format!("RAG lookup failed for id {}", request.id))?;
+
+        Ok(MemoryResponse {
+            notes: match record {
+                Some(_) => format!("memory_id={} found", request.id),
+                None => format!("memory_id={} not found", request.id),
+            },
+            records: record.into_iter().collect(),
+            memory_ids: Vec::new(),
+        })
+    }
+
+    /// Replace a stored memory wholesale; `handle_patch` reads the record back and lands here.
+    async fn handle_update(&self, request: MemoryUpdateRequest) -> anyhow::Result<MemoryResponse> {
+        let record = request.record;
+        let Some(id) = record.id.clone() else {
+            anyhow::bail!("Memory updates need the id of the memory being replaced");
+        };
+        anyhow::ensure!(
+            (0.0..=1.0).contains(&record.confidence),
+            "Memory confidence must be between 0 and 1"
+        );
+
+        let update_ack = self
+            .client
+            .update(MemoryUpdateRequest {
+                record: record.clone(),
+            })
+            .await
+            .with_context(|| format!("RAG update failed for id {id}"))?;
+
+        Ok(MemoryResponse {
+            notes: format!("memory_id={} updated", update_ack.memory_id),
+            records: vec![record],
+            memory_ids: vec![update_ack.memory_id],
+        })
+    }
+
+    /// Read-modify-write: fetch the stored record, apply the edits, then update it in place.
+    async fn handle_patch(&self, request: MemoryPatchRequest) -> anyhow::Result<MemoryResponse> {
+        anyhow::ensure!(
+            !request.patch.is_empty(),
+            "Memory patch for id {} changes nothing",
+            request.id
+        );
+
+        let current = self
+            .client
+            .get(&request.id)
+            .await
+            .with_context(|| format!("RAG lookup failed for id {}", request.id))?
+            .ok_or_else(|| anyhow::anyhow!("memory_id {} not found", request.id))?;
+        let mut record = request.patch.apply(current);
+        record.id = Some(request.id);
+
+        self.handle_update(MemoryUpdateRequest { record }).await
+    }
 }
 
 /// Attempt to build a RAG agent based on env configuration. Optionally falls back to a mock.
@@ -104,7 +172,10 @@ pub async fn build_rag_agent_from_env(
         Err(_) => return Ok(None),
     };
 
-    let embedder = Arc::new(OpenAiEmbeddingsClient::from_config(&embed_config)?);
+    let embedder = wrap_embeddings_from_env(
+        Arc::new(OpenAiEmbeddingsClient::from_config(&embed_config)?),
+        &embed_config.embedding_model,
+    )?;
     let vector_dim = embed_config.vector_dim;
     let embedding_model = embed_config.embedding_model.clone();
 
@@ -126,3 +197,74 @@ pub async fn build_rag_agent_from_env(
         Err(err) => Err(err),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::rag::types::MemoryUpdateRequest;
+    use crate::rag::{MemoryGetRequest, MemoryRecord, MemoryWriteRequest};
+
+    fn record(body: &str) -> MemoryRecord {
+        MemoryRecord {
+            id: None,
+            agent_name: String::from("Agent"),
+            topic: String::from("notes"),
+            project: None,
+            conversation_id: None,
+            timestamp: Utc::now(),
+            summary: body.to_string(),
+            full_content: body.to_string(),
+            confidence: 0.8,
+            open_questions: Vec::new(),
+            perspectives: Vec::new(),
+            messages: Vec::new(),
+            artifacts: Vec::new(),
+            tool_calls: Vec::new(),
+            metadata: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn update_replaces_a_stored_record_in_place() {
+        let rag = RagAgent::new(Arc::new(MockRagClient::default()));
+        let written = rag
+            .handle(MemoryRequest::Write(MemoryWriteRequest {
+                record: record("Billing runs nightly."),
+            }))
+            .await
+            .unwrap();
+        let id = written.memory_ids[0].clone();
+
+        let replacement = MemoryRecord {
+            id: Some(id.clone()),
+            topic: String::from("billing"),
+            ..record("Billing runs hourly.")
+        };
+        let updated = rag
+            .handle(MemoryRequest::Update(MemoryUpdateRequest {
+                record: replacement,
+            }))
+            .await
+            .unwrap();
+        assert_eq!(updated.memory_ids, vec![id.clone()]);
+
+        let stored = rag
+            .handle(MemoryRequest::Get(MemoryGetRequest { id: id.clone() }))
+            .await
+            .unwrap()
+            .records;
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].full_content, "Billing runs hourly.");
+        assert_eq!(stored[0].topic, "billing");
+
+        // Without an id there is nothing to replace.
+        let missing = rag
+            .handle(MemoryRequest::Update(MemoryUpdateRequest {
+                record: record("orphan"),
+            }))
+            .await;
+        assert!(missing.is_err());
+    }
+}
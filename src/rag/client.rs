@@ -3,7 +3,8 @@ use std::sync::Arc;
 use async_trait::async_trait;
 
 use super::types::{
-    MemoryDeleteRequest, MemoryQuery, MemoryRecord, MemoryWriteRequest, MemoryWriteResponse,
+    MemoryDeleteRequest, MemoryQuery, MemoryRecord, MemoryUpdateRequest, MemoryWriteRequest,
+    MemoryWriteResponse,
 };
 
 #[async_trait]
@@ -11,6 +12,17 @@ pub trait RagClient: Send + Sync {
     async fn write(&self, request: MemoryWriteRequest) -> anyhow::Result<MemoryWriteResponse>;
     async fn query(&self, query: MemoryQuery) -> anyhow::Result<Vec<MemoryRecord>>;
     async fn delete(&self, request: MemoryDeleteRequest) -> anyhow::Result<()>;
+
+    /// Look a memory up by the id its write returned.
+    async fn get(&self, id: &str) -> anyhow::Result<Option<MemoryRecord>> {
+        anyhow::bail!("memory lookups by id are not supported by this backend (id {id})")
+    }
+
+    /// Replace a memory in place, keeping its id.
+    async fn update(&self, request: MemoryUpdateRequest) -> anyhow::Result<MemoryWriteResponse> {
+        let _ = request;
+        anyhow::bail!("in-place memory updates are not supported by this backend")
+    }
 }
 
 pub type SharedRagClient = Arc<dyn RagClient>;
@@ -9,6 +9,8 @@ use async_trait::async_trait;
 use blake3;
 
 use super::config::RagConfig;
+use crate::retry::{disabled_openai_backoff, RetryPolicy};
+use crate::usage::{self, TokenUsage};
 
 #[async_trait]
 pub trait EmbeddingsProvider: Send + Sync {
@@ -20,6 +22,7 @@ pub struct OpenAiEmbeddingsClient {
     model: String,
     cache: Mutex<HashMap<String, Vec<f32>>>,
     cache_capacity: usize,
+    retry: RetryPolicy,
 }
 
 impl OpenAiEmbeddingsClient {
@@ -30,10 +33,12 @@ impl OpenAiEmbeddingsClient {
         }
 
         Ok(Self {
-            client: OpenAiClient::with_config(openai_config),
+            client: OpenAiClient::with_config(openai_config)
+                .with_backoff(disabled_openai_backoff()),
             model: config.embedding_model.clone(),
             cache: Mutex::new(HashMap::new()),
             cache_capacity: 512,
+            retry: RetryPolicy::from_env("RAG_EMBEDDING", RetryPolicy::embedding_defaults()),
         })
     }
 }
@@ -58,7 +63,20 @@ impl EmbeddingsProvider for OpenAiEmbeddingsClient {
             .input(text)
             .build()?;
 
-        let response = self.client.embeddings().create(request).await?;
+        let response = self
+            .retry
+            .run("Embedding request", || {
+                let request = request.clone();
+                async move { Ok(self.client.embeddings().create(request).await?) }
+            })
+            .await?;
+        let usage = TokenUsage {
+            prompt_tokens: response.usage.prompt_tokens as u64,
+            completion_tokens: 0,
+            estimated: false,
+        };
+        usage::record(&self.model, "embed", usage);
+
         let embedding = response
             .data
             .first()
@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 use std::env;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use anyhow::{anyhow, Context};
@@ -17,8 +17,9 @@ use super::client::RagClient;
 use super::config::HelixConfig;
 use super::embed::EmbeddingsProvider;
 use super::types::{
-    ArtifactRef, MemoryDeleteRequest, MemoryFilters, MemoryQuery, MemoryRecord, MemoryWriteRequest,
-    MemoryWriteResponse, MessageRecord, PayoutEvent, PerspectiveView, ToolCallRecord, UsageEvent,
+    ArtifactRef, MemoryDeleteRequest, MemoryFilters, MemoryQuery, MemoryRecord,
+    MemoryUpdateRequest, MemoryWriteRequest, MemoryWriteResponse, MessageRecord, PayoutEvent,
+    PerspectiveView, ToolCallRecord, UsageEvent,
 };
 
 /// Minimal HTTP client for HelixDB's REST surface.
@@ -134,6 +135,24 @@ impl HelixClient {
             .with_context(|| format!("Failed to deserialize Helix query '{query_name}' response"))
     }
 
+    /// Persist one token-usage event as a `UsageEvent` node.
+    pub async fn write_usage_event(&self, event: &UsageEvent) -> anyhow::Result<()> {
+        let payload = json!({
+            "request_id": event.request_id,
+            "agent_name": event.agent_name,
+            "operator_id": event.operator_id.clone().unwrap_or_default(),
+            "operation": event.tool_name,
+            "model": event.model.clone().unwrap_or_default(),
+            "prompt_tokens": event.prompt_tokens,
+            "completion_tokens": event.completion_tokens,
+            "tokens_consumed": event.tokens_consumed,
+            "estimated": event.estimated,
+            "timestamp": event.timestamp.to_rfc3339(),
+        });
+        let _: Value = self.post_query("write_usage_event_v1", &payload).await?;
+        Ok(())
+    }
+
     #[allow(dead_code)]
     pub fn namespace(&self) -> &str {
         &self.config.namespace
@@ -288,6 +307,8 @@ pub struct HelixQueryRagClient {
     _embedding_model: String,
     vector_dim: usize,
     neighbor_depth: Option<usize>,
+    /// conversation_id / message_id -> Helix node id for threads written by this process.
+    thread_nodes: Mutex<HashMap<String, String>>,
 }
 
 impl HelixQueryRagClient {
@@ -304,6 +325,7 @@ impl HelixQueryRagClient {
             _embedding_model: embedding_model,
             vector_dim,
             neighbor_depth: neighbor_depth_from_env(),
+            thread_nodes: Mutex::new(HashMap::new()),
         }
     }
 
@@ -388,6 +410,8 @@ impl HelixGraphClient {
         let records = hits
             .into_iter()
             .filter_map(|hit| match Self::record_from_hit(hit) {
+                // Tags live inside the record JSON, which Helix cannot filter on.
+                Some((record, _)) if !query.filters.matches(&record) => None,
                 Some((record, has_neighbors)) => {
                     if !has_neighbors {
                         any_missing_neighbors = true;
@@ -887,18 +911,8 @@ impl HelixGraphClient {
     }
 
     #[allow(dead_code)]
-    /// Placeholder for future Helix-backed usage logging; currently just records via tracing.
     pub async fn log_usage_event(&self, event: &UsageEvent) -> anyhow::Result<()> {
-        info!(
-            request_id = %event.request_id,
-            agent = event.agent_name,
-            operator = ?event.operator_id,
-            specialist = ?event.specialist_agent_id,
-            tokens = event.tokens_consumed,
-            tool = event.tool_name,
-            "Usage event (Helix logging stub)"
-        );
-        Ok(())
+        self.helix.write_usage_event(event).await
     }
 
     #[allow(dead_code)]
@@ -972,6 +986,16 @@ struct WriteMemoryV2Response {
     memory_chunk: InsertMemoryChunkNode,
 }
 
+#[derive(Deserialize)]
+struct WriteConversationV2Response {
+    conversation: HelixWriteNode,
+}
+
+#[derive(Deserialize)]
+struct WriteMessageV2Response {
+    message: HelixWriteNode,
+}
+
 #[derive(Deserialize)]
 struct HelixWriteNode {
     id: String,
@@ -984,6 +1008,164 @@ struct InsertMemoryChunkNode {
     chunk_id: Option<String>,
 }
 
+#[derive(Deserialize)]
+struct DeleteMemoryV2Response {
+    #[serde(default)]
+    matched: u64,
+}
+
+#[derive(Deserialize)]
+struct GetMemoryV2Response {
+    #[serde(default)]
+    memory_entry: Vec<MemoryEntryNode>,
+    #[serde(default)]
+    perspectives: Vec<PerspectiveNode>,
+    #[serde(default)]
+    tool_calls: Vec<ToolCallNode>,
+}
+
+impl GetMemoryV2Response {
+    fn into_record(self, chunk_id: &str) -> Option<MemoryRecord> {
+        let mut record = self.memory_entry.into_iter().next()?.into_record(chunk_id);
+        record.perspectives = self
+            .perspectives
+            .into_iter()
+            .map(PerspectiveNode::into_view)
+            .collect();
+        record.tool_calls = self
+            .tool_calls
+            .into_iter()
+            .map(ToolCallNode::into_record)
+            .collect();
+        Some(record)
+    }
+}
+
+#[derive(Deserialize)]
+struct MemoryContextV2Response {
+    #[serde(default)]
+    perspectives: Vec<PerspectiveNode>,
+    #[serde(default)]
+    tool_calls: Vec<ToolCallNode>,
+}
+
+#[derive(Deserialize)]
+struct PerspectiveNode {
+    role: String,
+    #[serde(default)]
+    summary: String,
+    #[serde(default)]
+    body: String,
+    #[serde(default)]
+    risks: Option<String>,
+    #[serde(default)]
+    decisions: Option<String>,
+    #[serde(default)]
+    actions: Option<String>,
+}
+
+impl PerspectiveNode {
+    /// write_perspective_v2 stores absent risks/decisions/actions as empty strings.
+    fn into_view(self) -> PerspectiveView {
+        let non_empty = |value: Option<String>| value.filter(|v| !v.is_empty());
+        PerspectiveView {
+            role: self.role,
+            summary: self.summary,
+            body: self.body,
+            risks: non_empty(self.risks),
+            decisions: non_empty(self.decisions),
+            actions: non_empty(self.actions),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct MemoryEntryNode {
+    agent_name: String,
+    topic: String,
+    #[serde(default)]
+    project: Option<String>,
+    summary: String,
+    full_content: String,
+    timestamp: String,
+    #[serde(default)]
+    confidence: Option<f32>,
+    #[serde(default)]
+    open_questions: Vec<String>,
+    #[serde(default)]
+    metadata: Option<String>,
+    #[serde(default)]
+    conversation_id: Option<String>,
+}
+
+impl MemoryEntryNode {
+    /// write_memory_v2 stores absent project/conversation as empty strings.
+    fn into_record(self, chunk_id: &str) -> MemoryRecord {
+        let non_empty = |value: Option<String>| value.filter(|v| !v.is_empty());
+        MemoryRecord {
+            id: Some(chunk_id.to_string()),
+            agent_name: self.agent_name,
+            topic: self.topic,
+            project: non_empty(self.project),
+            conversation_id: non_empty(self.conversation_id),
+            timestamp: DateTime::parse_from_rfc3339(&self.timestamp)
+                .map(|dt| dt.with_timezone(&Utc))
+                .unwrap_or_else(|_| Utc::now()),
+            summary: self.summary,
+            full_content: self.full_content,
+            confidence: self.confidence.unwrap_or(0.5),
+            open_questions: self.open_questions,
+            perspectives: Vec::new(),
+            messages: Vec::new(),
+            artifacts: Vec::new(),
+            tool_calls: Vec::new(),
+            metadata: self
+                .metadata
+                .as_deref()
+                .and_then(|m| serde_json::from_str(m).ok()),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ToolCallNode {
+    #[serde(default)]
+    tool_call_id: String,
+    tool_name: String,
+    #[serde(default)]
+    args_json: String,
+    #[serde(default)]
+    result_summary: String,
+    #[serde(default)]
+    created_at: Option<String>,
+    #[serde(default)]
+    metadata: Option<String>,
+}
+
+impl ToolCallNode {
+    /// write_tool_call_v2 stores the arguments and metadata as JSON strings.
+    fn into_record(self) -> ToolCallRecord {
+        let non_empty = |value: String| Some(value).filter(|v| !v.is_empty());
+        ToolCallRecord {
+            tool_call_id: non_empty(self.tool_call_id),
+            tool_name: self.tool_name,
+            args_json: serde_json::from_str(&self.args_json)
+                .unwrap_or(Value::String(self.args_json)),
+            result_summary: non_empty(self.result_summary),
+            created_at: self
+                .created_at
+                .as_deref()
+                .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok())
+                .map(|dt| dt.with_timezone(&Utc)),
+            metadata: self
+                .metadata
+                .as_deref()
+                .and_then(|m| serde_json::from_str(m).ok())
+                .filter(|m: &Value| !m.is_null()),
+        }
+    }
+}
+
 #[derive(Deserialize)]
 struct SearchMemoryChunkResponse {
     matches: Vec<MemoryChunkHit>,
@@ -1017,57 +1199,11 @@ struct MemoryChunkHit {
 impl RagClient for HelixQueryRagClient {
     async fn write(&self, request: MemoryWriteRequest) -> anyhow::Result<MemoryWriteResponse> {
         let record = request.record;
-        // Embed combined summary + full_content to capture more semantics.
-        let embed_text = format!("{}\n\n{}", record.summary, record.full_content);
-        let vector = self
-            .embedder
-            .embed(&embed_text)
-            .await
-            .context("Helix embedding failed")?;
-
-        if vector.len() != self.vector_dim {
-            warn!(
-                expected = self.vector_dim,
-                actual = vector.len(),
-                "Embedding dimension mismatch during HelixQL write"
-            );
-        }
-
-        let timestamp = record.timestamp.to_rfc3339();
-        let metadata_json = record
-            .metadata
-            .as_ref()
-            .map(|m| m.to_string())
-            .unwrap_or_else(|| "{}".to_string());
-
         let chunk_id = record
             .id
             .clone()
             .unwrap_or_else(|| format!("chunk-{}", record.timestamp.timestamp_millis()));
-
-        let artifact_id = record
-            .project
-            .clone()
-            .unwrap_or_else(|| "artifact-auto".to_string());
-
-        let payload_hash = format!("sha256:{}", blake3::hash(embed_text.as_bytes()).to_hex());
-
-        let payload = json!({
-            "vector": Self::to_f64(&vector),
-            "agent_name": record.agent_name,
-            "topic": record.topic,
-            "project": record.project.clone().unwrap_or_default(),
-            "summary": record.summary,
-            "full_content": record.full_content,
-            "timestamp": timestamp,
-            "confidence": record.confidence,
-            "open_questions": record.open_questions,
-            "metadata": metadata_json,
-            "payload_hash": payload_hash,
-            "chunk_id": chunk_id,
-            "artifact_id": artifact_id,
-            "conversation_id": record.conversation_id.unwrap_or_default(),
-        });
+        let payload = self.memory_payload(&record, &chunk_id).await?;
 
         let response: WriteMemoryV2Response = self
             .helix
@@ -1075,7 +1211,24 @@ impl RagClient for HelixQueryRagClient {
             .await
             .context("HelixQL write_memory_v2 failed")?;
 
-        let _memory_entry_id = response.memory_entry.id;
+        let memory_entry_id = response.memory_entry.id;
+        if let Err(err) = self
+            .write_perspectives(&memory_entry_id, &record.perspectives)
+            .await
+        {
+            warn!(?err, %memory_entry_id, "Failed to write perspectives to Helix");
+        }
+        if let Err(err) = self
+            .write_tool_calls(&memory_entry_id, &record.tool_calls)
+            .await
+        {
+            warn!(?err, %memory_entry_id, "Failed to write tool calls to Helix");
+        }
+        if let Some(conversation_id) = record.conversation_id.as_deref() {
+            if let Err(err) = self.write_thread(conversation_id, &record.messages).await {
+                warn!(?err, %conversation_id, "Failed to write conversation thread to Helix");
+            }
+        }
         let memory_id = response
             .memory_chunk
             .chunk_id
@@ -1151,6 +1304,15 @@ impl RagClient for HelixQueryRagClient {
                 metadata,
             };
 
+            // search_memory_v2 is vector-only, so filters are applied to the hits here.
+            if !query.filters.matches(&record) {
+                continue;
+            }
+
+            if let Err(err) = self.attach_context(&mut record).await {
+                warn!(?err, "Failed to read HelixQL memory context");
+            }
+
             if let Some(depth) = self.neighbor_depth {
                 if let Err(err) = self.enrich_from_neighbors(&mut record, depth).await {
                     warn!(?err, "Failed to enrich HelixQL hit with neighbors");
@@ -1164,14 +1326,278 @@ impl RagClient for HelixQueryRagClient {
     }
 
     async fn delete(&self, request: MemoryDeleteRequest) -> anyhow::Result<()> {
-        self.helix
-            .delete_node(&request.id)
+        let response: DeleteMemoryV2Response = self
+            .helix
+            .post_query("delete_memory_v2", &json!({ "chunk_id": request.id }))
             .await
-            .with_context(|| format!("Helix delete failed for id {}", request.id))
+            .with_context(|| format!("HelixQL delete_memory_v2 failed for id {}", request.id))?;
+        anyhow::ensure!(response.matched > 0, "memory_id {} not found", request.id);
+        Ok(())
+    }
+
+    async fn get(&self, id: &str) -> anyhow::Result<Option<MemoryRecord>> {
+        let response: GetMemoryV2Response = self
+            .helix
+            .post_query("get_memory_v2", &json!({ "chunk_id": id }))
+            .await
+            .context("HelixQL get_memory_v2 failed")?;
+
+        Ok(response.into_record(id))
+    }
+
+    async fn update(&self, request: MemoryUpdateRequest) -> anyhow::Result<MemoryWriteResponse> {
+        let record = request.record;
+        let chunk_id = record
+            .id
+            .clone()
+            .context("HelixQL update needs the memory chunk id")?;
+        let payload = self.memory_payload(&record, &chunk_id).await?;
+
+        let _: Value = self
+            .helix
+            .post_query("update_memory_v2", &payload)
+            .await
+            .context("HelixQL update_memory_v2 failed")?;
+
+        Ok(MemoryWriteResponse {
+            memory_id: chunk_id,
+        })
     }
 }
 
 impl HelixQueryRagClient {
+    /// Embed a record and build the parameters shared by write_memory_v2 and update_memory_v2.
+    async fn memory_payload(&self, record: &MemoryRecord, chunk_id: &str) -> anyhow::Result<Value> {
+        // Embed combined summary + full_content to capture more semantics.
+        let embed_text = format!("{}\n\n{}", record.summary, record.full_content);
+        let vector = self
+            .embedder
+            .embed(&embed_text)
+            .await
+            .context("Helix embedding failed")?;
+
+        if vector.len() != self.vector_dim {
+            warn!(
+                expected = self.vector_dim,
+                actual = vector.len(),
+                "Embedding dimension mismatch during HelixQL write"
+            );
+        }
+
+        let metadata_json = record
+            .metadata
+            .as_ref()
+            .map(|m| m.to_string())
+            .unwrap_or_else(|| "{}".to_string());
+
+        let artifact_id = record
+            .project
+            .clone()
+            .unwrap_or_else(|| "artifact-auto".to_string());
+
+        let payload_hash = format!("sha256:{}", blake3::hash(embed_text.as_bytes()).to_hex());
+
+        Ok(json!({
+            "vector": Self::to_f64(&vector),
+            "agent_name": record.agent_name,
+            "topic": record.topic,
+            "project": record.project.clone().unwrap_or_default(),
+            "summary": record.summary,
+            "full_content": record.full_content,
+            "timestamp": record.timestamp.to_rfc3339(),
+            "confidence": record.confidence,
+            "open_questions": record.open_questions,
+            "metadata": metadata_json,
+            "payload_hash": payload_hash,
+            "chunk_id": chunk_id,
+            "artifact_id": artifact_id,
+            "conversation_id": record.conversation_id.clone().unwrap_or_default(),
+        }))
+    }
+
+    /// Parameters for write_perspective_v2; the view hangs off the MemoryEntry node `memory`.
+    fn perspective_payload(memory: &str, view: &PerspectiveView) -> Value {
+        json!({
+            "memory": memory,
+            "role": view.role,
+            "summary": view.summary,
+            "body": view.body,
+            "risks": view.risks.clone().unwrap_or_default(),
+            "decisions": view.decisions.clone().unwrap_or_default(),
+            "actions": view.actions.clone().unwrap_or_default(),
+            "metadata": "{}",
+        })
+    }
+
+    async fn write_perspectives(
+        &self,
+        memory_entry_id: &str,
+        views: &[PerspectiveView],
+    ) -> anyhow::Result<()> {
+        for view in views.iter().filter(|view| !view.role.trim().is_empty()) {
+            let _: Value = self
+                .helix
+                .post_query(
+                    "write_perspective_v2",
+                    &Self::perspective_payload(memory_entry_id, view),
+                )
+                .await
+                .context("HelixQL write_perspective_v2 failed")?;
+        }
+        Ok(())
+    }
+
+    /// Parameters for write_tool_call_v2; the call points at the MemoryEntry node `memory`.
+    fn tool_call_payload(memory: &str, tool_call: &ToolCallRecord) -> Value {
+        json!({
+            "memory": memory,
+            "tool_call_id": tool_call.tool_call_id.clone().unwrap_or_default(),
+            "tool_name": tool_call.tool_name,
+            "args_json": tool_call.args_json.to_string(),
+            "result_summary": tool_call.result_summary.clone().unwrap_or_default(),
+            "created_at": tool_call.created_at.unwrap_or_else(Utc::now).to_rfc3339(),
+            "metadata": tool_call
+                .metadata
+                .as_ref()
+                .map(|m| m.to_string())
+                .unwrap_or_else(|| "{}".to_string()),
+        })
+    }
+
+    async fn write_tool_calls(
+        &self,
+        memory_entry_id: &str,
+        tool_calls: &[ToolCallRecord],
+    ) -> anyhow::Result<()> {
+        for tool_call in tool_calls
+            .iter()
+            .filter(|call| !call.tool_name.trim().is_empty())
+        {
+            let _: Value = self
+                .helix
+                .post_query(
+                    "write_tool_call_v2",
+                    &Self::tool_call_payload(memory_entry_id, tool_call),
+                )
+                .await
+                .context("HelixQL write_tool_call_v2 failed")?;
+        }
+        Ok(())
+    }
+
+    /// Fill in the graph context a vector hit does not carry.
+    async fn attach_context(&self, record: &mut MemoryRecord) -> anyhow::Result<()> {
+        let Some(chunk_id) = record.id.as_deref() else {
+            return Ok(());
+        };
+        let response: MemoryContextV2Response = self
+            .helix
+            .post_query("memory_context_v2", &json!({ "chunk_id": chunk_id }))
+            .await
+            .context("HelixQL memory_context_v2 failed")?;
+        record.perspectives = response
+            .perspectives
+            .into_iter()
+            .map(PerspectiveNode::into_view)
+            .collect();
+        record.tool_calls = response
+            .tool_calls
+            .into_iter()
+            .map(ToolCallNode::into_record)
+            .collect();
+        Ok(())
+    }
+
+    fn thread_node(&self, key: &str) -> Option<String> {
+        self.thread_nodes
+            .lock()
+            .ok()
+            .and_then(|nodes| nodes.get(key).cloned())
+    }
+
+    fn remember_thread_node(&self, key: String, node_id: String) {
+        if let Ok(mut nodes) = self.thread_nodes.lock() {
+            nodes.insert(key, node_id);
+        }
+    }
+
+    /// Persist messages as Conversation/Message nodes, threading replies via Replies_to.
+    async fn write_thread(
+        &self,
+        conversation_id: &str,
+        messages: &[MessageRecord],
+    ) -> anyhow::Result<()> {
+        if messages.is_empty() {
+            return Ok(());
+        }
+
+        let conversation_key = format!("conversation::{conversation_id}");
+        let conversation_node = match self.thread_node(&conversation_key) {
+            Some(node_id) => node_id,
+            None => {
+                let payload = json!({
+                    "conversation_id": conversation_id,
+                    "title": conversation_id,
+                    "metadata": "{}",
+                });
+                let response: WriteConversationV2Response = self
+                    .helix
+                    .post_query("write_conversation_v2", &payload)
+                    .await
+                    .context("HelixQL write_conversation_v2 failed")?;
+                let node_id = response.conversation.id;
+                self.remember_thread_node(conversation_key, node_id.clone());
+                node_id
+            }
+        };
+
+        for message in messages {
+            let Some(message_id) = message.message_id.as_deref() else {
+                continue;
+            };
+            if message.content.trim().is_empty() {
+                continue;
+            }
+
+            let payload = json!({
+                "conversation": conversation_node,
+                "message_id": message_id,
+                "conversation_id": conversation_id,
+                "role": message.role,
+                "content": message.content,
+                "created_at": message.created_at.unwrap_or_else(Utc::now).to_rfc3339(),
+                "reply_to": message.reply_to.clone().unwrap_or_default(),
+                "metadata": message
+                    .metadata
+                    .as_ref()
+                    .map(|m| m.to_string())
+                    .unwrap_or_else(|| "{}".to_string()),
+            });
+            let response: WriteMessageV2Response = self
+                .helix
+                .post_query("write_message_v2", &payload)
+                .await
+                .context("HelixQL write_message_v2 failed")?;
+            let node_id = response.message.id;
+            self.remember_thread_node(format!("message::{message_id}"), node_id.clone());
+
+            let parent = message
+                .reply_to
+                .as_deref()
+                .and_then(|reply_to| self.thread_node(&format!("message::{reply_to}")));
+            if let Some(parent) = parent {
+                let payload = json!({ "message": node_id, "parent": parent });
+                let _: Value = self
+                    .helix
+                    .post_query("link_reply_v2", &payload)
+                    .await
+                    .context("HelixQL link_reply_v2 failed")?;
+            }
+        }
+
+        Ok(())
+    }
+
     async fn enrich_from_neighbors(
         &self,
         record: &mut MemoryRecord,
@@ -1487,3 +1913,99 @@ fn slugify(value: &str) -> String {
         slug
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn perspectives_round_trip_through_helixql_payloads() {
+        let view = PerspectiveView {
+            role: String::from("OpsChainAgent"),
+            summary: String::from("Cost is flat"),
+            body: String::from("Cost is flat after the move."),
+            risks: Some(String::from("egress fees")),
+            ..PerspectiveView::default()
+        };
+        let payload = HelixQueryRagClient::perspective_payload("entry-7", &view);
+        assert_eq!(payload["memory"], "entry-7");
+        assert_eq!(payload["role"], "OpsChainAgent");
+        assert_eq!(payload["risks"], "egress fees");
+        assert_eq!(payload["decisions"], "");
+
+        let response: GetMemoryV2Response = serde_json::from_value(json!({
+            "memory_entry": [{
+                "agent_name": "Router",
+                "topic": "architecture",
+                "project": "",
+                "summary": "Move the queue",
+                "full_content": "Move the queue to the managed service.",
+                "timestamp": "2026-01-02T03:04:05Z",
+                "confidence": 0.7,
+                "metadata": "{\"fanout\":true}",
+                "conversation_id": "",
+            }],
+            "perspectives": [{
+                "role": payload["role"],
+                "summary": payload["summary"],
+                "body": payload["body"],
+                "risks": payload["risks"],
+                "decisions": payload["decisions"],
+                "actions": payload["actions"],
+                "metadata": payload["metadata"],
+            }],
+        }))
+        .unwrap();
+        let record = response.into_record("chunk-1").unwrap();
+        assert_eq!(record.id.as_deref(), Some("chunk-1"));
+        assert_eq!(record.project, None);
+        assert_eq!(record.perspectives.len(), 1);
+        let stored = &record.perspectives[0];
+        assert_eq!(stored.role, "OpsChainAgent");
+        assert_eq!(stored.body, "Cost is flat after the move.");
+        assert_eq!(stored.risks.as_deref(), Some("egress fees"));
+        assert_eq!(stored.decisions, None);
+
+        let missing: GetMemoryV2Response = serde_json::from_value(json!({})).unwrap();
+        assert!(missing.into_record("chunk-2").is_none());
+    }
+
+    #[test]
+    fn tool_calls_round_trip_through_helixql_payloads() {
+        let call = ToolCallRecord {
+            tool_call_id: Some(String::from("call_1")),
+            tool_name: String::from("memory_search"),
+            args_json: json!({ "query": "router", "limit": 3 }),
+            result_summary: Some(String::from("2 memories")),
+            created_at: Some(Utc::now()),
+            metadata: None,
+        };
+        let payload = HelixQueryRagClient::tool_call_payload("entry-7", &call);
+        assert_eq!(payload["memory"], "entry-7");
+        assert_eq!(payload["args_json"], call.args_json.to_string());
+
+        let context: MemoryContextV2Response = serde_json::from_value(json!({
+            "perspectives": [],
+            "tool_calls": [{
+                "tool_call_id": payload["tool_call_id"],
+                "tool_name": payload["tool_name"],
+                "args_json": payload["args_json"],
+                "result_summary": payload["result_summary"],
+                "created_at": payload["created_at"],
+                "metadata": payload["metadata"],
+            }],
+        }))
+        .unwrap();
+        let stored = context
+            .tool_calls
+            .into_iter()
+            .map(ToolCallNode::into_record)
+            .next()
+            .unwrap();
+        assert_eq!(stored.tool_call_id.as_deref(), Some("call_1"));
+        assert_eq!(stored.tool_name, "memory_search");
+        assert_eq!(stored.args_json, call.args_json);
+        assert_eq!(stored.result_summary.as_deref(), Some("2 memories"));
+        assert!(stored.created_at.is_some());
+    }
+}
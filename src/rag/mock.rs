@@ -6,8 +6,8 @@ use anyhow::anyhow;
 use super::client::RagClient;
 use super::config::RagConfig;
 use super::types::{
-    MemoryDeleteRequest, MemoryFilters, MemoryQuery, MemoryRecord, MemoryWriteRequest,
-    MemoryWriteResponse,
+    MemoryDeleteRequest, MemoryFilters, MemoryQuery, MemoryRecord, MemoryUpdateRequest,
+    MemoryWriteRequest, MemoryWriteResponse,
 };
 
 #[derive(Default)]
@@ -78,4 +78,29 @@ impl RagClient for MockRagClient {
         }
         Ok(())
     }
+
+    async fn get(&self, id: &str) -> anyhow::Result<Option<MemoryRecord>> {
+        let records = self
+            .records
+            .lock()
+            .map_err(|_| anyhow!("mock rag client lock poisoned"))?;
+        Ok(records
+            .iter()
+            .find(|r| r.id.as_deref() == Some(id))
+            .cloned())
+    }
+
+    async fn update(&self, request: MemoryUpdateRequest) -> anyhow::Result<MemoryWriteResponse> {
+        let mut records = self
+            .records
+            .lock()
+            .map_err(|_| anyhow!("mock rag client lock poisoned"))?;
+        let id = request.record.id.clone().unwrap_or_default();
+        let slot = records
+            .iter_mut()
+            .find(|r| r.id.as_deref() == Some(id.as_str()))
+            .ok_or_else(|| anyhow!("memory_id not found"))?;
+        *slot = request.record;
+        Ok(MemoryWriteResponse { memory_id: id })
+    }
 }
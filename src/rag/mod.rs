@@ -11,6 +11,7 @@ pub use agent::{build_rag_agent_from_env, SharedRagAgent};
 pub use config::HelixConfig;
 pub use helix::HelixClient;
 pub use types::{
-    MemoryDeleteRequest, MemoryFilters, MemoryQuery, MemoryRecord, MemoryRequest, MemoryResponse,
-    MemoryWriteRequest,
+    MemoryDeleteRequest, MemoryFilters, MemoryGetRequest, MemoryPatch, MemoryPatchRequest,
+    MemoryQuery, MemoryRecord, MemoryRequest, MemoryResponse, MemoryWriteRequest, MessageRecord,
+    PerspectiveView, ToolCallRecord,
 };
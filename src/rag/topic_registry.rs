@@ -1,7 +1,8 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 use anyhow::Context;
+use async_trait::async_trait;
 use serde_json::Value;
 use tokio::sync::Mutex;
 
@@ -10,6 +11,21 @@ use super::helix::HelixClient;
 
 pub type SharedTopicRegistry = Arc<TopicRegistry>;
 
+/// A registered topic; `description` and `parent` come from the node's metadata JSON.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TopicEntry {
+    pub name: String,
+    pub description: String,
+    pub parent: Option<String>,
+}
+
+/// Taxonomy that save-time topic classification reads from and proposes new topics to.
+#[async_trait]
+pub trait TopicStore: Send + Sync {
+    async fn list_topics(&self) -> anyhow::Result<Vec<TopicEntry>>;
+    async fn register_topic(&self, name: &str, metadata: &Value) -> anyhow::Result<String>;
+}
+
 /// Minimal helper to upsert topic/category nodes via the InsertTopic HelixQL query.
 pub struct TopicRegistry {
     client: HelixClient,
@@ -76,4 +92,128 @@ impl TopicRegistry {
         }
         Ok(ids)
     }
+
+    /// Registered topics via ListTopics; see `registered_topics` for what is kept.
+    pub async fn list_topics(&self) -> anyhow::Result<Vec<TopicEntry>> {
+        #[derive(serde::Deserialize)]
+        struct ListTopicsResponse {
+            #[serde(default)]
+            topics: Vec<ListedTopic>,
+        }
+
+        let resp: ListTopicsResponse = self
+            .client
+            .post_query("ListTopics", &serde_json::json!({}))
+            .await
+            .context("ListTopics call failed")?;
+
+        Ok(registered_topics(resp.topics))
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct ListedTopic {
+    name: String,
+    #[serde(default)]
+    metadata: Option<String>,
+}
+
+/// Seeded and registered topics carry a `status` in their metadata (the seed script and
+/// `register_topic` both write it); Topic nodes that memory writes add for their own topic do
+/// not, and are dropped along with `status=skip` sentinels. Duplicate names keep the described
+/// entry.
+fn registered_topics(listed: Vec<ListedTopic>) -> Vec<TopicEntry> {
+    let mut entries: HashMap<String, TopicEntry> = HashMap::new();
+    for topic in listed {
+        let name = topic.name.trim();
+        if name.is_empty() {
+            continue;
+        }
+        let metadata: Value = topic
+            .metadata
+            .as_deref()
+            .and_then(|m| serde_json::from_str(m).ok())
+            .unwrap_or(Value::Null);
+        if metadata.get("status").is_none() {
+            continue;
+        }
+        let field = |key: &str| {
+            metadata
+                .get(key)
+                .and_then(Value::as_str)
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(str::to_string)
+        };
+        if field("status").as_deref() == Some("skip") {
+            continue;
+        }
+        let entry = TopicEntry {
+            name: name.to_string(),
+            description: field("description").unwrap_or_default(),
+            parent: field("parent"),
+        };
+        match entries.get(name) {
+            Some(existing) if !existing.description.is_empty() => {}
+            _ => {
+                entries.insert(name.to_string(), entry);
+            }
+        }
+    }
+
+    let mut topics: Vec<TopicEntry> = entries.into_values().collect();
+    topics.sort_by(|a, b| a.name.cmp(&b.name));
+    topics
+}
+
+#[async_trait]
+impl TopicStore for TopicRegistry {
+    async fn list_topics(&self) -> anyhow::Result<Vec<TopicEntry>> {
+        TopicRegistry::list_topics(self).await
+    }
+
+    async fn register_topic(&self, name: &str, metadata: &Value) -> anyhow::Result<String> {
+        let ids = self
+            .upsert_topics(&[(name.to_string(), metadata.clone())])
+            .await?;
+        Ok(ids.into_iter().next().unwrap_or_else(|| name.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listed(name: &str, metadata: &str) -> ListedTopic {
+        ListedTopic {
+            name: name.to_string(),
+            metadata: Some(metadata.to_string()),
+        }
+    }
+
+    #[test]
+    fn lists_only_seeded_and_registered_topics() {
+        let topics = registered_topics(vec![
+            listed(
+                "hobby.music",
+                r#"{"description": "Instruments", "status": "active", "parent": "hobby"}"#,
+            ),
+            listed(
+                "hobby",
+                r#"{"description": null, "status": null, "parent": null}"#,
+            ),
+            // Added by a memory write: carries the memory's metadata, no status.
+            listed(
+                "hobby.music",
+                r#"{"source": "agent.save", "tags": ["guitar"]}"#,
+            ),
+            listed("groceries", r#"{"source": "agent.save", "body": "milk"}"#),
+            listed("_sentinel", r#"{"status": "skip"}"#),
+        ]);
+
+        let names: Vec<_> = topics.iter().map(|topic| topic.name.as_str()).collect();
+        assert_eq!(names, ["hobby", "hobby.music"]);
+        assert_eq!(topics[1].description, "Instruments");
+        assert_eq!(topics[1].parent.as_deref(), Some("hobby"));
+    }
 }
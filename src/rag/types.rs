@@ -1,6 +1,6 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{Map, Value};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MemoryRecord {
@@ -27,6 +27,23 @@ pub struct MemoryRecord {
     pub metadata: Option<Value>,
 }
 
+impl MemoryRecord {
+    /// Tags attached at save time (`metadata.tags`).
+    pub fn tags(&self) -> Vec<String> {
+        self.metadata
+            .as_ref()
+            .and_then(|m| m.get("tags"))
+            .and_then(Value::as_array)
+            .map(|tags| {
+                tags.iter()
+                    .filter_map(Value::as_str)
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct PerspectiveView {
     pub role: String,
@@ -55,6 +72,74 @@ pub struct MemoryDeleteRequest {
     pub id: String,
 }
 
+/// Look one memory up by the id its write returned.
+#[derive(Debug, Clone)]
+pub struct MemoryGetRequest {
+    pub id: String,
+}
+
+/// Replace a stored memory in place; `record.id` names the memory being replaced.
+#[derive(Debug, Clone)]
+pub struct MemoryUpdateRequest {
+    pub record: MemoryRecord,
+}
+
+/// Edit selected fields of a stored memory.
+#[derive(Debug, Clone)]
+pub struct MemoryPatchRequest {
+    pub id: String,
+    pub patch: MemoryPatch,
+}
+
+/// Field edits for a stored memory; `None` leaves the field as it is.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MemoryPatch {
+    pub body: Option<String>,
+    pub tags: Option<Vec<String>>,
+    pub topic: Option<String>,
+    pub project: Option<String>,
+    pub confidence: Option<f32>,
+}
+
+impl MemoryPatch {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Apply the edits; a new body also refreshes the summary, and the edit time is kept in
+    /// `metadata.updated_at` while the original `timestamp` stays.
+    pub fn apply(&self, mut record: MemoryRecord) -> MemoryRecord {
+        let mut metadata = match record.metadata.take() {
+            Some(Value::Object(map)) => map,
+            _ => Map::new(),
+        };
+        if let Some(body) = &self.body {
+            record.summary = body.chars().take(200).collect();
+            record.full_content = body.clone();
+            // HelixQL hits read the full body back from metadata.
+            metadata.insert("body".to_string(), Value::String(body.clone()));
+        }
+        if let Some(tags) = &self.tags {
+            metadata.insert("tags".to_string(), serde_json::json!(tags));
+        }
+        if let Some(topic) = &self.topic {
+            record.topic = topic.clone();
+        }
+        if let Some(project) = &self.project {
+            record.project = Some(project.clone());
+        }
+        if let Some(confidence) = self.confidence {
+            record.confidence = confidence;
+        }
+        metadata.insert(
+            "updated_at".to_string(),
+            Value::String(Utc::now().to_rfc3339()),
+        );
+        record.metadata = Some(Value::Object(metadata));
+        record
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct MessageRecord {
     #[serde(default)]
@@ -100,15 +185,22 @@ pub struct ToolCallRecord {
     pub metadata: Option<Value>,
 }
 
-#[allow(dead_code)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UsageEvent {
     pub request_id: String,
     pub agent_name: String,
     pub operator_id: Option<String>,
     pub specialist_agent_id: Option<String>,
+    /// Model operation, e.g. `chat`, `chat_stream`, `chat_with_tools`, `embed`.
     pub tool_name: String,
     pub tokens_consumed: u64,
+    #[serde(default)]
+    pub prompt_tokens: u64,
+    #[serde(default)]
+    pub completion_tokens: u64,
+    /// Counts are a local estimate because the backend reported no usage.
+    #[serde(default)]
+    pub estimated: bool,
     pub model: Option<String>,
     pub timestamp: DateTime<Utc>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -142,13 +234,16 @@ pub struct PayoutEvent {
     pub metadata: Option<Value>,
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct MemoryFilters {
     pub agent_name: Option<String>,
     pub topic: Option<String>,
     pub project: Option<String>,
     pub conversation_id: Option<String>,
     pub since: Option<DateTime<Utc>>,
+    /// Every listed tag must be on the record (case-insensitive).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
 }
 
 impl MemoryFilters {
@@ -172,6 +267,17 @@ impl MemoryFilters {
                 .since
                 .as_ref()
                 .is_none_or(|since| record.timestamp >= *since)
+            && self.matches_tags(record)
+    }
+
+    fn matches_tags(&self, record: &MemoryRecord) -> bool {
+        if self.tags.is_empty() {
+            return true;
+        }
+        let tags = record.tags();
+        self.tags
+            .iter()
+            .all(|needle| tags.iter().any(|tag| tag.eq_ignore_ascii_case(needle)))
     }
 }
 
@@ -195,6 +301,10 @@ pub enum MemoryRequest {
     Retrieve(MemoryQuery),
     #[allow(dead_code)]
     Delete(MemoryDeleteRequest),
+    Get(MemoryGetRequest),
+    #[allow(dead_code)]
+    Update(MemoryUpdateRequest),
+    Patch(MemoryPatchRequest),
 }
 
 #[derive(Debug, Clone)]
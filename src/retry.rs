@@ -0,0 +1,281 @@
+use std::env;
+use std::error::Error as StdError;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::time::Duration;
+
+use async_openai::error::OpenAIError;
+use rand::Rng;
+use tracing::warn;
+
+/// Retry/backoff/deadline settings shared by the LLM and embeddings clients.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt (0 disables retrying).
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Deadline applied to each attempt.
+    pub timeout: Duration,
+}
+
+impl RetryPolicy {
+    /// Read `<PREFIX>_HTTP_TIMEOUT_MS`, `<PREFIX>_MAX_RETRIES`, `<PREFIX>_RETRY_BASE_MS` and
+    /// `<PREFIX>_RETRY_MAX_MS` (each with an `AIE_` fallback), using `defaults` for unset values.
+    pub fn from_env(prefix: &str, defaults: RetryPolicy) -> Self {
+        let read = |suffix: &str| -> Option<u64> {
+            [
+                format!("{prefix}_{suffix}"),
+                format!("AIE_{prefix}_{suffix}"),
+            ]
+            .iter()
+            .find_map(|key| env::var(key).ok())
+            .and_then(|value| value.trim().parse::<u64>().ok())
+        };
+
+        Self {
+            max_retries: read("MAX_RETRIES")
+                .map(|v| v as u32)
+                .unwrap_or(defaults.max_retries),
+            base_delay: read("RETRY_BASE_MS")
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.base_delay),
+            max_delay: read("RETRY_MAX_MS")
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.max_delay),
+            timeout: read("HTTP_TIMEOUT_MS")
+                .filter(|ms| *ms > 0)
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.timeout),
+        }
+    }
+
+    /// Defaults for chat completions: generation is slow, so the deadline is generous.
+    pub fn llm_defaults() -> Self {
+        Self {
+            max_retries: 2,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(8),
+            timeout: Duration::from_secs(120),
+        }
+    }
+
+    /// Defaults for embedding calls: short payloads, short deadline.
+    pub fn embedding_defaults() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(250),
+            max_delay: Duration::from_secs(4),
+            timeout: Duration::from_secs(15),
+        }
+    }
+
+    /// Run `op` under the per-attempt deadline, retrying retryable failures with backoff.
+    pub async fn run<T, F, Fut>(&self, label: &str, mut op: F) -> anyhow::Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = anyhow::Result<T>>,
+    {
+        let mut attempt = 0u32;
+        loop {
+            let result = match tokio::time::timeout(self.timeout, op()).await {
+                Ok(result) => result,
+                Err(_) => Err(anyhow::Error::new(DeadlineExceeded(self.timeout))),
+            };
+
+            let err = match result {
+                Ok(value) => return Ok(value),
+                Err(err) => err,
+            };
+
+            if attempt >= self.max_retries || !is_retryable(&err) {
+                return Err(err.context(format!("{label} failed after {} attempt(s)", attempt + 1)));
+            }
+
+            let delay = self.backoff(attempt);
+            warn!(
+                %label,
+                attempt = attempt + 1,
+                delay_ms = delay.as_millis() as u64,
+                error = %err,
+                "Retryable failure; backing off"
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Exponential backoff with full jitter: uniform in `[0, min(max, base * 2^attempt)]`.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let ceiling = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        let millis = ceiling.as_millis() as u64;
+        if millis == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_millis(rand::thread_rng().gen_range(0..=millis))
+    }
+}
+
+/// An attempt ran past the policy deadline.
+#[derive(Debug)]
+pub struct DeadlineExceeded(pub Duration);
+
+impl fmt::Display for DeadlineExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "call exceeded {}ms deadline", self.0.as_millis())
+    }
+}
+
+impl StdError for DeadlineExceeded {}
+
+/// Transient failures worth another attempt: deadlines, connection drops, 429 and 5xx.
+pub fn is_retryable(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        if cause.is::<DeadlineExceeded>() {
+            return true;
+        }
+        if let Some(err) = cause.downcast_ref::<reqwest::Error>() {
+            return is_retryable_reqwest(err);
+        }
+        if let Some(err) = cause.downcast_ref::<io::Error>() {
+            return matches!(
+                err.kind(),
+                io::ErrorKind::ConnectionReset
+                    | io::ErrorKind::ConnectionAborted
+                    | io::ErrorKind::ConnectionRefused
+                    | io::ErrorKind::BrokenPipe
+                    | io::ErrorKind::TimedOut
+                    | io::ErrorKind::UnexpectedEof
+            );
+        }
+        if let Some(err) = cause.downcast_ref::<OpenAIError>() {
+            return is_retryable_openai(err);
+        }
+        false
+    })
+}
+
+fn is_retryable_reqwest(err: &reqwest::Error) -> bool {
+    if err.is_timeout() || err.is_connect() {
+        return true;
+    }
+    err.status()
+        .is_some_and(|status| status.as_u16() == 429 || status.is_server_error())
+}
+
+fn is_retryable_openai(err: &OpenAIError) -> bool {
+    match err {
+        // async-openai links its own reqwest major, so this mirrors `is_retryable_reqwest`.
+        OpenAIError::Reqwest(err) => {
+            err.is_timeout()
+                || err.is_connect()
+                || err
+                    .status()
+                    .is_some_and(|status| status.as_u16() == 429 || status.is_server_error())
+        }
+        // async-openai drops the HTTP status, so classify on the error type/code text.
+        OpenAIError::ApiError(api) => {
+            let marker = format!(
+                "{} {} {}",
+                api.r#type.as_deref().unwrap_or_default(),
+                api.code.as_deref().unwrap_or_default(),
+                api.message
+            )
+            .to_lowercase();
+            [
+                "rate_limit",
+                "rate limit",
+                "server_error",
+                "overloaded",
+                "timeout",
+                "unavailable",
+            ]
+            .iter()
+            .any(|needle| marker.contains(needle))
+        }
+        OpenAIError::StreamError(_) => true,
+        _ => false,
+    }
+}
+
+/// Backoff config that hands async-openai's built-in 429 retry over to `RetryPolicy`.
+pub fn disabled_openai_backoff() -> backoff::ExponentialBackoff {
+    backoff::ExponentialBackoff {
+        max_elapsed_time: Some(Duration::ZERO),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+
+    fn fast_policy() -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 2,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(2),
+            timeout: Duration::from_millis(50),
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_transient_errors_but_not_permanent_ones() {
+        let calls = AtomicU32::new(0);
+        let value = fast_policy()
+            .run("flaky", || async {
+                if calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                    Err(io::Error::from(io::ErrorKind::ConnectionReset).into())
+                } else {
+                    Ok(7)
+                }
+            })
+            .await
+            .unwrap();
+        assert_eq!(value, 7);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        let calls = AtomicU32::new(0);
+        let err = fast_policy()
+            .run("bad-request", || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err::<(), _>(anyhow::Error::new(OpenAIError::InvalidArgument(
+                    "bad".into(),
+                )))
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(err
+            .to_string()
+            .contains("bad-request failed after 1 attempt"));
+    }
+
+    #[test]
+    fn undecodable_payloads_are_not_retried() {
+        let payload = serde_json::from_str::<serde_json::Value>("<html>").unwrap_err();
+        let err = anyhow::Error::new(OpenAIError::JSONDeserialize(payload));
+        assert!(!is_retryable(&err));
+    }
+
+    #[tokio::test]
+    async fn deadline_counts_as_retryable() {
+        let calls = AtomicU32::new(0);
+        let err = fast_policy()
+            .run("slow", || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(200)).await;
+                Ok(())
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert!(is_retryable(&err));
+    }
+}
@@ -0,0 +1,355 @@
+use std::env;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
+
+use anyhow::{bail, Context};
+use async_trait::async_trait;
+use chrono::Utc;
+use serde_json::json;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{mpsc, oneshot, Mutex};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+use crate::llm_client::ChatMessage;
+use crate::rag::types::UsageEvent;
+use crate::rag::{HelixClient, HelixConfig};
+
+const DEFAULT_USAGE_LOG: &str = "logs/usage.jsonl";
+
+/// Token counts for one model call.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenUsage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// True when the backend reported nothing and the counts come from `estimate_tokens`.
+    pub estimated: bool,
+}
+
+impl TokenUsage {
+    pub fn reported(prompt_tokens: u32, completion_tokens: u32) -> Self {
+        Self {
+            prompt_tokens: prompt_tokens as u64,
+            completion_tokens: completion_tokens as u64,
+            estimated: false,
+        }
+    }
+
+    pub fn estimated(messages: &[ChatMessage], completion: &str) -> Self {
+        Self {
+            prompt_tokens: estimate_message_tokens(messages),
+            completion_tokens: estimate_tokens(completion),
+            estimated: true,
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+}
+
+/// Rough BPE-style estimate: about four characters per token for English and code.
+pub fn estimate_tokens(text: &str) -> u64 {
+    (text.chars().count() as u64).div_ceil(4)
+}
+
+/// Estimate for a chat transcript, including the few tokens of per-message framing.
+pub fn estimate_message_tokens(messages: &[ChatMessage]) -> u64 {
+    const PER_MESSAGE_OVERHEAD: u64 = 4;
+    messages
+        .iter()
+        .map(|message| {
+            let tool_args: u64 = message
+                .tool_calls
+                .iter()
+                .map(|call| estimate_tokens(&call.name) + estimate_tokens(&call.arguments))
+                .sum();
+            PER_MESSAGE_OVERHEAD + estimate_tokens(&message.content) + tool_args
+        })
+        .sum()
+}
+
+/// Who a model call is billed to. Set by the router around each dispatched request.
+#[derive(Debug, Clone)]
+pub struct UsageScope {
+    pub request_id: String,
+    pub agent_name: String,
+}
+
+tokio::task_local! {
+    static USAGE_SCOPE: UsageScope;
+}
+
+impl UsageScope {
+    pub fn new(request_id: impl Into<String>, agent_name: impl Into<String>) -> Self {
+        Self {
+            request_id: request_id.into(),
+            agent_name: agent_name.into(),
+        }
+    }
+
+    /// Attribute every model call made while `fut` runs to this scope.
+    pub async fn run<F: Future>(self, fut: F) -> F::Output {
+        USAGE_SCOPE.scope(self, fut).await
+    }
+
+    pub fn current() -> Option<Self> {
+        USAGE_SCOPE.try_with(Clone::clone).ok()
+    }
+}
+
+/// Destination for `UsageEvent`s.
+#[async_trait]
+pub trait UsageSink: Send + Sync {
+    async fn record(&self, event: &UsageEvent) -> anyhow::Result<()>;
+}
+
+/// Appends one JSON object per line; the default sink.
+pub struct JsonlUsageSink {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl JsonlUsageSink {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            lock: Mutex::new(()),
+        }
+    }
+}
+
+#[async_trait]
+impl UsageSink for JsonlUsageSink {
+    async fn record(&self, event: &UsageEvent) -> anyhow::Result<()> {
+        let mut line = serde_json::to_string(event)?;
+        line.push('\n');
+
+        let _guard = self.lock.lock().await;
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .with_context(|| format!("Failed to open usage log {}", self.path.display()))?;
+        file.write_all(line.as_bytes()).await?;
+        // tokio finishes file writes in the background; wait so the line is on disk.
+        file.flush().await?;
+        Ok(())
+    }
+}
+
+/// Writes each event as a `UsageEvent` node through the `write_usage_event_v1` query.
+pub struct HelixUsageSink {
+    helix: HelixClient,
+}
+
+impl HelixUsageSink {
+    pub fn new(helix: HelixClient) -> Self {
+        Self { helix }
+    }
+}
+
+#[async_trait]
+impl UsageSink for HelixUsageSink {
+    async fn record(&self, event: &UsageEvent) -> anyhow::Result<()> {
+        self.helix.write_usage_event(event).await
+    }
+}
+
+enum UsageMessage {
+    Event(Box<UsageEvent>),
+    /// Answered once every event queued before it has been written.
+    Flush(oneshot::Sender<()>),
+}
+
+/// Queues events for a background task that writes them to a sink, so model calls never wait
+/// on the usage log. Events are written in the order they were recorded.
+pub struct UsageRecorder {
+    queue: mpsc::UnboundedSender<UsageMessage>,
+}
+
+impl UsageRecorder {
+    /// Spawn the writer task on the current runtime. It exits once the recorder is dropped and
+    /// the queue is drained.
+    pub fn spawn(sink: Arc<dyn UsageSink>) -> (Self, JoinHandle<()>) {
+        let (queue, mut messages) = mpsc::unbounded_channel();
+        let writer = tokio::spawn(async move {
+            while let Some(message) = messages.recv().await {
+                match message {
+                    UsageMessage::Event(event) => {
+                        if let Err(err) = sink.record(&event).await {
+                            warn!(?err, event = %json!(event), "Failed to record usage event");
+                        }
+                    }
+                    UsageMessage::Flush(done) => {
+                        let _ = done.send(());
+                    }
+                }
+            }
+        });
+        (Self { queue }, writer)
+    }
+
+    pub fn send(&self, event: UsageEvent) {
+        if self
+            .queue
+            .send(UsageMessage::Event(Box::new(event)))
+            .is_err()
+        {
+            warn!("Usage writer has stopped; dropping usage event");
+        }
+    }
+
+    /// Wait until every event sent so far has been handed to the sink.
+    pub async fn flush(&self) {
+        let (done, written) = oneshot::channel();
+        if self.queue.send(UsageMessage::Flush(done)).is_ok() {
+            let _ = written.await;
+        }
+    }
+}
+
+static RECORDER: OnceLock<UsageRecorder> = OnceLock::new();
+
+/// Install the process-wide sink and start its writer task. Later calls are ignored.
+pub fn install(sink: Arc<dyn UsageSink>) {
+    if RECORDER.get().is_some() {
+        debug!("Usage sink already installed; keeping the first one");
+        return;
+    }
+    let (recorder, _writer) = UsageRecorder::spawn(sink);
+    if RECORDER.set(recorder).is_err() {
+        debug!("Usage sink already installed; keeping the first one");
+    }
+}
+
+/// Wait for queued usage events to be written; call before the process exits.
+pub async fn flush() {
+    if let Some(recorder) = RECORDER.get() {
+        recorder.flush().await;
+    }
+}
+
+/// Build the sink selected by `VK_CORTEX_USAGE_SINK` (`jsonl` default, `helix`, or `off`).
+pub fn sink_from_env() -> anyhow::Result<Option<Arc<dyn UsageSink>>> {
+    let kind = env::var("VK_CORTEX_USAGE_SINK").unwrap_or_else(|_| "jsonl".to_string());
+    match kind.trim().to_ascii_lowercase().as_str() {
+        "off" | "none" | "" => Ok(None),
+        "jsonl" => {
+            let path =
+                env::var("VK_CORTEX_USAGE_LOG").unwrap_or_else(|_| DEFAULT_USAGE_LOG.to_string());
+            info!(%path, "Recording token usage to JSONL");
+            Ok(Some(Arc::new(JsonlUsageSink::new(path))))
+        }
+        "helix" => {
+            let config = HelixConfig::from_env().context("Helix usage sink needs HELIX_* env")?;
+            let client = HelixClient::new(config).context("Failed to build Helix usage sink")?;
+            info!("Recording token usage to Helix");
+            Ok(Some(Arc::new(HelixUsageSink::new(client))))
+        }
+        other => bail!("Unknown VK_CORTEX_USAGE_SINK '{other}' (use jsonl, helix, or off)"),
+    }
+}
+
+/// Queue a `UsageEvent` for one model call, attributed to the current `UsageScope`.
+pub fn record(model: &str, operation: &str, usage: TokenUsage) {
+    record_in(UsageScope::current(), model, operation, usage)
+}
+
+/// Like `record`, for calls that finish outside the scope they started in (e.g. streams).
+pub fn record_in(scope: Option<UsageScope>, model: &str, operation: &str, usage: TokenUsage) {
+    let Some(recorder) = RECORDER.get() else {
+        return;
+    };
+
+    let (request_id, agent_name) = match scope {
+        Some(scope) => (scope.request_id, scope.agent_name),
+        None => (String::from("unscoped"), String::from("unscoped")),
+    };
+    let event = UsageEvent {
+        request_id,
+        agent_name,
+        operator_id: env::var("VK_CORTEX_OPERATOR_ID").ok(),
+        specialist_agent_id: None,
+        tool_name: operation.to_string(),
+        tokens_consumed: usage.total(),
+        prompt_tokens: usage.prompt_tokens,
+        completion_tokens: usage.completion_tokens,
+        estimated: usage.estimated,
+        model: Some(model.to_string()),
+        timestamp: Utc::now(),
+        metadata: None,
+    };
+
+    recorder.send(event);
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn scope_is_visible_inside_and_absent_outside() {
+        assert!(UsageScope::current().is_none());
+        let agent = UsageScope::new("conv-1-msg-1", "CTOAgent")
+            .run(async { UsageScope::current().map(|scope| scope.agent_name) })
+            .await;
+        assert_eq!(agent.as_deref(), Some("CTOAgent"));
+
+        let usage = TokenUsage::estimated(&[ChatMessage::user("12345678")], "abcd");
+        assert_eq!((usage.prompt_tokens, usage.completion_tokens), (6, 1));
+    }
+
+    #[tokio::test]
+    async fn recorder_writes_events_in_the_background() {
+        let path = env::temp_dir().join(format!(
+            "vk-usage-{}-{}.jsonl",
+            std::process::id(),
+            Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        ));
+        let (recorder, writer) = UsageRecorder::spawn(Arc::new(JsonlUsageSink::new(&path)));
+        let event = |request_id: &str, tokens: u64| UsageEvent {
+            request_id: request_id.to_string(),
+            agent_name: String::from("CTOAgent"),
+            operator_id: None,
+            specialist_agent_id: None,
+            tool_name: String::from("chat"),
+            tokens_consumed: tokens,
+            prompt_tokens: tokens - 1,
+            completion_tokens: 1,
+            estimated: false,
+            model: Some(String::from("gpt-4o-mini")),
+            timestamp: Utc::now(),
+            metadata: None,
+        };
+        recorder.send(event("msg-1", 12));
+        recorder.flush().await;
+        let written = std::fs::read_to_string(&path).unwrap();
+        let first: Value = serde_json::from_str(written.lines().next().unwrap()).unwrap();
+        assert_eq!(first["request_id"], "msg-1");
+        assert_eq!(first["agent_name"], "CTOAgent");
+        assert_eq!(first["tokens_consumed"], 12);
+        assert_eq!(first["prompt_tokens"], 11);
+        assert_eq!(first["model"], "gpt-4o-mini");
+
+        // Dropping the recorder lets the writer drain the queue and stop.
+        recorder.send(event("msg-2", 5));
+        drop(recorder);
+        writer.await.unwrap();
+        let written = std::fs::read_to_string(&path).unwrap();
+        let ids = written
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap()["request_id"].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec!["msg-1", "msg-2"]);
+
+        let _ = std::fs::remove_file(&path);
+    }
+}